@@ -6,6 +6,7 @@ import {IEntryPoint} from "@account-abstraction/contracts/interfaces/IEntryPoint
 import {PackedUserOperation} from "@account-abstraction/contracts/interfaces/PackedUserOperation.sol";
 import {UserOperationLib} from "@account-abstraction/contracts/core/UserOperationLib.sol";
 import {IPBHEntryPoint} from "./interfaces/IPBHEntryPoint.sol";
+import {IMulticall3} from "./interfaces/IMulticall3.sol";
 import {ByteHasher} from "./lib/ByteHasher.sol";
 import {PBHExternalNullifier} from "./lib/PBHExternalNullifier.sol";
 import {ReentrancyGuardTransient} from "@openzeppelin/contracts/utils/ReentrancyGuardTransient.sol";
@@ -77,6 +78,13 @@ contract PBHEntryPointImplV1 is IPBHEntryPoint, Base, ReentrancyGuardTransient {
     /// @param payload The zero-knowledge proof that demonstrates the claimer is registered with World ID.
     event PBH(address indexed sender, bytes32 indexed userOpHash, PBHPayload payload);
 
+    /// @notice Emitted when a PBH multicall is executed.
+    ///
+    /// @param sender The sender of the multicall.
+    /// @param signalHash The signal hash the PBH payload was verified against.
+    /// @param payload The zero-knowledge proof that demonstrates the claimer is registered with World ID.
+    event PBHMulticall(address indexed sender, uint256 indexed signalHash, PBHPayload payload);
+
     /// @notice Emitted when the World ID address is set.
     ///
     /// @param worldId The World ID instance that will be used for verifying proofs.
@@ -131,6 +139,12 @@ contract PBHEntryPointImplV1 is IPBHEntryPoint, Base, ReentrancyGuardTransient {
     /// @notice Thrown when the gas limit for a PBH multicall transaction is exceeded
     error GasLimitExceeded(uint256 gasLeft, uint256 gasLimit);
 
+    /// @notice Thrown when a call of a PBH multicall which does not allow failure reverts
+    error MulticallFailed(uint256 index, bytes returnData);
+
+    /// @notice Thrown when a call of a PBH multicall targets the PBH Entry Point or the EIP-4337 Entry Point
+    error InvalidMulticallTarget(uint256 index, address target);
+
     /// @notice Thrown when setting the gas limit for a PBH multicall to 0
     error InvalidPBHGasLimit(uint256 gasLimit);
 
@@ -277,10 +291,7 @@ contract PBHEntryPointImplV1 is IPBHEntryPoint, Base, ReentrancyGuardTransient {
             );
             for (uint256 j = 0; j < pbhPayloads.length; ++j) {
                 address sender = opsPerAggregator[i].userOps[j].sender;
-                // We now generate the signal hash from the sender, nonce, and calldata
-                uint256 signalHash = abi.encodePacked(
-                        sender, opsPerAggregator[i].userOps[j].nonce, opsPerAggregator[i].userOps[j].callData
-                    ).hashToField();
+                uint256 signalHash = getSignalHash(opsPerAggregator[i].userOps[j]);
 
                 _verifyPbh(signalHash, pbhPayloads[j]);
                 bytes32 userOpHash = getUserOpHash(opsPerAggregator[i].userOps[j]);
@@ -291,6 +302,48 @@ contract PBHEntryPointImplV1 is IPBHEntryPoint, Base, ReentrancyGuardTransient {
         entryPoint.handleAggregatedOps(opsPerAggregator, beneficiary);
     }
 
+    /// @notice Executes a batch of calls with priority blockspace for a World ID verified EOA.
+    /// @dev The calls are executed by the PBH Entry Point. The signal hash binds the PBH payload to
+    ///      `msg.sender` and the calls. As for bundles, the nullifier hash is spent by the builder.
+    ///
+    ///      Trust model: any World ID verified caller can make the PBH Entry Point call arbitrary
+    ///      contracts, so the PBH Entry Point must never hold assets, approvals or privileges on
+    ///      other contracts. Calls to the PBH Entry Point itself and to the EIP-4337 Entry Point,
+    ///      where it may hold a deposit, are rejected.
+    /// @param calls The calls to execute.
+    /// @param pbhPayload The PBH payload containing the proof data.
+    /// @return returnData The results of the calls.
+    function pbhMulticall(IMulticall3.Call3[] calldata calls, PBHPayload calldata pbhPayload)
+        external
+        virtual
+        onlyProxy
+        nonReentrant
+        returns (IMulticall3.Result[] memory returnData)
+    {
+        if (gasleft() > pbhGasLimit) {
+            revert GasLimitExceeded(gasleft(), pbhGasLimit);
+        }
+
+        uint256 signalHash = abi.encode(msg.sender, calls).hashToField();
+        _verifyPbh(signalHash, pbhPayload);
+
+        returnData = new IMulticall3.Result[](calls.length);
+        for (uint256 i = 0; i < calls.length; ++i) {
+            address target = calls[i].target;
+            if (target == address(this) || target == address(entryPoint)) {
+                revert InvalidMulticallTarget(i, target);
+            }
+
+            (bool success, bytes memory ret) = target.call(calls[i].callData);
+            if (!success && !calls[i].allowFailure) {
+                revert MulticallFailed(i, ret);
+            }
+            returnData[i] = IMulticall3.Result({success: success, returnData: ret});
+        }
+
+        emit PBHMulticall(msg.sender, signalHash, pbhPayload);
+    }
+
     /// @notice Validates the hashed operations is the same as the hash transiently stored.
     /// @param hashedOps The hashed operations to validate.
     function validateSignaturesCallback(bytes32 hashedOps) external view virtual onlyProxy {
@@ -360,6 +413,14 @@ contract PBHEntryPointImplV1 is IPBHEntryPoint, Base, ReentrancyGuardTransient {
         emit NullifierHashesSpent(msg.sender, _nullifierHashes);
     }
 
+    /// @notice Returns the signal hash the PBH payload of the UserOperation must be generated for.
+    /// @dev Version 1 of the signal derivation, hashing the sender, nonce, and calldata. Off-chain
+    ///      validators derive the same signal, so any change here must bump the version.
+    /// @param userOp The UserOperation to derive the signal hash for.
+    function getSignalHash(PackedUserOperation calldata userOp) public pure virtual returns (uint256) {
+        return abi.encodePacked(userOp.sender, userOp.nonce, userOp.callData).hashToField();
+    }
+
     /// @notice Returns a hash of the UserOperation.
     /// @param userOp The UserOperation to hash.
     function getUserOpHash(PackedUserOperation calldata userOp) public view virtual returns (bytes32 hash) {
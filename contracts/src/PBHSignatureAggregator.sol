@@ -6,7 +6,6 @@ import {IAggregator} from "@account-abstraction/contracts/interfaces/IAggregator
 import {ISafe} from "@4337/interfaces/Safe.sol";
 import {IWorldID} from "@world-id-contracts/interfaces/IWorldID.sol";
 import {IPBHEntryPoint} from "./interfaces/IPBHEntryPoint.sol";
-import {ByteHasher} from "./lib/ByteHasher.sol";
 import {SafeModuleSignatures} from "./lib/SafeModuleSignatures.sol";
 
 /// @title PBH Signature Aggregator
@@ -17,7 +16,6 @@ import {SafeModuleSignatures} from "./lib/SafeModuleSignatures.sol";
 ///         will be considered as Priority User Operations, and will need to pack a World ID proof in the signature field.
 /// @custom:security-contact security@toolsforhumanity.com
 contract PBHSignatureAggregator is IAggregator {
-    using ByteHasher for bytes;
 
     ///////////////////////////////////////////////////////////////////////////////
     ///                             STATE VARIABLES                             ///
@@ -76,8 +74,7 @@ contract PBHSignatureAggregator is IAggregator {
             SafeModuleSignatures.extractProof(userOp.signature, ISafe(payable(userOp.sender)).getThreshold());
         IPBHEntryPoint.PBHPayload memory pbhPayload = abi.decode(proofData, (IPBHEntryPoint.PBHPayload));
 
-        // We now generate the signal hash from the sender, nonce, and calldata
-        uint256 signalHash = abi.encodePacked(userOp.sender, userOp.nonce, userOp.callData).hashToField();
+        uint256 signalHash = pbhEntryPoint.getSignalHash(userOp);
 
         pbhEntryPoint.verifyPbh(signalHash, pbhPayload);
 
@@ -23,6 +23,9 @@ interface IPBHEntryPoint {
         IEntryPoint.UserOpsPerAggregator[] calldata opsPerAggregator,
         address payable beneficiary
     ) external;
+    function pbhMulticall(IMulticall3.Call3[] calldata calls, PBHPayload calldata pbhPayload)
+        external
+        returns (IMulticall3.Result[] memory returnData);
     function initialize(
         IWorldID worldId,
         IEntryPoint entryPoint,
@@ -45,6 +48,7 @@ interface IPBHEntryPoint {
     function addBuilder(address builder) external;
     function removeBuilder(address builder) external;
     function getUserOpHash(PackedUserOperation calldata userOp) external view returns (bytes32);
+    function getSignalHash(PackedUserOperation calldata userOp) external view returns (uint256);
     function getFirstUnspentNullifierHash(uint256[] calldata hashes) external view returns (int256);
     function getUnspentNullifierHashes(uint256[] calldata hashes) external view returns (uint256[] memory);
 }
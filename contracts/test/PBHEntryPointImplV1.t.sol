@@ -190,6 +190,73 @@ contract PBHEntryPointImplV1Test is TestSetup {
         pbhEntryPoint.handleAggregatedOps(userOpsPerAggregator, payable(address(this)));
     }
 
+    function test_pbhMulticall(address sender) public {
+        worldIDGroups.setVerifyProofSuccess(true);
+        IPBHEntryPoint.PBHPayload memory payload = TestUtils.mockPBHPayload(0, 0, TestUtils.getPBHExternalNullifier(0));
+
+        IMulticall3.Call3[] memory calls = new IMulticall3.Call3[](1);
+        calls[0] = IMulticall3.Call3({
+            target: address(worldIDGroups),
+            allowFailure: false,
+            callData: abi.encodeCall(MockWorldIDGroups.verifyProofSuccess, ())
+        });
+        uint256 signalHash = abi.encode(sender, calls).hashToField();
+
+        vm.expectEmit(true, true, true, true);
+        emit PBHEntryPointImplV1.PBHMulticall(sender, signalHash, payload);
+        vm.prank(sender);
+        IMulticall3.Result[] memory returnData = pbhEntryPoint.pbhMulticall{gas: MAX_PBH_GAS_LIMIT}(calls, payload);
+
+        assertTrue(returnData[0].success);
+        assertTrue(abi.decode(returnData[0].returnData, (bool)));
+    }
+
+    function test_pbhMulticall_RevertIf_GasLimitExceeded() public {
+        IPBHEntryPoint.PBHPayload memory payload = TestUtils.mockPBHPayload(0, 0, TestUtils.getPBHExternalNullifier(0));
+        IMulticall3.Call3[] memory calls = new IMulticall3.Call3[](0);
+
+        // The gas left at the time of the check is not known in advance
+        vm.expectRevert();
+        pbhEntryPoint.pbhMulticall{gas: MAX_PBH_GAS_LIMIT + 100000}(calls, payload);
+    }
+
+    function test_pbhMulticall_RevertIf_MulticallFailed() public {
+        worldIDGroups.setVerifyProofSuccess(true);
+        IPBHEntryPoint.PBHPayload memory payload = TestUtils.mockPBHPayload(0, 0, TestUtils.getPBHExternalNullifier(0));
+
+        IMulticall3.Call3[] memory calls = new IMulticall3.Call3[](1);
+        calls[0] = IMulticall3.Call3({target: address(worldIDGroups), allowFailure: false, callData: hex"deadbeef"});
+
+        vm.expectRevert(abi.encodeWithSelector(PBHEntryPointImplV1.MulticallFailed.selector, 0, ""));
+        pbhEntryPoint.pbhMulticall{gas: MAX_PBH_GAS_LIMIT}(calls, payload);
+    }
+
+    function test_pbhMulticall_RevertIf_InvalidMulticallTarget() public {
+        worldIDGroups.setVerifyProofSuccess(true);
+        IPBHEntryPoint.PBHPayload memory payload = TestUtils.mockPBHPayload(0, 0, TestUtils.getPBHExternalNullifier(0));
+
+        // Calls would otherwise be executed with the privileges of the PBH Entry Point
+        address[2] memory targets = [address(pbhEntryPoint), address(entryPoint)];
+        for (uint256 i = 0; i < targets.length; ++i) {
+            IMulticall3.Call3[] memory calls = new IMulticall3.Call3[](2);
+            calls[0] = IMulticall3.Call3({
+                target: address(worldIDGroups),
+                allowFailure: false,
+                callData: abi.encodeCall(MockWorldIDGroups.verifyProofSuccess, ())
+            });
+            calls[1] = IMulticall3.Call3({
+                target: targets[i],
+                allowFailure: true,
+                callData: abi.encodeCall(IPBHEntryPoint.setNumPbhPerMonth, (1))
+            });
+
+            vm.expectRevert(
+                abi.encodeWithSelector(PBHEntryPointImplV1.InvalidMulticallTarget.selector, 1, targets[i])
+            );
+            pbhEntryPoint.pbhMulticall{gas: MAX_PBH_GAS_LIMIT}(calls, payload);
+        }
+    }
+
     function test_validateSignaturesCallback_RevertIf_IncorrectHashedOps() public {
         bytes32 hashedOps = 0x0000000000000000000000000000000000000000000000000000000000000001;
         vm.expectRevert(PBHEntryPointImplV1.InvalidHashedOps.selector);
@@ -294,6 +361,32 @@ contract PBHEntryPointImplV1Test is TestSetup {
         assertEq(userOpHash, expectedHash, "UserOp hash does not match expected hash");
     }
 
+    function test_getSignalHash(PackedUserOperation memory userOp) public view {
+        uint256 signalHash = pbhEntryPoint.getSignalHash(userOp);
+        uint256 expectedHash = abi.encodePacked(userOp.sender, userOp.nonce, userOp.callData).hashToField();
+        assertEq(signalHash, expectedHash, "Signal hash does not match expected hash");
+    }
+
+    /// @dev The expected signal hash is shared with `v1_matches_entrypoint` in the world-chain-pool crate.
+    function test_getSignalHash_MatchesPool() public view {
+        PackedUserOperation memory userOp = PackedUserOperation({
+            sender: 0x1111111111111111111111111111111111111111,
+            nonce: 7,
+            initCode: hex"",
+            callData: hex"deadbeef",
+            accountGasLimits: 0x000000000000000000000000000fffd30000000000000000000000000000C350,
+            preVerificationGas: 500836,
+            gasFees: 0x0000000000000000000000003B9ACA0000000000000000000000000073140B60,
+            paymasterAndData: hex"",
+            signature: hex""
+        });
+        assertEq(
+            pbhEntryPoint.getSignalHash(userOp),
+            0x3f6ba085d299f560037f96450f7792a12fffe0856c11a2ebd9be4ca9f8711c,
+            "Signal hash does not match the pool"
+        );
+    }
+
     function test_getFirstUnspentNullifierHash_Returns_CorrectIndex() public {
         vm.prank(BLOCK_BUILDER);
 
@@ -31,6 +31,7 @@ use tokio::{
 };
 use tracing::{debug, error, info};
 use world_chain_pbh::{
+    abi::PBHPayload as PBHPayloadSolidity,
     date_marker::DateMarker,
     external_nullifier::{EncodedExternalNullifier, ExternalNullifier},
     payload::PBHPayload,
@@ -57,7 +58,6 @@ use super::{
     identities::SerializableIdentity, BundleArgs, SendAAArgs, SendArgs, SendInvalidProofPBHArgs,
     StakeAAArgs, TxType,
 };
-use world_chain_test::bindings::IPBHEntryPoint::PBHPayload as PBHPayloadSolidity;
 
 static SEMAPHORE: Lazy<Arc<Semaphore>> = Lazy::new(|| Arc::new(Semaphore::const_new(150)));
 
@@ -232,16 +232,7 @@ pub async fn send_user_operations(
                 external_nullifier_hash,
                 signal_hash,
             )?;
-            let proof = world_chain_pbh::payload::Proof(semaphore_proof);
-            let p0 = proof.0 .0 .0;
-            let p1 = proof.0 .0 .1;
-            let p2 = proof.0 .1 .0[0];
-            let p3 = proof.0 .1 .0[1];
-            let p4 = proof.0 .1 .1[0];
-            let p5 = proof.0 .1 .1[1];
-            let p6 = proof.0 .2 .0;
-            let p7 = proof.0 .2 .1;
-            let proof = [p0, p1, p2, p3, p4, p5, p6, p7];
+            let proof = semaphore_proof.flatten();
             let input = [root, nullifier_hash, signal_hash, external_nullifier_hash];
 
             let calldata: Bytes = SemaphoreVerifier::verifyProofCall { proof, input }
@@ -353,7 +344,7 @@ pub async fn bundle_pbh_transactions(
 
             let call = IMulticall3::Call3::default();
             let calls = vec![call];
-            let signal_hash = hash_to_field(&SolValue::abi_encode_packed(&(sender, calls.clone())));
+            let signal_hash = hash_to_field(&SolValue::abi_encode_params(&(sender, calls.clone())));
 
             let root = proof.root;
 
@@ -371,7 +362,7 @@ pub async fn bundle_pbh_transactions(
                 root,
                 nullifier_hash,
                 external_nullifier,
-                proof: world_chain_pbh::payload::Proof(semaphore_proof),
+                proof: world_chain_pbh::payload::Proof::Full(semaphore_proof),
             };
 
             let calldata = IPBHEntryPoint::pbhMulticallCall {
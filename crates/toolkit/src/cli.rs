@@ -1,3 +1,4 @@
+use alloy_primitives::Address;
 use bytes::Bytes;
 use chrono::NaiveDate;
 use clap::Parser;
@@ -29,6 +30,12 @@ pub enum Cmd {
     /// For the inclusion proof you can fetch it dynamically from the (staging) sequencer API via `--inclusion-proof-url https://signup-orb-ethereum.stage-crypto.worldcoin.dev/inclusionProof`
     /// or `export INCLUSION_PROOF_URL=https://signup-orb-ethereum.stage-crypto.worldcoin.dev/inclusionProof` env var
     Prove(ProveArgs),
+    /// Proves a batch of calls and returns the hex encoded calldata of a `pbhMulticall` on the PBHEntryPoint
+    ///
+    /// The calldata must be sent to the PBHEntryPoint by the sender it was proven for, e.g. with `cast send`
+    ///
+    /// The identity and inclusion proof are provided in the same way as for the `prove` command
+    Multicall(MulticallArgs),
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -64,6 +71,52 @@ pub struct ProveArgs {
 
     #[command(flatten)]
     pub inclusion_proof_source: InclusionProofSource,
+
+    /// Emits the semaphore proof in compressed form
+    #[clap(long)]
+    pub compress: bool,
+
+    /// Prints the PBH payload as JSON instead of the hex encoded transaction and payload
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct MulticallArgs {
+    /// The address which will send the `pbhMulticall` transaction
+    #[clap(short, long)]
+    pub sender: Address,
+
+    /// A call to execute, formatted as `<target>:<calldata>`
+    ///
+    /// can be repeated, calldata can be constructed with `cast calldata`
+    #[clap(short, long = "call", required = true)]
+    #[clap(value_parser = utils::parse_call)]
+    pub calls: Vec<(Address, Bytes)>,
+
+    /// The PBH nonce for the priority tx
+    ///
+    /// should be in range 0-30 otherwise the tx will be discarded as invalid
+    #[clap(short = 'N', long)]
+    #[clap(alias = "nonce")]
+    pub pbh_nonce: u8,
+
+    /// Overrides the current date for PBH proof generation
+    /// Format: "YYYY-MM-DD"
+    ///
+    /// Dates are always assumed to be in UTC
+    #[clap(short = 'D', long)]
+    pub custom_date: Option<NaiveDate>,
+
+    #[command(flatten)]
+    pub identity_source: IdentitySource,
+
+    #[command(flatten)]
+    pub inclusion_proof_source: InclusionProofSource,
+
+    /// Emits the semaphore proof in compressed form
+    #[clap(long)]
+    pub compress: bool,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -2,9 +2,9 @@ use std::path::PathBuf;
 
 use clap::Args;
 use semaphore_rs::poseidon_tree::Proof;
+use world_chain_pbh::payload::InclusionProof;
 
 use super::utils::parse_from_json;
-use crate::InclusionProof;
 
 #[derive(Debug, Clone, Args)]
 pub struct InclusionProofSource {
@@ -1,3 +1,4 @@
+use alloy_primitives::Address;
 use bytes::{Bytes, BytesMut};
 use serde::de::DeserializeOwned;
 
@@ -11,6 +12,14 @@ pub fn bytes_parse_hex(s: &str) -> eyre::Result<Bytes> {
     Ok(Bytes::from(hex::decode(s.trim_start_matches("0x"))?))
 }
 
+pub fn parse_call(s: &str) -> eyre::Result<(Address, Bytes)> {
+    let (target, calldata) = s
+        .split_once(':')
+        .ok_or_else(|| eyre::eyre!("expected `<target>:<calldata>`, got `{s}`"))?;
+
+    Ok((target.parse()?, bytes_parse_hex(calldata)?))
+}
+
 pub fn parse_from_json<T>(s: &str) -> eyre::Result<T>
 where
     T: DeserializeOwned,
@@ -1,23 +1,23 @@
 use alloy_consensus::TxEnvelope;
 use alloy_rlp::Decodable;
+use alloy_sol_types::{SolCall, SolValue};
+use chrono::NaiveDate;
 use clap::Parser;
-use cli::{inclusion_proof_source::InclusionProofSourceVariant, Cmd, Opt};
-use semaphore_rs::{hash_to_field, identity::Identity, poseidon_tree::Proof, Field};
-use serde::{Deserialize, Serialize};
+use cli::{
+    identity_source::IdentitySource,
+    inclusion_proof_source::{InclusionProofSource, InclusionProofSourceVariant},
+    Cmd, Opt,
+};
+use semaphore_rs::{hash_to_field, identity::Identity, Field};
 use world_chain_pbh::{
+    abi::{IMulticall3, IPBHEntryPoint},
     date_marker::DateMarker,
-    external_nullifier::{EncodedExternalNullifier, ExternalNullifier},
-    payload::PBHPayload,
+    external_nullifier::ExternalNullifier,
+    payload::{InclusionProof, PBHPayload},
 };
 
 mod cli;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InclusionProof {
-    root: Field,
-    proof: Proof,
-}
-
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     dotenvy::dotenv().ok();
@@ -32,44 +32,20 @@ async fn main() -> eyre::Result<()> {
             let tx_hash = tx.tx_hash();
             let signal_hash = hash_to_field(tx_hash.as_ref());
 
-            let identity = prove_args.identity_source.load();
-
-            let inclusion_proof_proof_src =
-                prove_args.inclusion_proof_source.clone().into_variant();
-            let inclusion_proof = match inclusion_proof_proof_src {
-                InclusionProofSourceVariant::Proof(proof) => proof,
-                InclusionProofSourceVariant::File(file) => load_inclusion_proof_file(file)?,
-                InclusionProofSourceVariant::Url(url) => {
-                    fetch_inclusion_proof(&url, &identity).await?
-                }
-            };
-
-            let date = prove_args
-                .custom_date
-                .unwrap_or_else(|| chrono::Utc::now().naive_utc().date());
-
-            let date_marker = DateMarker::from(date);
-
-            let external_nullifier =
-                ExternalNullifier::with_date_marker(date_marker, prove_args.pbh_nonce as u16);
-            let external_nullifier_hash = EncodedExternalNullifier::from(external_nullifier).0;
-
-            let semaphore_proof = semaphore_rs::protocol::generate_proof(
-                &identity,
-                &inclusion_proof.proof,
-                external_nullifier_hash,
+            let proof = prove(
+                &prove_args.identity_source,
+                &prove_args.inclusion_proof_source,
+                prove_args.custom_date,
+                prove_args.pbh_nonce,
+                prove_args.compress,
                 signal_hash,
-            )?;
+            )
+            .await?;
 
-            let nullifier_hash =
-                semaphore_rs::protocol::generate_nullifier_hash(&identity, external_nullifier_hash);
-
-            let proof = PBHPayload {
-                external_nullifier,
-                nullifier_hash,
-                root: inclusion_proof.root,
-                proof: world_chain_pbh::payload::Proof(semaphore_proof),
-            };
+            if prove_args.json {
+                println!("{}", serde_json::to_string_pretty(&proof)?);
+                return Ok(());
+            }
 
             let encoded = alloy_rlp::encode(proof);
 
@@ -79,11 +55,75 @@ async fn main() -> eyre::Result<()> {
 
             println!("{encoded_hex}");
         }
+        Cmd::Multicall(multicall_args) => {
+            let calls: Vec<_> = multicall_args
+                .calls
+                .into_iter()
+                .map(|(target, call_data)| IMulticall3::Call3 {
+                    target,
+                    allowFailure: false,
+                    callData: call_data.into(),
+                })
+                .collect();
+
+            // Matches the signal hash computed by `pbhMulticall` on the PBHEntryPoint
+            let signal_hash = hash_to_field(&SolValue::abi_encode_params(&(
+                multicall_args.sender,
+                &calls,
+            )));
+
+            let proof = prove(
+                &multicall_args.identity_source,
+                &multicall_args.inclusion_proof_source,
+                multicall_args.custom_date,
+                multicall_args.pbh_nonce,
+                multicall_args.compress,
+                signal_hash,
+            )
+            .await?;
+
+            let calldata = IPBHEntryPoint::pbhMulticallCall {
+                calls,
+                payload: proof.into(),
+            }
+            .abi_encode();
+
+            println!("0x{}", hex::encode(calldata));
+        }
     }
 
     Ok(())
 }
 
+async fn prove(
+    identity_source: &IdentitySource,
+    inclusion_proof_source: &InclusionProofSource,
+    custom_date: Option<NaiveDate>,
+    pbh_nonce: u8,
+    compress: bool,
+    signal_hash: Field,
+) -> eyre::Result<PBHPayload> {
+    let identity = identity_source.load();
+
+    let inclusion_proof = match inclusion_proof_source.clone().into_variant() {
+        InclusionProofSourceVariant::Proof(proof) => proof,
+        InclusionProofSourceVariant::File(file) => load_inclusion_proof_file(file)?,
+        InclusionProofSourceVariant::Url(url) => fetch_inclusion_proof(&url, &identity).await?,
+    };
+
+    let date = custom_date.unwrap_or_else(|| chrono::Utc::now().naive_utc().date());
+
+    let date_marker = DateMarker::from(date);
+
+    let external_nullifier = ExternalNullifier::with_date_marker(date_marker, pbh_nonce as u16);
+
+    Ok(
+        PBHPayload::generate(&identity, &inclusion_proof, external_nullifier, signal_hash)
+            .compress(compress)
+            .call()?,
+    )
+}
+
 fn load_inclusion_proof_file(path: impl AsRef<std::path::Path>) -> eyre::Result<InclusionProof> {
     let file = std::fs::File::open(path)?;
     let proof = serde_json::from_reader(file)?;
@@ -10,7 +10,9 @@ use world_chain_node::{
     node::WorldChainNode,
     FlashblocksOpApi, OpApiExtServer,
 };
-use world_chain_rpc::{EthApiExtServer, SequencerClient, WorldChainEthApiExt};
+use world_chain_rpc::{
+    EthApiExtServer, PbhApiServer, SequencerClient, WorldChainEthApiExt, WorldChainPbhApi,
+};
 
 #[cfg(all(feature = "jemalloc", unix))]
 #[global_allocator]
@@ -57,9 +59,14 @@ fn main() {
                             let pool = ctx.pool().clone();
                             let sequencer_client =
                                 config.args.rollup.sequencer.map(SequencerClient::new);
+                            let pbh_api = WorldChainPbhApi::new(
+                                provider.clone(),
+                                config.args.pbh.entrypoints(),
+                            );
                             let eth_api_ext =
                                 WorldChainEthApiExt::new(pool, provider, sequencer_client);
                             ctx.modules.replace_configured(eth_api_ext.into_rpc())?;
+                            ctx.modules.merge_configured(pbh_api.into_rpc())?;
                             Ok(())
                         })
                         .launch()
@@ -79,9 +86,14 @@ fn main() {
                             let pool = ctx.pool().clone();
                             let sequencer_client =
                                 config.args.rollup.sequencer.map(SequencerClient::new);
+                            let pbh_api = WorldChainPbhApi::new(
+                                provider.clone(),
+                                config.args.pbh.entrypoints(),
+                            );
                             let eth_api_ext =
                                 WorldChainEthApiExt::new(pool, provider, sequencer_client);
                             ctx.modules.replace_configured(eth_api_ext.into_rpc())?;
+                            ctx.modules.merge_configured(pbh_api.into_rpc())?;
                             ctx.modules
                                 .replace_configured(FlashblocksOpApi.into_rpc())?;
                             Ok(())
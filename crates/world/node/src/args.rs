@@ -9,8 +9,15 @@ use reth::chainspec::NamedChain;
 use reth_network_peers::PeerId;
 use reth_optimism_chainspec::OpChainSpec;
 use reth_optimism_node::args::RollupArgs;
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 use tracing::warn;
+use world_chain_pool::{
+    entrypoint::{PbhEntrypoint, PbhEntrypoints},
+    nullifier_index::DuplicateNullifierPolicy,
+    ordering::WeightedPriorityPolicy,
+    quota::PbhQuotas,
+    simulation::SimulationConfig,
+};
 
 use crate::config::WorldChainNodeConfig;
 
@@ -141,6 +148,153 @@ pub struct PbhArgs {
         default_value_t = Default::default(),
     )]
     pub signature_aggregator: Address,
+
+    /// Sets the grace period in seconds after a period rollover during which the pool still
+    /// accepts PBH transactions with an external nullifier for the previous period.
+    /// This only tolerates skew between the local clock and the timestamp of the blocks being
+    /// built, and should not exceed a few block times. The PBHEntryPoint accepts no grace
+    /// period, so the payload builder only includes PBH transactions whose external nullifier
+    /// matches the period of the block, and the rest are evicted once the grace period ends.
+    #[arg(long = "pbh.grace_period", default_value = "0")]
+    pub grace_period: u64,
+
+    /// Accepts PBH payloads with a V2 external nullifier, which supports weekly and daily
+    /// periods. Only enable this once the PBHEntryPoint verifies V2 external nullifiers,
+    /// otherwise the PBH bundles carrying them revert on chain.
+    #[arg(long = "pbh.external_nullifier_v2", default_value_t = false)]
+    pub external_nullifier_v2: bool,
+
+    /// Sets the policy used to resolve two pooled transactions carrying the same PBH nullifier
+    /// hash. Either `first_seen` or `higher_tip`.
+    #[arg(long = "pbh.duplicate_nullifier_policy", default_value_t = Default::default())]
+    pub duplicate_nullifier_policy: DuplicateNullifierPolicy,
+
+    /// Overrides the duration in seconds for which a World ID root remains valid after it was
+    /// superseded. By default the root history expiry of the WorldID contract is used.
+    /// Intended for devnets whose WorldID contract does not define an expiry.
+    #[arg(long = "pbh.root_expiration_window")]
+    pub root_expiration_window: Option<u64>,
+
+    /// Sets the maximum number of PBH transactions a single sender can have in the pool.
+    #[arg(long = "pbh.max_txs_per_sender")]
+    pub max_txs_per_sender: Option<usize>,
+
+    /// Sets the maximum number of PBH nullifier hashes with an external nullifier for the same
+    /// period the pool holds.
+    #[arg(long = "pbh.max_nullifiers_per_period")]
+    pub max_nullifiers_per_period: Option<usize>,
+
+    /// Sets the maximum number of PBH transactions the pool holds, independent of the pool
+    /// limits. Once reached, the PBH transactions paying the lowest priority fee are evicted.
+    #[arg(long = "pbh.max_pool_txs")]
+    pub max_pool_txs: Option<usize>,
+
+    /// Sets the version of the signal derivation of `--pbh.entrypoint` for the PBH payloads of
+    /// UserOperations. Version 1 hashes the sender, nonce and calldata, version 2 hashes the
+    /// ERC-4337 v0.7 UserOperation hash. The node fails to start if it does not match
+    /// `getSignalHash` on the deployed PBHEntryPoint.
+    #[arg(long = "pbh.entrypoint_signal_version", default_value = "1", value_parser = value_parser!(u8).range(1..=2))]
+    pub entrypoint_signal_version: u8,
+
+    /// Adds a PBHEntryPoint which is accepted alongside `--pbh.entrypoint`, formatted as
+    /// `<entrypoint>:<signature_aggregator>:<activation_timestamp>[:[<deactivation_timestamp>][:<signal_version>]]`.
+    /// PBH transactions to the entrypoint are accepted from the activation timestamp onwards,
+    /// and until the deactivation timestamp if given, which allows migrating to a new deployment
+    /// without downtime. The signal version defaults to 1. Can be passed multiple times.
+    #[arg(
+        long = "pbh.additional_entrypoint",
+        value_name = "ENTRYPOINT:AGGREGATOR:TIMESTAMP[:[TIMESTAMP][:VERSION]]"
+    )]
+    pub additional_entrypoints: Vec<PbhEntrypoint>,
+
+    /// Sets the timestamp from which PBH transactions to `--pbh.entrypoint` are no longer
+    /// accepted, once it is superseded by an additional entrypoint. Nullifier hashes spent on it
+    /// remain spent on every other configured entrypoint.
+    #[arg(long = "pbh.entrypoint_deactivation_timestamp")]
+    pub entrypoint_deactivation_timestamp: Option<u64>,
+
+    /// Simulates PBH bundles against the latest state before admitting them to the pool.
+    /// Bundles whose UserOps revert or violate the ERC-7562 validation rules are rejected.
+    #[arg(long = "pbh.simulate_bundles", default_value_t = false)]
+    pub simulate_bundles: bool,
+
+    /// Sets the maximum verification gas limit of a UserOp in a simulated PBH bundle.
+    #[arg(long = "pbh.max_verification_gas", requires = "simulate_bundles")]
+    pub max_verification_gas: Option<u64>,
+
+    /// Sets the maximum call gas limit of a UserOp in a simulated PBH bundle.
+    #[arg(long = "pbh.max_call_gas", requires = "simulate_bundles")]
+    pub max_call_gas: Option<u64>,
+
+    /// Sets the maximum gas limit per PBH payload for a transaction to be ordered as a PBH
+    /// transaction. Larger transactions are ordered by tip alongside non-PBH transactions.
+    #[arg(long = "pbh.ordering_max_gas_per_op")]
+    pub ordering_max_gas_per_op: Option<u64>,
+
+    /// Orders PBH transactions carrying more UserOps before smaller ones.
+    #[arg(long = "pbh.ordering_prefer_larger_bundles", default_value_t = false)]
+    pub ordering_prefer_larger_bundles: bool,
+
+    /// Sets the time in seconds a PBH transaction must spend in the pool to move ahead of PBH
+    /// transactions which entered the pool one step later, regardless of their tip.
+    #[arg(long = "pbh.ordering_age_step")]
+    pub ordering_age_step: Option<u64>,
+
+    /// Orders PBH transactions whose external nullifier nonce is below the given number by how
+    /// few PBH transactions the identity behind them has sent in the current period, so that
+    /// a few heavy users can't exhaust the PBH blockspace.
+    #[arg(long = "pbh.ordering_fair_nonces")]
+    pub ordering_fair_nonces: Option<u16>,
+}
+
+impl PbhArgs {
+    /// Returns the configured PBH admission quotas.
+    pub fn quotas(&self) -> PbhQuotas {
+        PbhQuotas {
+            max_txs_per_sender: self.max_txs_per_sender,
+            max_nullifiers_per_period: self.max_nullifiers_per_period,
+            max_pbh_txs: self.max_pool_txs,
+        }
+    }
+
+    /// Returns the configuration of the PBH bundle simulation, if enabled.
+    pub fn simulation(&self) -> Option<SimulationConfig> {
+        self.simulate_bundles.then(|| SimulationConfig {
+            max_verification_gas: self.max_verification_gas,
+            max_call_gas: self.max_call_gas,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the configured policy used to order the transactions of the pool.
+    pub fn priority_policy(&self) -> WeightedPriorityPolicy {
+        let mut policy = WeightedPriorityPolicy::new()
+            .with_prefer_larger_bundles(self.ordering_prefer_larger_bundles);
+        if let Some(max_gas_per_op) = self.ordering_max_gas_per_op {
+            policy = policy.with_max_gas_per_pbh_op(max_gas_per_op);
+        }
+        if let Some(age_step) = self.ordering_age_step {
+            policy = policy.with_age_step(Duration::from_secs(age_step));
+        }
+        if let Some(fair_nonces) = self.ordering_fair_nonces {
+            policy = policy.with_fair_nonces(fair_nonces);
+        }
+        policy
+    }
+
+    /// Returns the configured PBHEntryPoints, the one at `--pbh.entrypoint` being active from
+    /// genesis.
+    pub fn entrypoints(&self) -> PbhEntrypoints {
+        let mut entrypoint = PbhEntrypoint::new(self.entrypoint, self.signature_aggregator)
+            .with_signal_version(self.entrypoint_signal_version);
+        if let Some(deactivation_timestamp) = self.entrypoint_deactivation_timestamp {
+            entrypoint = entrypoint.with_deactivation_timestamp(deactivation_timestamp);
+        }
+
+        std::iter::once(entrypoint)
+            .chain(self.additional_entrypoints.iter().copied())
+            .collect()
+    }
 }
 
 /// Parameters for pbh builder configuration
@@ -332,6 +486,23 @@ mod tests {
                 entrypoint: Default::default(),
                 world_id: Default::default(),
                 signature_aggregator: Default::default(),
+                grace_period: 0,
+                external_nullifier_v2: false,
+                duplicate_nullifier_policy: Default::default(),
+                root_expiration_window: None,
+                max_txs_per_sender: None,
+                max_nullifiers_per_period: None,
+                max_pool_txs: None,
+                entrypoint_signal_version: 1,
+                additional_entrypoints: vec![],
+                entrypoint_deactivation_timestamp: None,
+                simulate_bundles: false,
+                max_verification_gas: None,
+                max_call_gas: None,
+                ordering_max_gas_per_op: None,
+                ordering_prefer_larger_bundles: false,
+                ordering_age_step: None,
+                ordering_fair_nonces: None,
             },
             builder: BuilderArgs {
                 enabled: false,
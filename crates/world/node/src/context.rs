@@ -176,11 +176,21 @@ where
 
         ComponentsBuilder::default()
             .node_types::<N>()
-            .pool(WorldChainPoolBuilder::new(
-                pbh.entrypoint,
-                pbh.signature_aggregator,
-                pbh.world_id,
-            ))
+            .pool(
+                WorldChainPoolBuilder::new(
+                    pbh.entrypoint,
+                    pbh.signature_aggregator,
+                    pbh.world_id,
+                    Duration::from_secs(pbh.grace_period),
+                )
+                .with_external_nullifier_v2(pbh.external_nullifier_v2)
+                .with_duplicate_nullifier_policy(pbh.duplicate_nullifier_policy)
+                .with_root_expiration_override(pbh.root_expiration_window)
+                .with_pbh_quotas(pbh.quotas())
+                .with_pbh_entrypoints(pbh.entrypoints())
+                .with_simulation(pbh.simulation())
+                .with_priority_policy(pbh.priority_policy()),
+            )
             .executor(OpExecutorBuilder::default())
             .payload(BasicPayloadServiceBuilder::new(
                 WorldChainPayloadBuilderBuilder::new(
@@ -190,7 +200,8 @@ where
                     pbh.signature_aggregator,
                     builder.private_key,
                 )
-                .with_da_config(builder_config.da_config),
+                .with_da_config(builder_config.da_config)
+                .with_pbh_entrypoints(pbh.entrypoints()),
             ))
             .network(network_builder)
             .consensus(OpConsensusBuilder::default())
@@ -282,18 +293,27 @@ where
 
         let ctx_builder = WorldChainPayloadBuilderCtxBuilder {
             verified_blockspace_capacity: pbh.verified_blockspace_capacity,
-            pbh_entry_point: pbh.entrypoint,
-            pbh_signature_aggregator: pbh.signature_aggregator,
+            pbh_entrypoints: pbh.entrypoints(),
             builder_private_key: builder.private_key,
         };
 
         ComponentsBuilder::default()
             .node_types::<N>()
-            .pool(WorldChainPoolBuilder::new(
-                pbh.entrypoint,
-                pbh.signature_aggregator,
-                pbh.world_id,
-            ))
+            .pool(
+                WorldChainPoolBuilder::new(
+                    pbh.entrypoint,
+                    pbh.signature_aggregator,
+                    pbh.world_id,
+                    Duration::from_secs(pbh.grace_period),
+                )
+                .with_external_nullifier_v2(pbh.external_nullifier_v2)
+                .with_duplicate_nullifier_policy(pbh.duplicate_nullifier_policy)
+                .with_root_expiration_override(pbh.root_expiration_window)
+                .with_pbh_quotas(pbh.quotas())
+                .with_pbh_entrypoints(pbh.entrypoints())
+                .with_simulation(pbh.simulation())
+                .with_priority_policy(pbh.priority_policy()),
+            )
             .executor(OpExecutorBuilder::default())
             .payload(FlashblocksPayloadServiceBuilder::new(
                 FlashblocksPayloadBuilderBuilder::new(
@@ -1,4 +1,4 @@
-use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+use std::{fmt::Debug, marker::PhantomData, sync::Arc, time::Duration};
 
 use alloy_primitives::Address;
 use alloy_signer_local::PrivateKeySigner;
@@ -11,13 +11,15 @@ use reth::builder::{
 };
 
 use reth::{
+    chainspec::EthChainSpec,
+    revm::database::StateProviderDatabase,
     rpc::eth::EthApiTypes,
     transaction_pool::{blobstore::DiskFileBlobStore, TransactionValidationTaskExecutor},
 };
 
 use reth_engine_local::LocalPayloadAttributesBuilder;
 
-use reth_evm::ConfigureEvm;
+use reth_evm::{ConfigureEvm, EvmEnv};
 use reth_node_api::{NodeAddOns, PayloadAttributesBuilder};
 use reth_node_builder::{
     components::{NetworkBuilder, PayloadServiceBuilder},
@@ -40,17 +42,25 @@ use reth_optimism_payload_builder::{
 use reth_optimism_primitives::{OpBlock, OpPrimitives};
 
 use reth_provider::{
-    BlockReader, BlockReaderIdExt, CanonStateSubscriptions, ChainSpecProvider, StateProviderFactory,
+    BlockReader, BlockReaderIdExt, CanonStateSubscriptions, ChainSpecProvider, ProviderResult,
+    StateProviderFactory,
 };
 
 use reth_transaction_pool::{BlobStore, TransactionPool};
 
 use crate::config::WorldChainNodeConfig;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use world_chain_payload::builder::WorldChainPayloadBuilder;
 use world_chain_pool::{
-    ordering::WorldChainOrdering,
-    root::WorldChainRootValidator,
+    eip4337::verify_user_op_signal,
+    entrypoint::PbhEntrypoints,
+    limits::{maintain_pbh_limits, PbhLimits},
+    maintain::{maintain_pbh_transactions, MaintainPbhConfig},
+    nullifier_index::{maintain_nullifier_index, DuplicateNullifierPolicy, NullifierIndex},
+    ordering::{WeightedPriorityPolicy, WorldChainOrdering},
+    quota::{maintain_pbh_quotas, PbhQuotaTracker, PbhQuotas},
+    root::{maintain_roots, RootConfig, WorldChainRootValidator},
+    simulation::{EvmBundleSimulator, SimulationConfig},
     tx::{WorldChainPoolTransaction, WorldChainPooledTransaction},
     validator::WorldChainTransactionValidator,
     WorldChainTransactionPool,
@@ -243,9 +253,24 @@ impl<T: Unpin + Send + Clone + Sync + Debug + 'static> NodeTypes for WorldChainN
 /// config.
 #[derive(Debug, Clone)]
 pub struct WorldChainPoolBuilder {
-    pub pbh_entrypoint: Address,
-    pub pbh_signature_aggregator: Address,
+    /// The PBHEntryPoints accepting PBH transactions.
+    pub pbh_entrypoints: PbhEntrypoints,
     pub world_id: Address,
+    /// The grace period after a period rollover during which PBH payloads for the previous
+    /// period are still accepted.
+    pub pbh_grace_period: Duration,
+    /// Whether PBH payloads with a V2 external nullifier are accepted.
+    pub external_nullifier_v2: bool,
+    /// The policy used to resolve pooled transactions carrying the same PBH nullifier hash.
+    pub duplicate_nullifier_policy: DuplicateNullifierPolicy,
+    /// Overrides the root expiration window of the WorldID contract, in seconds.
+    pub root_expiration_override: Option<u64>,
+    /// The admission quotas for PBH transactions.
+    pub pbh_quotas: PbhQuotas,
+    /// The configuration of the PBH bundle simulation, if enabled.
+    pub simulation: Option<SimulationConfig>,
+    /// The policy used to order the transactions of the pool.
+    pub priority_policy: WeightedPriorityPolicy,
     /// Enforced overrides that are applied to the pool config.
     pub pool_config_overrides: PoolBuilderConfigOverrides,
 }
@@ -255,11 +280,18 @@ impl WorldChainPoolBuilder {
         pbh_entrypoint: Address,
         pbh_signature_aggregator: Address,
         world_id: Address,
+        pbh_grace_period: Duration,
     ) -> Self {
         Self {
-            pbh_entrypoint,
-            pbh_signature_aggregator,
+            pbh_entrypoints: PbhEntrypoints::single(pbh_entrypoint, pbh_signature_aggregator),
             world_id,
+            pbh_grace_period,
+            external_nullifier_v2: false,
+            duplicate_nullifier_policy: Default::default(),
+            root_expiration_override: None,
+            pbh_quotas: Default::default(),
+            simulation: None,
+            priority_policy: Default::default(),
             pool_config_overrides: Default::default(),
         }
     }
@@ -274,6 +306,53 @@ impl WorldChainPoolBuilder {
         self.pool_config_overrides = pool_config_overrides;
         self
     }
+
+    /// Sets the [`DuplicateNullifierPolicy`] on the pool builder.
+    pub fn with_duplicate_nullifier_policy(
+        mut self,
+        duplicate_nullifier_policy: DuplicateNullifierPolicy,
+    ) -> Self {
+        self.duplicate_nullifier_policy = duplicate_nullifier_policy;
+        self
+    }
+
+    /// Sets whether PBH payloads with a V2 external nullifier are accepted on the pool builder.
+    pub fn with_external_nullifier_v2(mut self, external_nullifier_v2: bool) -> Self {
+        self.external_nullifier_v2 = external_nullifier_v2;
+        self
+    }
+
+    /// Overrides the root expiration window of the WorldID contract on the pool builder.
+    pub fn with_root_expiration_override(mut self, root_expiration_override: Option<u64>) -> Self {
+        self.root_expiration_override = root_expiration_override;
+        self
+    }
+
+    /// Sets the [`PbhQuotas`] on the pool builder.
+    pub fn with_pbh_quotas(mut self, pbh_quotas: PbhQuotas) -> Self {
+        self.pbh_quotas = pbh_quotas;
+        self
+    }
+
+    /// Sets the [`PbhEntrypoints`] on the pool builder.
+    pub fn with_pbh_entrypoints(mut self, pbh_entrypoints: PbhEntrypoints) -> Self {
+        self.pbh_entrypoints = pbh_entrypoints;
+        self
+    }
+
+    /// Enables the simulation of PBH bundles with the given [`SimulationConfig`] on the pool
+    /// builder.
+    pub fn with_simulation(mut self, simulation: Option<SimulationConfig>) -> Self {
+        self.simulation = simulation;
+        self
+    }
+
+    /// Sets the [`WeightedPriorityPolicy`] used to order the transactions of the pool on the
+    /// pool builder.
+    pub fn with_priority_policy(mut self, priority_policy: WeightedPriorityPolicy) -> Self {
+        self.priority_policy = priority_policy;
+        self
+    }
 }
 
 impl<Node> PoolBuilder<Node> for WorldChainPoolBuilder
@@ -284,16 +363,70 @@ where
 
     async fn build_pool(self, ctx: &BuilderContext<Node>) -> eyre::Result<Self::Pool> {
         let Self {
-            pbh_entrypoint,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             world_id,
+            pbh_grace_period,
+            external_nullifier_v2,
+            duplicate_nullifier_policy,
+            root_expiration_override,
+            pbh_quotas,
+            simulation,
+            priority_policy,
             pool_config_overrides,
             ..
         } = self;
 
+        let nullifier_index = Arc::new(NullifierIndex::new(duplicate_nullifier_policy));
+        let quota_tracker = Arc::new(PbhQuotaTracker::new(pbh_quotas));
+        let state = ctx.provider().latest()?;
+
+        // Every PBH proof is rejected either by the pool or on chain if the pool does not derive
+        // the same signals as the deployed entrypoints. Entrypoints which can't be verified yet
+        // must not prevent the node from starting, as it sequences their upgrade.
+        {
+            let chain_id = ctx.chain_spec().chain_id();
+            let mut evm_env = EvmEnv::default();
+            evm_env.cfg_env.chain_id = chain_id;
+            let mut evm = OpEvmConfig::optimism(ctx.chain_spec())
+                .evm_with_env(StateProviderDatabase::new(&state), evm_env);
+            for entrypoint in pbh_entrypoints.iter() {
+                let user_op_signal = entrypoint.user_op_signal(chain_id).ok_or_else(|| {
+                    eyre::eyre!(
+                        "unsupported PBH signal version {} of {}",
+                        entrypoint.signal_version,
+                        entrypoint.entrypoint
+                    )
+                })?;
+                if !verify_user_op_signal(&mut evm, entrypoint.entrypoint, user_op_signal)? {
+                    warn!(
+                        target: "reth::cli",
+                        entrypoint = %entrypoint.entrypoint,
+                        signal_version = entrypoint.signal_version,
+                        "PBHEntryPoint is not deployed or predates getSignalHash, skipping signal verification"
+                    );
+                }
+            }
+        }
+        let pbh_limits = pbh_entrypoints
+            .iter()
+            .map(|entrypoint| {
+                let limits = PbhLimits::from_state(&state, entrypoint.entrypoint)?;
+                Ok((entrypoint.entrypoint, Arc::new(limits)))
+            })
+            .collect::<ProviderResult<Vec<_>>>()?;
+
         let data_dir = ctx.config().datadir();
         let blob_store = DiskFileBlobStore::open(data_dir.blobstore(), Default::default())?;
 
+        let root_validator = WorldChainRootValidator::with_config(
+            ctx.provider().clone(),
+            world_id,
+            RootConfig {
+                root_expiration_override,
+                ..Default::default()
+            },
+        )?;
+
         let validator = TransactionValidationTaskExecutor::eth_builder(ctx.provider().clone())
             .no_eip4844()
             .with_head_timestamp(ctx.head().timestamp)
@@ -305,26 +438,41 @@ where
             )
             .build_with_tasks(ctx.task_executor().clone(), blob_store.clone())
             .map(|validator| {
-                let client = validator.client().clone();
                 let op_tx_validator = OpTransactionValidator::new(validator)
                     // In --dev mode we can't require gas fees because we're unable to decode the L1
                     // block info
                     .require_l1_data_gas_fee(!ctx.config().dev.dev);
-                let root_validator = WorldChainRootValidator::new(client, world_id)
-                    .expect("failed to initialize root validator");
 
-                WorldChainTransactionValidator::new(
+                let validator = WorldChainTransactionValidator::with_entrypoints(
                     op_tx_validator,
-                    root_validator,
-                    pbh_entrypoint,
-                    pbh_signature_aggregator,
+                    root_validator.clone(),
+                    pbh_entrypoints.clone(),
                 )
                 .expect("failed to create world chain validator")
+                .with_pbh_grace_period(pbh_grace_period)
+                .with_external_nullifier_v2(external_nullifier_v2)
+                .with_nullifier_index(nullifier_index.clone())
+                .with_quota_tracker(quota_tracker.clone());
+
+                let validator = match simulation {
+                    Some(config) => validator.with_bundle_simulator(EvmBundleSimulator::new(
+                        ctx.provider().clone(),
+                        OpEvmConfig::optimism(ctx.chain_spec()),
+                        config,
+                    )),
+                    None => validator,
+                };
+
+                pbh_limits
+                    .iter()
+                    .fold(validator, |validator, (pbh_entrypoint, limits)| {
+                        validator.with_pbh_limits(*pbh_entrypoint, limits.clone())
+                    })
             });
 
         let transaction_pool = reth_transaction_pool::Pool::new(
             validator,
-            WorldChainOrdering::default(),
+            WorldChainOrdering::new(priority_policy),
             blob_store,
             pool_config_overrides.apply(ctx.pool_config()),
         );
@@ -371,6 +519,58 @@ where
                 ),
             );
             debug!(target: "reth::cli", "Spawned txpool maintenance task");
+
+            // spawn the PBH maintenance task
+            ctx.task_executor().spawn_critical(
+                "pbh txpool maintenance task",
+                maintain_pbh_transactions(
+                    ctx.provider().clone(),
+                    pool.clone(),
+                    root_validator.clone(),
+                    pbh_entrypoints,
+                    ctx.provider().canonical_state_stream(),
+                    MaintainPbhConfig {
+                        grace_period: pbh_grace_period,
+                    },
+                ),
+            );
+            debug!(target: "reth::cli", "Spawned PBH txpool maintenance task");
+
+            // spawn the nullifier index maintenance task
+            ctx.task_executor().spawn_critical(
+                "pbh nullifier index maintenance task",
+                maintain_nullifier_index(pool.clone(), nullifier_index),
+            );
+            debug!(target: "reth::cli", "Spawned PBH nullifier index maintenance task");
+
+            // spawn the PBH quota maintenance task
+            ctx.task_executor().spawn_critical(
+                "pbh quota maintenance task",
+                maintain_pbh_quotas(pool.clone(), quota_tracker),
+            );
+            debug!(target: "reth::cli", "Spawned PBH quota maintenance task");
+
+            // spawn a PBH limits maintenance task per PBHEntryPoint
+            for (pbh_entrypoint, limits) in pbh_limits {
+                ctx.task_executor().spawn_critical(
+                    "pbh limits maintenance task",
+                    maintain_pbh_limits(
+                        ctx.provider().clone(),
+                        pool.clone(),
+                        limits,
+                        pbh_entrypoint,
+                        ctx.provider().canonical_state_stream(),
+                    ),
+                );
+                debug!(target: "reth::cli", %pbh_entrypoint, "Spawned PBH limits maintenance task");
+            }
+
+            // spawn the World ID root maintenance task
+            ctx.task_executor().spawn_critical(
+                "world id root maintenance task",
+                maintain_roots(root_validator, ctx.provider().canonical_state_stream()),
+            );
+            debug!(target: "reth::cli", "Spawned World ID root maintenance task");
         }
 
         Ok(transaction_pool)
@@ -396,8 +596,8 @@ pub struct WorldChainPayloadBuilderBuilder<Txs = ()> {
     /// when assembling payloads
     pub builder_config: OpBuilderConfig,
     pub verified_blockspace_capacity: u8,
-    pub pbh_entry_point: Address,
-    pub pbh_signature_aggregator: Address,
+    /// The PBHEntryPoints on which the nullifier hashes of included PBH payloads are spent.
+    pub pbh_entrypoints: PbhEntrypoints,
 
     /// Sets the private key of the builder
     pub builder_private_key: PrivateKeySigner,
@@ -416,8 +616,7 @@ impl WorldChainPayloadBuilderBuilder {
         Self {
             compute_pending_block,
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints: PbhEntrypoints::single(pbh_entry_point, pbh_signature_aggregator),
             best_transactions: (),
             builder_private_key,
             builder_config: OpBuilderConfig::default(),
@@ -429,6 +628,12 @@ impl WorldChainPayloadBuilderBuilder {
         self.builder_config.da_config = da_config;
         self
     }
+
+    /// Sets the [`PbhEntrypoints`] on the payload builder.
+    pub fn with_pbh_entrypoints(mut self, pbh_entrypoints: PbhEntrypoints) -> Self {
+        self.pbh_entrypoints = pbh_entrypoints;
+        self
+    }
 }
 
 impl<Txs> WorldChainPayloadBuilderBuilder<Txs> {
@@ -439,8 +644,7 @@ impl<Txs> WorldChainPayloadBuilderBuilder<Txs> {
             compute_pending_block,
             builder_config,
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             builder_private_key,
             ..
         } = self;
@@ -449,8 +653,7 @@ impl<Txs> WorldChainPayloadBuilderBuilder<Txs> {
             compute_pending_block,
             builder_config,
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             best_transactions,
             builder_private_key,
         }
@@ -491,8 +694,7 @@ where
             self.builder_config.clone(),
             self.compute_pending_block,
             self.verified_blockspace_capacity,
-            self.pbh_entry_point,
-            self.pbh_signature_aggregator,
+            self.pbh_entrypoints.clone(),
             self.builder_private_key.clone(),
         )
         .with_transactions(self.best_transactions.clone()))
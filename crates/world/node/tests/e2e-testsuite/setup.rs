@@ -49,7 +49,9 @@ use world_chain_pool::{
     validator::{MAX_U16, PBH_GAS_LIMIT_SLOT, PBH_NONCE_LIMIT_SLOT},
     BasicWorldChainPool,
 };
-use world_chain_rpc::{EthApiExtServer, SequencerClient, WorldChainEthApiExt};
+use world_chain_rpc::{
+    EthApiExtServer, PbhApiServer, SequencerClient, WorldChainEthApiExt, WorldChainPbhApi,
+};
 
 const GENESIS: &str = include_str!("../res/genesis.json");
 
@@ -207,8 +209,10 @@ where
                 let provider = ctx.provider().clone();
                 let pool = ctx.pool().clone();
                 let sequencer_client = config.args.rollup.sequencer.map(SequencerClient::new);
+                let pbh_api = WorldChainPbhApi::new(provider.clone(), config.args.pbh.entrypoints());
                 let eth_api_ext = WorldChainEthApiExt::new(pool, provider, sequencer_client);
                 ctx.modules.replace_configured(eth_api_ext.into_rpc())?;
+                ctx.modules.merge_configured(pbh_api.into_rpc())?;
                 ctx.modules.replace_configured(FlashblocksOpApi.into_rpc())?;
                 Ok(())
             })
@@ -35,10 +35,11 @@ use reth_provider::{
     StateProviderFactory,
 };
 use reth_transaction_pool::BlobStore;
-use revm_primitives::Address;
 use std::sync::Arc;
 use tracing::debug;
-use world_chain_pool::{tx::WorldChainPooledTransaction, WorldChainTransactionPool};
+use world_chain_pool::{
+    entrypoint::PbhEntrypoints, tx::WorldChainPooledTransaction, WorldChainTransactionPool,
+};
 
 /// World Chain payload builder
 #[derive(Debug, Clone)]
@@ -52,8 +53,7 @@ where
 {
     pub inner: OpPayloadBuilder<WorldChainTransactionPool<Client, S>, Client, OpEvmConfig, Txs>,
     pub verified_blockspace_capacity: u8,
-    pub pbh_entry_point: Address,
-    pub pbh_signature_aggregator: Address,
+    pub pbh_entrypoints: PbhEntrypoints,
     pub builder_private_key: PrivateKeySigner,
 }
 
@@ -72,8 +72,7 @@ where
         evm_config: OpEvmConfig,
         compute_pending_block: bool,
         verified_blockspace_capacity: u8,
-        pbh_entry_point: Address,
-        pbh_signature_aggregator: Address,
+        pbh_entrypoints: PbhEntrypoints,
         builder_private_key: PrivateKeySigner,
     ) -> Self {
         Self::with_builder_config(
@@ -83,8 +82,7 @@ where
             OpBuilderConfig::default(),
             compute_pending_block,
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             builder_private_key,
         )
     }
@@ -97,8 +95,7 @@ where
         config: OpBuilderConfig,
         compute_pending_block: bool,
         verified_blockspace_capacity: u8,
-        pbh_entry_point: Address,
-        pbh_signature_aggregator: Address,
+        pbh_entrypoints: PbhEntrypoints,
         builder_private_key: PrivateKeySigner,
     ) -> Self {
         let inner = OpPayloadBuilder::with_builder_config(pool, client, evm_config, config)
@@ -107,8 +104,7 @@ where
         Self {
             inner,
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             builder_private_key,
         }
     }
@@ -135,16 +131,14 @@ where
         let Self {
             inner,
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             builder_private_key,
         } = self;
 
         WorldChainPayloadBuilder {
             inner: inner.with_transactions(best_transactions),
             verified_blockspace_capacity,
-            pbh_entry_point,
-            pbh_signature_aggregator,
+            pbh_entrypoints,
             builder_private_key,
         }
     }
@@ -203,8 +197,7 @@ where
             }),
             client: self.inner.client.clone(),
             verified_blockspace_capacity: self.verified_blockspace_capacity,
-            pbh_entry_point: self.pbh_entry_point,
-            pbh_signature_aggregator: self.pbh_signature_aggregator,
+            pbh_entrypoints: self.pbh_entrypoints.clone(),
             builder_private_key: self.builder_private_key.clone(),
         };
 
@@ -256,8 +249,7 @@ where
             }),
             client,
             verified_blockspace_capacity: self.verified_blockspace_capacity,
-            pbh_entry_point: self.pbh_entry_point,
-            pbh_signature_aggregator: self.pbh_signature_aggregator,
+            pbh_entrypoints: self.pbh_entrypoints.clone(),
             builder_private_key: self.builder_private_key.clone(),
         };
 
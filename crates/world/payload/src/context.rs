@@ -42,11 +42,18 @@ use reth_transaction_pool::PoolTransaction;
 use revm::context::BlockEnv;
 use revm_primitives::{Address, U256};
 use semaphore_rs::Field;
-use std::{collections::HashSet, fmt::Debug, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+    time::Duration,
+};
 use tracing::{error, trace};
 
+use world_chain_pbh::clock::{Clock, FixedClock};
 use world_chain_pool::{
     bindings::IPBHEntryPoint::spendNullifierHashesCall,
+    entrypoint::PbhEntrypoints,
     tx::{WorldChainPoolTransaction, WorldChainPooledTransaction},
 };
 use world_chain_rpc::transactions::validate_conditional_options;
@@ -56,8 +63,7 @@ use world_chain_rpc::transactions::validate_conditional_options;
 pub struct WorldChainPayloadBuilderCtx<Client: ChainSpecProvider> {
     pub inner: Arc<OpPayloadBuilderCtx<OpEvmConfig, <Client as ChainSpecProvider>::ChainSpec>>,
     pub verified_blockspace_capacity: u8,
-    pub pbh_entry_point: Address,
-    pub pbh_signature_aggregator: Address,
+    pub pbh_entrypoints: PbhEntrypoints,
     pub client: Client,
     pub builder_private_key: PrivateKeySigner,
 }
@@ -65,8 +71,7 @@ pub struct WorldChainPayloadBuilderCtx<Client: ChainSpecProvider> {
 #[derive(Debug, Clone)]
 pub struct WorldChainPayloadBuilderCtxBuilder {
     pub verified_blockspace_capacity: u8,
-    pub pbh_entry_point: Address,
-    pub pbh_signature_aggregator: Address,
+    pub pbh_entrypoints: PbhEntrypoints,
     pub builder_private_key: PrivateKeySigner,
 }
 
@@ -256,7 +261,16 @@ where
         let mut invalid_txs = vec![];
         let verified_gas_limit = (self.verified_blockspace_capacity as u64 * gas_limit) / 100;
 
+        // PBH payloads are validated against the timestamp of the block being built rather than
+        // wall-clock time, as this is what the PBHEntryPoint checks the external nullifier against.
+        let clock = FixedClock::from_timestamp(self.attributes().timestamp()).ok_or_else(|| {
+            PayloadBuilderError::Other(eyre!("invalid payload attributes timestamp").into())
+        })?;
+
+        // Nullifier hashes are spent on the PBHEntryPoint the transaction was sent to, while
+        // duplicates are rejected across all entrypoints.
         let mut spent_nullifier_hashes = HashSet::new();
+        let mut nullifier_hashes_by_entrypoint: BTreeMap<Address, HashSet<Field>> = BTreeMap::new();
         while let Some(pooled_tx) = best_txs.next(()) {
             let tx_da_size = pooled_tx.estimated_da_size();
             let tx = pooled_tx.clone().into_consensus();
@@ -302,6 +316,30 @@ where
                     continue;
                 }
 
+                // The PBHEntryPoint requires the external nullifier to match the period of the
+                // block exactly, so the grace period of the pool does not apply here.
+                if payloads.iter().any(|payload| {
+                    payload
+                        .validate_external_nullifier_period(clock.now(), Duration::ZERO)
+                        .is_err()
+                }) {
+                    trace!(target: "payload_builder", ?tx, "skipping PBH transaction with an external nullifier outside of the block period");
+                    best_txs.mark_invalid(tx.signer(), tx.nonce());
+                    invalid_txs.push(*pooled_tx.hash());
+                    continue;
+                }
+
+                let Some(pbh_entrypoint) = tx.to().filter(|to| {
+                    self.pbh_entrypoints
+                        .active(*to, self.attributes().timestamp())
+                        .is_some()
+                }) else {
+                    trace!(target: "payload_builder", ?tx, "skipping PBH transaction to an inactive PBHEntryPoint");
+                    best_txs.mark_invalid(tx.signer(), tx.nonce());
+                    invalid_txs.push(*pooled_tx.hash());
+                    continue;
+                };
+
                 if payloads
                     .iter()
                     .any(|payload| !spent_nullifier_hashes.insert(payload.nullifier_hash))
@@ -310,12 +348,21 @@ where
                     invalid_txs.push(*pooled_tx.hash());
                     continue;
                 }
+
+                nullifier_hashes_by_entrypoint
+                    .entry(pbh_entrypoint)
+                    .or_default()
+                    .extend(payloads.iter().map(|payload| payload.nullifier_hash));
             }
 
             let gas_used = match builder.execute_transaction(tx.clone()) {
                 Ok(res) => {
                     if let Some(payloads) = pooled_tx.pbh_payload() {
-                        if spent_nullifier_hashes.len() == payloads.len() {
+                        let entrypoint_nullifier_hashes = tx
+                            .to()
+                            .and_then(|to| nullifier_hashes_by_entrypoint.get(&to))
+                            .map_or(0, HashSet::len);
+                        if entrypoint_nullifier_hashes == payloads.len() {
                             gas_limit -= FIXED_GAS
                         }
 
@@ -353,13 +400,12 @@ where
             self.commit_changes(info, base_fee, gas_used, tx);
         }
 
-        if !spent_nullifier_hashes.is_empty() {
-            let tx = spend_nullifiers_tx(self, builder.evm_mut(), spent_nullifier_hashes).map_err(
-                |e| {
-                    error!(target: "payload_builder", %e, "failed to build spend nullifiers transaction");
+        for (pbh_entrypoint, nullifier_hashes) in nullifier_hashes_by_entrypoint {
+            let tx = spend_nullifiers_tx(self, builder.evm_mut(), pbh_entrypoint, nullifier_hashes)
+                .map_err(|e| {
+                    error!(target: "payload_builder", %e, %pbh_entrypoint, "failed to build spend nullifiers transaction");
                     PayloadBuilderError::Other(e.into())
-                },
-            )?;
+                })?;
 
             // Try to execute the builder tx. In the event that execution fails due to
             // insufficient funds, continue with the built payload. This ensures that
@@ -368,7 +414,7 @@ where
             match builder.execute_transaction(tx.clone()) {
                 Ok(gas_used) => self.commit_changes(info, base_fee, gas_used, tx),
                 Err(e) => {
-                    error!(target: "payload_builder", %e, "spend nullifiers transaction failed")
+                    error!(target: "payload_builder", %e, %pbh_entrypoint, "spend nullifiers transaction failed")
                 }
             }
         }
@@ -423,8 +469,7 @@ where
             inner: Arc::new(inner),
             client: provider.clone(),
             verified_blockspace_capacity: self.verified_blockspace_capacity,
-            pbh_entry_point: self.pbh_entry_point,
-            pbh_signature_aggregator: self.pbh_signature_aggregator,
+            pbh_entrypoints: self.pbh_entrypoints.clone(),
             builder_private_key: self.builder_private_key.clone(),
         }
     }
@@ -437,9 +482,15 @@ pub const fn dyn_gas_limit(len: u64) -> u64 {
     FIXED_GAS + len * COLD_SSTORE_GAS
 }
 
+/// Builds the builder transaction spending `nullifier_hashes` on the PBHEntryPoint at
+/// `pbh_entrypoint`.
+///
+/// The nonce is read from `evm`, so one transaction per entrypoint can be built and executed in
+/// sequence.
 pub fn spend_nullifiers_tx<DB, EVM, Client>(
     ctx: &WorldChainPayloadBuilderCtx<Client>,
     evm: &mut EVM,
+    pbh_entrypoint: Address,
     nullifier_hashes: HashSet<Field>,
 ) -> eyre::Result<Recovered<OpTransactionSigned>>
 where
@@ -468,7 +519,7 @@ where
         .with_call(&spendNullifierHashesCall {
             _nullifierHashes: nullifier_hashes.into_iter().collect(),
         })
-        .to(ctx.pbh_entry_point)
+        .to(pbh_entrypoint)
         .build_typed_tx()
         .map_err(|e| eyre!("{:?}", e))?;
 
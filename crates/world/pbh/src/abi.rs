@@ -0,0 +1,180 @@
+//! Solidity ABI bindings of the `PBHEntryPoint` contract and the ERC-4337 interfaces it builds
+//! on.
+//!
+//! These are the only bindings of the PBH types, every crate interacting with the contracts
+//! should use them rather than declaring its own.
+use alloy_sol_types::sol;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    external_nullifier::{EncodedExternalNullifier, ExternalNullifier},
+    payload::{self, Proof},
+};
+
+sol! {
+    /// The ABI encoding of a [`payload::PBHPayload`].
+    #[derive(Default, Debug, PartialEq, Eq)]
+    struct PBHPayload {
+        uint256 root;
+        uint256 pbhExternalNullifier;
+        uint256 nullifierHash;
+        uint256[8] proof;
+    }
+
+    contract IMulticall3 {
+        #[derive(Default)]
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+    }
+
+    contract IEntryPoint {
+        #[derive(Default, Serialize, Deserialize, Debug)]
+        struct PackedUserOperation {
+            address sender;
+            uint256 nonce;
+            bytes initCode;
+            bytes callData;
+            bytes32 accountGasLimits;
+            uint256 preVerificationGas;
+            bytes32 gasFees;
+            bytes paymasterAndData;
+            bytes signature;
+        }
+
+        #[derive(Default)]
+        struct UserOpsPerAggregator {
+            PackedUserOperation[] userOps;
+            address aggregator;
+            bytes signature;
+        }
+
+        error FailedOp(uint256 opIndex, string reason);
+
+        error FailedOpWithRevert(uint256 opIndex, string reason, bytes inner);
+    }
+
+    contract IAccount {
+        function validateUserOp(
+            IEntryPoint.PackedUserOperation calldata userOp,
+            bytes32 userOpHash,
+            uint256 missingAccountFunds
+        ) external returns (uint256 validationData);
+    }
+
+    contract IPaymaster {
+        function validatePaymasterUserOp(
+            IEntryPoint.PackedUserOperation calldata userOp,
+            bytes32 userOpHash,
+            uint256 maxCost
+        ) external returns (bytes memory context, uint256 validationData);
+    }
+
+    contract IPBHEntryPoint {
+        function handleAggregatedOps(
+            IEntryPoint.UserOpsPerAggregator[] calldata,
+            address payable
+        ) external;
+
+        function pbhMulticall(
+            IMulticall3.Call3[] calls,
+            PBHPayload payload,
+        ) external;
+
+        function spendNullifierHashes(uint256[] memory _nullifierHashes) external;
+
+        function getSignalHash(IEntryPoint.PackedUserOperation calldata userOp) external view returns (uint256);
+
+        event PBHEntryPointImplInitialized(
+            address indexed worldId,
+            address indexed entryPoint,
+            uint16 indexed numPbhPerMonth,
+            uint256 pbhGasLimit,
+            address[] authorizedBuilders,
+            address owner
+        );
+
+        event NumPbhPerMonthSet(uint16 indexed numPbhPerMonth);
+
+        event PBHGasLimitSet(uint256 indexed pbhGasLimit);
+    }
+}
+
+impl From<&payload::PBHPayload> for PBHPayload {
+    fn from(val: &payload::PBHPayload) -> Self {
+        Self {
+            root: val.root,
+            pbhExternalNullifier: EncodedExternalNullifier::from(val.external_nullifier).0,
+            nullifierHash: val.nullifier_hash,
+            proof: val.proof.flatten(),
+        }
+    }
+}
+
+impl From<payload::PBHPayload> for PBHPayload {
+    fn from(val: payload::PBHPayload) -> Self {
+        Self::from(&val)
+    }
+}
+
+impl TryFrom<PBHPayload> for payload::PBHPayload {
+    type Error = alloy_rlp::Error;
+
+    fn try_from(val: PBHPayload) -> Result<Self, Self::Error> {
+        Ok(Self {
+            external_nullifier: ExternalNullifier::try_from(EncodedExternalNullifier(
+                val.pbhExternalNullifier,
+            ))?,
+            nullifier_hash: val.nullifierHash,
+            root: val.root,
+            proof: Proof::from_flat(val.proof),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use alloy_sol_types::SolValue;
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::external_nullifier::Period;
+
+    fn payload(external_nullifier: ExternalNullifier) -> payload::PBHPayload {
+        payload::PBHPayload {
+            external_nullifier,
+            nullifier_hash: U256::from(7),
+            root: U256::from(11),
+            proof: Proof::from_flat(std::array::from_fn(|i| U256::from(i + 1))),
+        }
+    }
+
+    #[test]
+    fn abi_round_trip() {
+        for external_nullifier in [
+            ExternalNullifier::v1(1, 2025, 3),
+            ExternalNullifier::v2(
+                Period::Weekly,
+                NaiveDate::from_ymd_opt(2025, 6, 18).unwrap(),
+                5,
+            ),
+        ] {
+            let payload = payload(external_nullifier);
+            let encoded = PBHPayload::from(&payload).abi_encode();
+            let decoded = PBHPayload::abi_decode(&encoded).unwrap();
+            assert_eq!(payload::PBHPayload::try_from(decoded).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn abi_invalid_external_nullifier() {
+        let abi = PBHPayload {
+            pbhExternalNullifier: U256::from(3),
+            ..PBHPayload::from(payload(ExternalNullifier::v1(1, 2025, 0)))
+        };
+        assert!(payload::PBHPayload::try_from(abi).is_err());
+    }
+}
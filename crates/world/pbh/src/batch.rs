@@ -0,0 +1,215 @@
+//! Randomized batch verification of semaphore Groth16 proofs.
+//!
+//! A Groth16 proof `(A, B, C)` for public inputs `x` is valid iff
+//! `e(A, B) = e(α, β) · e(IC(x), γ) · e(C, δ)`. Given `n` proofs sharing a verifying key,
+//! each equation is raised to a random scalar `r_i` and the results are multiplied together,
+//! so that the whole batch is checked with `n + 3` miller loops and a single final
+//! exponentiation:
+//!
+//! `Π e(r_i·A_i, B_i) · e(Σ r_i·IC(x_i), -γ) · e(Σ r_i·C_i, -δ) · e(-(Σ r_i)·α, β) = 1`
+//!
+//! The scalars are derived by hashing the entire batch, so a prover cannot choose proofs
+//! which cancel each other out.
+use std::sync::LazyLock;
+
+use alloy_primitives::{keccak256, U256};
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{One, PrimeField, Zero};
+use ark_groth16::{prepare_verifying_key, PreparedVerifyingKey};
+use semaphore_rs::Field;
+
+use crate::payload::TREE_DEPTH;
+
+/// The number of public inputs of the semaphore circuit.
+const NUM_INPUTS: usize = 4;
+
+/// The prepared verifying key of the semaphore circuit at [`TREE_DEPTH`].
+static VERIFYING_KEY: LazyLock<PreparedVerifyingKey<Bn254>> =
+    LazyLock::new(|| prepare_verifying_key(&semaphore_rs::circuit::zkey(TREE_DEPTH).0.vk));
+
+/// A decompressed semaphore proof along with its public inputs.
+#[derive(Debug, Clone)]
+pub(crate) struct BatchEntry {
+    proof: ark_groth16::Proof<Bn254>,
+    inputs: [Fr; NUM_INPUTS],
+    /// Transcript of the proof and inputs used to derive the batch randomness.
+    transcript: [U256; 8 + NUM_INPUTS],
+}
+
+impl BatchEntry {
+    /// Creates a new [`BatchEntry`] for the semaphore public inputs
+    /// `[root, nullifier_hash, signal, external_nullifier]`.
+    ///
+    /// Returns `None` if any public input is not a canonical field element, or if the proof
+    /// contains points which are not on the curve or in the correct subgroup.
+    pub(crate) fn new(
+        proof: &semaphore_rs::protocol::Proof,
+        inputs: [Field; NUM_INPUTS],
+    ) -> Option<Self> {
+        let modulus = U256::from_limbs(Fr::MODULUS.0);
+        if inputs.iter().any(|input| *input >= modulus) {
+            return None;
+        }
+
+        let mut transcript = [U256::ZERO; 8 + NUM_INPUTS];
+        transcript[..8].copy_from_slice(&proof.flatten());
+        transcript[8..].copy_from_slice(&inputs);
+
+        let proof: ark_groth16::Proof<Bn254> = (*proof).into();
+        let points_valid = proof.a.is_on_curve()
+            && proof.b.is_on_curve()
+            && proof.b.is_in_correct_subgroup_assuming_on_curve()
+            && proof.c.is_on_curve();
+        if !points_valid {
+            return None;
+        }
+
+        Some(Self {
+            proof,
+            inputs: inputs.map(|input| Fr::from_le_bytes_mod_order(&input.to_le_bytes::<32>())),
+            transcript,
+        })
+    }
+}
+
+/// Verifies all entries against the semaphore verifying key in a single randomized check.
+///
+/// Returns `true` only if every proof in the batch is valid (with overwhelming probability).
+/// If `false` is returned at least one proof is invalid, and the entries must be verified
+/// individually to find out which.
+pub(crate) fn batch_verify(entries: &[BatchEntry]) -> bool {
+    if entries.is_empty() {
+        return true;
+    }
+
+    let pvk = &*VERIFYING_KEY;
+    let scalars = batch_scalars(entries);
+
+    let mut g1 = Vec::with_capacity(entries.len() + 3);
+    let mut g2 = Vec::with_capacity(entries.len() + 3);
+    for (entry, r) in entries.iter().zip(&scalars) {
+        g1.push((entry.proof.a * r).into_affine());
+        g2.push(<Bn254 as Pairing>::G2Prepared::from(entry.proof.b));
+    }
+
+    // Σ r_i·IC(x_i) = (Σ r_i)·γ_abc[0] + Σ_j (Σ_i r_i·x_ij)·γ_abc[j + 1]
+    let r_sum: Fr = scalars.iter().sum();
+    let mut ic_scalars = [Fr::zero(); NUM_INPUTS + 1];
+    ic_scalars[0] = r_sum;
+    for (entry, r) in entries.iter().zip(&scalars) {
+        for (acc, input) in ic_scalars[1..].iter_mut().zip(&entry.inputs) {
+            *acc += *r * input;
+        }
+    }
+    let ic = G1Projective::msm_unchecked(&pvk.vk.gamma_abc_g1, &ic_scalars);
+
+    let c_bases: Vec<G1Affine> = entries.iter().map(|entry| entry.proof.c).collect();
+    let c = G1Projective::msm_unchecked(&c_bases, &scalars);
+
+    let alpha = pvk.vk.alpha_g1.into_group() * -r_sum;
+
+    g1.push(ic.into_affine());
+    g2.push(pvk.gamma_g2_neg_pc.clone());
+    g1.push(c.into_affine());
+    g2.push(pvk.delta_g2_neg_pc.clone());
+    g1.push(alpha.into_affine());
+    g2.push(pvk.vk.beta_g2.into());
+
+    let g1: Vec<<Bn254 as Pairing>::G1Prepared> = g1.into_iter().map(Into::into).collect();
+    let miller_loop = Bn254::multi_miller_loop(g1, g2);
+
+    Bn254::final_exponentiation(miller_loop).is_some_and(|output| output.0.is_one())
+}
+
+/// Derives one random scalar per entry from a hash of the whole batch.
+fn batch_scalars(entries: &[BatchEntry]) -> Vec<Fr> {
+    let mut transcript = Vec::with_capacity(entries.len() * (8 + NUM_INPUTS) * 32);
+    for entry in entries {
+        for word in &entry.transcript {
+            transcript.extend_from_slice(&word.to_be_bytes::<32>());
+        }
+    }
+    let seed = keccak256(&transcript);
+
+    (0..entries.len() as u64)
+        .map(|i| {
+            let mut preimage = [0u8; 40];
+            preimage[..32].copy_from_slice(seed.as_slice());
+            preimage[32..].copy_from_slice(&i.to_be_bytes());
+            let r = Fr::from_be_bytes_mod_order(keccak256(preimage).as_slice());
+            // A zero scalar would exclude the proof from the check entirely.
+            if r.is_zero() {
+                Fr::one()
+            } else {
+                r
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use semaphore_rs::{
+        identity::Identity,
+        poseidon_tree::LazyPoseidonTree,
+        protocol::{generate_nullifier_hash, generate_proof},
+    };
+
+    use super::*;
+
+    fn entries(n: usize) -> Vec<BatchEntry> {
+        let mut tree = LazyPoseidonTree::new_with_dense_prefix(TREE_DEPTH, 0, &U256::ZERO);
+        let identities: Vec<_> = (0..n)
+            .map(|i| Identity::from_secret(&mut [i as u8, 1, 2, 3], None))
+            .collect();
+        for (i, identity) in identities.iter().enumerate() {
+            tree = tree.update_with_mutation(i, &identity.commitment());
+        }
+
+        identities
+            .iter()
+            .enumerate()
+            .map(|(i, identity)| {
+                let external_nullifier = U256::from(i);
+                let signal = U256::from(i * 7);
+                let proof =
+                    generate_proof(identity, &tree.proof(i), external_nullifier, signal).unwrap();
+                let nullifier_hash = generate_nullifier_hash(identity, external_nullifier);
+                BatchEntry::new(
+                    &proof,
+                    [tree.root(), nullifier_hash, signal, external_nullifier],
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_verify_valid() {
+        assert!(batch_verify(&entries(3)));
+    }
+
+    #[test]
+    fn batch_verify_invalid_input() {
+        let mut entries = entries(3);
+        entries[1].inputs[2] += Fr::one();
+        assert!(!batch_verify(&entries));
+    }
+
+    #[test]
+    fn batch_verify_swapped_proofs() {
+        let mut entries = entries(2);
+        let proof = entries[0].proof.clone();
+        entries[0].proof = entries[1].proof.clone();
+        entries[1].proof = proof;
+        assert!(!batch_verify(&entries));
+    }
+
+    #[test]
+    fn non_canonical_input_is_rejected() {
+        let proof = semaphore_rs::protocol::Proof::from_flat([U256::ZERO; 8]);
+        let modulus = U256::from_limbs(Fr::MODULUS.0);
+        assert!(BatchEntry::new(&proof, [modulus, U256::ZERO, U256::ZERO, U256::ZERO]).is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use std::{fmt::Debug, sync::Arc};
+
+use chrono::{DateTime, Utc};
+
+/// A source of the current time used when validating PBH payloads.
+///
+/// The date marker of an external nullifier is only valid for the period it was generated for,
+/// so validation is inherently time dependent. Abstracting over the time source allows the
+/// pool to validate against wall-clock time while the payload builder, tests and replay
+/// tooling validate against a block timestamp.
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+/// A [`Clock`] backed by the system wall-clock.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A [`Clock`] that always returns the same instant.
+///
+/// Typically constructed from a block timestamp to validate PBH payloads
+/// against the time at which they are (or were) included on chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl FixedClock {
+    /// Creates a new [`FixedClock`] at the given instant.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(now)
+    }
+
+    /// Creates a new [`FixedClock`] from a unix timestamp in seconds.
+    ///
+    /// Returns `None` if the timestamp is out of range.
+    pub fn from_timestamp(timestamp: u64) -> Option<Self> {
+        let secs = i64::try_from(timestamp).ok()?;
+        DateTime::from_timestamp(secs, 0).map(Self)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn fixed_clock_from_timestamp() {
+        let clock = FixedClock::from_timestamp(1_735_689_600).unwrap();
+        assert_eq!(
+            clock.now(),
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn fixed_clock_out_of_range() {
+        assert!(FixedClock::from_timestamp(u64::MAX).is_none());
+    }
+
+    #[test]
+    fn clock_trait_objects() {
+        let clock: Arc<dyn Clock> = Arc::new(FixedClock::from_timestamp(0).unwrap());
+        assert_eq!(clock.now(), DateTime::UNIX_EPOCH);
+    }
+}
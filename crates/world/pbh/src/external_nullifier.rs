@@ -3,19 +3,90 @@ use std::str::FromStr;
 use alloy_primitives::U256;
 use alloy_rlp::{Decodable, Encodable};
 use bon::Builder;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
 use crate::date_marker::DateMarker;
 
-#[derive(Display, Default, EnumString, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(
+    Display, Default, EnumString, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
 #[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Prefix {
+    /// Monthly external nullifier encoded in the lower 48 bits.
     #[default]
     V1 = 1,
+    /// External nullifier with a configurable [`Period`], encoded in the lower 64 bits.
+    V2 = 2,
 }
 
-#[derive(Builder, Debug, Default, Clone, Copy, PartialEq, Eq)]
+impl TryFrom<u8> for Prefix {
+    type Error = alloy_rlp::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(alloy_rlp::Error::Custom(
+                "invalid external nullifier version",
+            )),
+        }
+    }
+}
+
+/// The period over which the PBH nonce of an external nullifier is rate limited.
+#[derive(
+    Display, Default, EnumString, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum Period {
+    /// Calendar months, starting on the first day of the month.
+    #[default]
+    Monthly = 0,
+    /// ISO weeks, starting on Monday.
+    Weekly = 1,
+    /// Calendar days.
+    Daily = 2,
+}
+
+impl Period {
+    /// Returns the first day of the period containing `date`.
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Monthly => date.with_day(1).expect("first day of month is valid"),
+            Self::Weekly => date - chrono::Days::new(date.weekday().num_days_from_monday() as u64),
+            Self::Daily => date,
+        }
+    }
+}
+
+impl TryFrom<u8> for Period {
+    type Error = alloy_rlp::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Monthly),
+            1 => Ok(Self::Weekly),
+            2 => Ok(Self::Daily),
+            _ => Err(alloy_rlp::Error::Custom(
+                "invalid external nullifier period",
+            )),
+        }
+    }
+}
+
+/// A PBH external nullifier.
+///
+/// Serializes to JSON with human-readable fields, e.g.
+/// `{"version":"v1","year":2025,"month":1,"nonce":0}`. The `day` and `period` fields are only
+/// present for [`Prefix::V2`].
+#[derive(Builder, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "ExternalNullifierJson", try_from = "ExternalNullifierJson")]
 pub struct ExternalNullifier {
     #[builder(default = Prefix::V1)]
     pub version: Prefix,
@@ -25,12 +96,29 @@ pub struct ExternalNullifier {
     pub month: u8,
     #[builder(default = 0)]
     pub nonce: u16,
+    /// The day of the month the period starts on. Always `0` for [`Prefix::V1`].
+    #[builder(default = 0)]
+    pub day: u8,
+    /// The rate limiting period. Always [`Period::Monthly`] for [`Prefix::V1`].
+    #[builder(default)]
+    pub period: Period,
 }
 
-/// The encoding format is as follows:
+/// The encoding format is versioned by the lowest byte.
+///
+/// [`Prefix::V1`]:
 ///      - Bits:48-255: Empty
-///      - Bits 40-47: Year
-///      - Bits 24-39: Month
+///      - Bits 32-47: Year
+///      - Bits 24-31: Month
+///      - Bits 8-23: Nonce
+///      - Bits 0-7: Version
+///
+/// [`Prefix::V2`]:
+///      - Bits:64-255: Empty
+///      - Bits 56-63: Period
+///      - Bits 48-55: Day
+///      - Bits 32-47: Year
+///      - Bits 24-31: Month
 ///      - Bits 8-23: Nonce
 ///      - Bits 0-7: Version
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -47,22 +135,60 @@ impl ExternalNullifier {
             year,
             month,
             nonce,
+            day: 0,
+            period: Period::Monthly,
+        }
+    }
+
+    /// Creates a [`Prefix::V2`] external nullifier for the `period` containing `date`.
+    pub fn v2(period: Period, date: NaiveDate, nonce: u16) -> Self {
+        let start = period.start(date);
+        Self {
+            version: Prefix::V2,
+            year: start.year() as u16,
+            month: start.month() as u8,
+            nonce,
+            day: start.day() as u8,
+            period,
         }
     }
 
-    pub fn date_marker(&self) -> DateMarker {
-        DateMarker::new(self.year as i32, self.month as u32)
+    /// Returns the month of a [`Prefix::V1`] external nullifier.
+    ///
+    /// Returns `None` for [`Prefix::V2`], whose period is not necessarily a month. Use
+    /// [`Self::period_start`] to compare external nullifiers of any version.
+    pub fn date_marker(&self) -> Option<DateMarker> {
+        match self.version {
+            Prefix::V1 => Some(DateMarker::new(self.year as i32, self.month as u32)),
+            Prefix::V2 => None,
+        }
+    }
+
+    /// Returns the first day of the period this external nullifier is valid for.
+    ///
+    /// Returns `None` if the encoded date is invalid.
+    pub fn period_start(&self) -> Option<NaiveDate> {
+        let day = match self.version {
+            Prefix::V1 => 1,
+            Prefix::V2 => self.day as u32,
+        };
+        NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, day)
     }
 }
 
 impl From<ExternalNullifier> for EncodedExternalNullifier {
     fn from(e: ExternalNullifier) -> Self {
-        EncodedExternalNullifier(U256::from(
-            (e.year as u64) << 32
-                | (e.month as u64) << 24
-                | (e.nonce as u64) << 8
-                | e.version as u64,
-        ))
+        let word = (e.year as u64) << 32
+            | (e.month as u64) << 24
+            | (e.nonce as u64) << 8
+            | e.version as u64;
+
+        let word = match e.version {
+            Prefix::V1 => word,
+            Prefix::V2 => (e.period as u64) << 56 | (e.day as u64) << 48 | word,
+        };
+
+        EncodedExternalNullifier(U256::from(word))
     }
 }
 
@@ -70,7 +196,7 @@ impl TryFrom<EncodedExternalNullifier> for ExternalNullifier {
     type Error = alloy_rlp::Error;
 
     fn try_from(value: EncodedExternalNullifier) -> Result<Self, Self::Error> {
-        if value.0 > U256::from(1) << 48 {
+        if value.0 >= U256::from(1) << 64 {
             return Err(alloy_rlp::Error::Custom("invalid external nullifier"));
         }
 
@@ -78,20 +204,78 @@ impl TryFrom<EncodedExternalNullifier> for ExternalNullifier {
         let year = (word >> 32) as u16;
         let month = ((word >> 24) & 0xFF) as u8;
         let nonce = ((word >> 8) & 0xFFFF) as u16;
-        let version = (word & 0xFF) as u8;
+        let version = Prefix::try_from((word & 0xFF) as u8)?;
 
-        if version != Prefix::V1 as u8 {
-            return Err(alloy_rlp::Error::Custom(
-                "invalid external nullifier version",
-            ));
+        match version {
+            Prefix::V1 => {
+                if word >> 48 != 0 {
+                    return Err(alloy_rlp::Error::Custom("invalid external nullifier"));
+                }
+
+                Ok(Self::v1(month, year, nonce))
+            }
+            Prefix::V2 => Ok(Self {
+                version,
+                year,
+                month,
+                nonce,
+                day: ((word >> 48) & 0xFF) as u8,
+                period: Period::try_from((word >> 56) as u8)?,
+            }),
         }
+    }
+}
 
-        Ok(Self {
-            version: Prefix::V1,
-            year,
-            month,
-            nonce,
-        })
+/// The JSON representation of an [`ExternalNullifier`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ExternalNullifierJson {
+    version: Prefix,
+    year: u16,
+    month: u8,
+    nonce: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    day: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    period: Option<Period>,
+}
+
+impl From<ExternalNullifier> for ExternalNullifierJson {
+    fn from(e: ExternalNullifier) -> Self {
+        let (day, period) = match e.version {
+            Prefix::V1 => (None, None),
+            Prefix::V2 => (Some(e.day), Some(e.period)),
+        };
+
+        Self {
+            version: e.version,
+            year: e.year,
+            month: e.month,
+            nonce: e.nonce,
+            day,
+            period,
+        }
+    }
+}
+
+impl TryFrom<ExternalNullifierJson> for ExternalNullifier {
+    type Error = alloy_rlp::Error;
+
+    fn try_from(value: ExternalNullifierJson) -> Result<Self, Self::Error> {
+        match (value.version, value.day, value.period) {
+            (Prefix::V1, None, None) => Ok(Self::v1(value.month, value.year, value.nonce)),
+            (Prefix::V2, Some(day), Some(period)) => Ok(Self {
+                version: Prefix::V2,
+                year: value.year,
+                month: value.month,
+                nonce: value.nonce,
+                day,
+                period,
+            }),
+            _ => Err(alloy_rlp::Error::Custom(
+                "invalid external nullifier fields for version",
+            )),
+        }
     }
 }
 
@@ -147,6 +331,9 @@ mod tests {
 
     #[test_case(ExternalNullifier::v1(1, 2025, 11))]
     #[test_case(ExternalNullifier::v1(12, 3078, 19))]
+    #[test_case(ExternalNullifier::v2(Period::Monthly, date(2025, 1, 17), 11))]
+    #[test_case(ExternalNullifier::v2(Period::Weekly, date(2025, 1, 17), 11))]
+    #[test_case(ExternalNullifier::v2(Period::Daily, date(2025, 1, 17), u16::MAX))]
     fn parse_external_nulliifer_roundtrip(e: ExternalNullifier) {
         let s = e.to_string();
         let actual: ExternalNullifier = s.parse().unwrap();
@@ -156,6 +343,7 @@ mod tests {
 
     #[test_case(ExternalNullifier::v1(1, 2025, 11))]
     #[test_case(ExternalNullifier::v1(12, 3078, 19))]
+    #[test_case(ExternalNullifier::v2(Period::Weekly, date(2024, 12, 31), 3))]
     fn rlp_roundtrip(e: ExternalNullifier) {
         let mut buffer = vec![];
         e.encode(&mut buffer);
@@ -167,4 +355,69 @@ mod tests {
         let decoded = EncodedExternalNullifier::decode(&mut buffer.as_slice()).unwrap();
         assert_eq!(encoded, decoded);
     }
+
+    #[test_case(ExternalNullifier::v1(1, 2025, 11))]
+    #[test_case(ExternalNullifier::v2(Period::Weekly, date(2024, 12, 31), 3))]
+    fn json_roundtrip(e: ExternalNullifier) {
+        let json = serde_json::to_string(&e).unwrap();
+        let decoded: ExternalNullifier = serde_json::from_str(&json).unwrap();
+        assert_eq!(e, decoded);
+    }
+
+    #[test]
+    fn json_format() {
+        assert_eq!(
+            serde_json::to_value(ExternalNullifier::v1(1, 2025, 11)).unwrap(),
+            serde_json::json!({"version": "v1", "year": 2025, "month": 1, "nonce": 11})
+        );
+        assert_eq!(
+            serde_json::to_value(ExternalNullifier::v2(Period::Weekly, date(2025, 1, 17), 3))
+                .unwrap(),
+            serde_json::json!({
+                "version": "v2",
+                "year": 2025,
+                "month": 1,
+                "nonce": 3,
+                "day": 13,
+                "period": "weekly"
+            })
+        );
+    }
+
+    #[test_case(r#"{"version":"v1","year":2025,"month":1,"nonce":0,"day":1}"# ; "v1 with day")]
+    #[test_case(r#"{"version":"v2","year":2025,"month":1,"nonce":0}"# ; "v2 without period")]
+    #[test_case(r#"{"version":"v3","year":2025,"month":1,"nonce":0}"# ; "unknown version")]
+    fn json_invalid(json: &str) {
+        assert!(serde_json::from_str::<ExternalNullifier>(json).is_err());
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn v1_encoding_is_unchanged() {
+        let encoded = EncodedExternalNullifier::from(ExternalNullifier::v1(1, 2025, 11));
+        assert_eq!(encoded.0, U256::from(2025u64 << 32 | 1 << 24 | 11 << 8 | 1));
+    }
+
+    #[test_case(Period::Monthly, date(2025, 1, 17), date(2025, 1, 1))]
+    #[test_case(Period::Weekly, date(2025, 1, 17), date(2025, 1, 13))]
+    #[test_case(Period::Weekly, date(2025, 1, 1), date(2024, 12, 30))]
+    #[test_case(Period::Daily, date(2025, 1, 17), date(2025, 1, 17))]
+    fn period_start(period: Period, date: NaiveDate, expected: NaiveDate) {
+        assert_eq!(period.start(date), expected);
+        assert_eq!(
+            ExternalNullifier::v2(period, date, 0).period_start(),
+            Some(expected)
+        );
+    }
+
+    #[test_case(U256::from(1u64 << 48 | 1) ; "v1 with high bits set")]
+    #[test_case(U256::from(1) << 64 | U256::from(2) ; "v2 with high bits set")]
+    #[test_case(U256::from(3u64 << 56 | 2) ; "v2 with unknown period")]
+    #[test_case(U256::from(3u64) ; "unknown version")]
+    fn invalid_encoding(word: U256) {
+        assert!(ExternalNullifier::try_from(EncodedExternalNullifier(word)).is_err());
+    }
 }
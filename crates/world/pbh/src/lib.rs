@@ -1,5 +1,9 @@
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 
+pub mod abi;
+mod batch;
+pub mod clock;
 pub mod date_marker;
 pub mod external_nullifier;
+pub mod nonce;
 pub mod payload;
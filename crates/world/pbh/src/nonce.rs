@@ -0,0 +1,84 @@
+//! Inspection of the PBH nonces consumed by a World ID within a period.
+use semaphore_rs::{identity::Identity, protocol::generate_nullifier_hash, Field};
+
+use crate::{
+    date_marker::DateMarker,
+    external_nullifier::{EncodedExternalNullifier, ExternalNullifier},
+};
+
+/// Returns the external nullifiers of every PBH nonce available in the month of `date_marker`,
+/// ordered by nonce.
+pub fn external_nullifiers(
+    date_marker: DateMarker,
+    pbh_nonce_limit: u16,
+) -> impl Iterator<Item = ExternalNullifier> {
+    (0..pbh_nonce_limit).map(move |nonce| ExternalNullifier::with_date_marker(date_marker, nonce))
+}
+
+/// Returns the nullifier hashes of `identity` for every PBH nonce available in the month of
+/// `date_marker`, ordered by nonce.
+pub fn nullifier_hashes(
+    identity: &Identity,
+    date_marker: DateMarker,
+    pbh_nonce_limit: u16,
+) -> Vec<Field> {
+    external_nullifiers(date_marker, pbh_nonce_limit)
+        .map(|external_nullifier| {
+            generate_nullifier_hash(
+                identity,
+                EncodedExternalNullifier::from(external_nullifier).0,
+            )
+        })
+        .collect()
+}
+
+/// Returns the first nonce whose nullifier hash is contained in `unspent`.
+///
+/// `nullifier_hashes` must be ordered by nonce, as returned by [`nullifier_hashes`]. `unspent`
+/// is the subset of them which has not been spent on chain, as returned by the
+/// `getUnspentNullifierHashes` function of the `PBHEntryPoint`.
+///
+/// Returns `None` if all nonces of the period have been consumed.
+pub fn first_unspent_nonce(nullifier_hashes: &[Field], unspent: &[Field]) -> Option<u16> {
+    nullifier_hashes
+        .iter()
+        .position(|hash| unspent.contains(hash))
+        .map(|nonce| nonce as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_nullifiers_are_ordered_by_nonce() {
+        let date_marker = DateMarker::new(2025, 1);
+        let external_nullifiers: Vec<_> = external_nullifiers(date_marker, 3).collect();
+        assert_eq!(
+            external_nullifiers,
+            vec![
+                ExternalNullifier::v1(1, 2025, 0),
+                ExternalNullifier::v1(1, 2025, 1),
+                ExternalNullifier::v1(1, 2025, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn nullifier_hashes_are_distinct() {
+        let identity = Identity::from_secret(&mut [1, 2, 3], None);
+        let hashes = nullifier_hashes(&identity, DateMarker::new(2025, 1), 4);
+        assert_eq!(hashes.len(), 4);
+        for (i, hash) in hashes.iter().enumerate() {
+            assert!(!hashes[i + 1..].contains(hash));
+        }
+    }
+
+    #[test]
+    fn first_unspent() {
+        let hashes: Vec<_> = (0..4u64).map(Field::from).collect();
+        assert_eq!(first_unspent_nonce(&hashes, &hashes), Some(0));
+        assert_eq!(first_unspent_nonce(&hashes, &hashes[2..]), Some(2));
+        assert_eq!(first_unspent_nonce(&hashes, &[]), None);
+    }
+}
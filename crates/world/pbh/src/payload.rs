@@ -1,86 +1,335 @@
 use crate::{
-    date_marker::DateMarker,
-    external_nullifier::{EncodedExternalNullifier, ExternalNullifier},
+    batch,
+    clock::Clock,
+    external_nullifier::{EncodedExternalNullifier, ExternalNullifier, Prefix},
 };
-use alloy_primitives::U256;
+use alloy_primitives::{Address, B256, U256};
 use alloy_rlp::{Decodable, Encodable, RlpDecodable, RlpEncodable};
+use chrono::NaiveDate;
 use semaphore_rs::{
+    identity::Identity,
     packed_proof::PackedProof,
-    protocol::{verify_proof, ProofError},
+    protocol::{generate_nullifier_hash, generate_proof, verify_proof, ProofError},
     Field,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashSet, hash::BuildHasher, time::Duration};
 use thiserror::Error;
 
 pub const TREE_DEPTH: usize = 30;
-const LEN: usize = 256;
 
-pub type ProofBytes = [u8; LEN];
+/// A set of World ID roots which PBH payloads may be proven against.
+pub trait RootSet {
+    /// Returns `true` if `root` is contained in the set.
+    fn contains_root(&self, root: &Field) -> bool;
+}
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Proof(pub semaphore_rs::protocol::Proof);
+impl RootSet for [Field] {
+    fn contains_root(&self, root: &Field) -> bool {
+        self.contains(root)
+    }
+}
+
+impl<const N: usize> RootSet for [Field; N] {
+    fn contains_root(&self, root: &Field) -> bool {
+        self.contains(root)
+    }
+}
+
+impl RootSet for Vec<Field> {
+    fn contains_root(&self, root: &Field) -> bool {
+        self.contains(root)
+    }
+}
+
+impl<S: BuildHasher> RootSet for HashSet<Field, S> {
+    fn contains_root(&self, root: &Field) -> bool {
+        self.contains(root)
+    }
+}
+
+/// The length in bytes of the RLP payload of a [`Proof::Full`].
+const FULL_LEN: usize = 256;
+/// The length in bytes of the RLP payload of a [`Proof::Compressed`].
+const COMPRESSED_LEN: usize = 128;
+
+pub type ProofBytes = [u8; FULL_LEN];
+
+/// A semaphore proof, either in full or in compressed form.
+///
+/// Both forms are accepted by the `PBHEntryPoint` contract, which takes proofs as a flat
+/// `uint256[8]`. A compressed proof occupies the first four words with the remaining words
+/// set to zero, see [`Proof::from_flat`] and [`Proof::flatten`].
+///
+/// The RLP encoding is a 256 byte string for a full proof and a 128 byte string for a
+/// compressed proof. The JSON encoding is an array of eight or four words respectively.
+///
+/// As on chain, a proof in the full layout whose last four words are zero is a compressed proof,
+/// regardless of whether it was decoded from the ABI, RLP or JSON encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Proof {
+    /// An uncompressed semaphore proof.
+    Full(semaphore_rs::protocol::Proof),
+    /// A compressed semaphore proof.
+    Compressed([U256; 4]),
+}
 
 impl Default for Proof {
     fn default() -> Self {
-        let proof = semaphore_rs::protocol::Proof(
-            (U256::ZERO, U256::ZERO),
-            ([U256::ZERO, U256::ZERO], [U256::ZERO, U256::ZERO]),
-            (U256::ZERO, U256::ZERO),
-        );
+        Proof::from_flat([U256::ZERO; 8])
+    }
+}
+
+impl From<semaphore_rs::protocol::Proof> for Proof {
+    fn from(proof: semaphore_rs::protocol::Proof) -> Self {
+        Proof::from_flat(proof.flatten())
+    }
+}
+
+impl Proof {
+    /// Creates a [`Proof`] from the flat `uint256[8]` layout used by the ABI encoding.
+    ///
+    /// A proof with the last four words set to zero is a [`Proof::Compressed`].
+    pub fn from_flat(flat: [U256; 8]) -> Self {
+        if flat[4..].iter().all(U256::is_zero) {
+            Proof::Compressed([flat[0], flat[1], flat[2], flat[3]])
+        } else {
+            Proof::Full(semaphore_rs::protocol::Proof::from_flat(flat))
+        }
+    }
+
+    /// Returns the flat `uint256[8]` layout of the proof used by the ABI encoding.
+    pub fn flatten(&self) -> [U256; 8] {
+        match self {
+            Proof::Full(proof) => proof.flatten(),
+            Proof::Compressed(words) => [
+                words[0],
+                words[1],
+                words[2],
+                words[3],
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+            ],
+        }
+    }
+
+    /// Returns the proof in compressed form.
+    ///
+    /// Returns `None` if the proof cannot be compressed.
+    pub fn compress(&self) -> Option<Self> {
+        match self {
+            Proof::Full(proof) => {
+                let compressed = semaphore_rs_proof::compression::compress_proof(*proof)?;
+                Some(Proof::Compressed(compressed.flatten()))
+            }
+            Proof::Compressed(_) => Some(*self),
+        }
+    }
 
-        Proof(proof)
+    /// Returns the full semaphore proof, decompressing it if necessary.
+    ///
+    /// Returns `None` if a compressed proof does not encode valid curve points.
+    pub fn decompress(&self) -> Option<semaphore_rs::protocol::Proof> {
+        match self {
+            Proof::Full(proof) => Some(*proof),
+            Proof::Compressed(words) => semaphore_rs_proof::compression::decompress_proof(
+                semaphore_rs_proof::compression::CompressedProof::from_flat(*words),
+            ),
+        }
+    }
+}
+
+impl Serialize for Proof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Proof::Full(proof) => proof.flatten().serialize(serializer),
+            Proof::Compressed(words) => words.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Proof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let words = Vec::<U256>::deserialize(deserializer)?;
+        if let Ok(flat) = <[U256; 8]>::try_from(words.as_slice()) {
+            Ok(Proof::from_flat(flat))
+        } else if let Ok(words) = <[U256; 4]>::try_from(words.as_slice()) {
+            Ok(Proof::Compressed(words))
+        } else {
+            Err(serde::de::Error::invalid_length(
+                words.len(),
+                &"a full proof of 8 words or a compressed proof of 4 words",
+            ))
+        }
     }
 }
 
 impl Decodable for Proof {
     fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
-        let bytes = ProofBytes::decode(buf)?;
-        Ok(Proof(PackedProof(bytes).into()))
+        let bytes = alloy_rlp::Header::decode_bytes(buf, false)?;
+        match bytes.len() {
+            FULL_LEN => {
+                let bytes: ProofBytes = bytes.try_into().expect("length is checked");
+                let proof: semaphore_rs::protocol::Proof = PackedProof(bytes).into();
+                Ok(proof.into())
+            }
+            COMPRESSED_LEN => Ok(Proof::Compressed(std::array::from_fn(|i| {
+                U256::from_be_slice(&bytes[i * 32..(i + 1) * 32])
+            }))),
+            _ => Err(alloy_rlp::Error::Custom("invalid proof length")),
+        }
     }
 }
 
 impl Encodable for Proof {
     fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
-        let PackedProof(bytes) = self.0.into();
-        bytes.encode(out)
+        match self {
+            Proof::Full(proof) => {
+                let PackedProof(bytes) = (*proof).into();
+                bytes.encode(out)
+            }
+            Proof::Compressed(words) => {
+                let mut bytes = [0u8; COMPRESSED_LEN];
+                for (chunk, word) in bytes.chunks_exact_mut(32).zip(words) {
+                    chunk.copy_from_slice(&word.to_be_bytes::<32>());
+                }
+                bytes.encode(out)
+            }
+        }
     }
 
     fn length(&self) -> usize {
-        LEN + 3
+        let len = match self {
+            Proof::Full(_) => FULL_LEN,
+            Proof::Compressed(_) => COMPRESSED_LEN,
+        };
+        alloy_rlp::length_of_length(len) + len
     }
 }
 
-#[derive(Error, Debug)]
+/// The reason a PBH payload, or the transaction carrying it, is invalid.
+///
+/// Variants carry the context needed to diagnose the rejection, and serialize to a JSON object
+/// tagged by `reason` so they can be surfaced to RPC clients. See [`Self::code`] for the stable
+/// numeric identifier of each variant.
+#[derive(Error, Debug, Serialize)]
+#[serde(
+    tag = "reason",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
 pub enum PBHValidationError {
-    #[error("Invalid root")]
-    InvalidRoot,
-    #[error("Invalid external nullifier period")]
-    InvalidExternalNullifierPeriod,
-    #[error("Invalid external nullifier nonce")]
-    InvalidExternalNullifierNonce,
+    #[error("Invalid root {root}")]
+    InvalidRoot { root: Field },
+    #[error("Invalid external nullifier period: {external_nullifier}, expected {expected}")]
+    InvalidExternalNullifierPeriod {
+        /// The external nullifier of the payload.
+        external_nullifier: ExternalNullifier,
+        /// The first day of the period the external nullifier should belong to.
+        expected: NaiveDate,
+    },
+    #[error("Invalid external nullifier nonce: {nonce} is not below the limit of {limit}")]
+    InvalidExternalNullifierNonce { nonce: u16, limit: u16 },
     #[error("Invalid proof")]
     InvalidProof,
     #[error(transparent)]
-    ProofError(#[from] ProofError),
+    ProofError {
+        #[from]
+        #[serde(serialize_with = "serialize_display")]
+        source: ProofError,
+    },
     #[error("Invalid calldata encoding")]
     InvalidCalldata,
-    #[error("Missing PBH Payload")]
-    MissingPbhPayload,
-    #[error("InvalidSignatureAggregator")]
-    InvalidSignatureAggregator,
+    #[error("Missing PBH Payload: {payloads} payloads for {user_ops} user operations")]
+    MissingPbhPayload { user_ops: usize, payloads: usize },
+    #[error("InvalidSignatureAggregator: {aggregator}")]
+    InvalidSignatureAggregator { aggregator: Address },
     #[error("PBH call tracer error")]
     PBHCallTracerError,
-    #[error("PBH gas limit exceeded")]
-    PbhGasLimitExceeded,
-    #[error("Duplicate nullifier hash")]
-    DuplicateNullifierHash,
+    #[error("PBH gas limit exceeded: {gas_limit} exceeds the limit of {limit}")]
+    PbhGasLimitExceeded { gas_limit: u64, limit: u64 },
+    #[error("Duplicate nullifier hash {nullifier_hash}")]
+    DuplicateNullifierHash { nullifier_hash: Field },
+    #[error("Nullifier hash {nullifier_hash} already spent in block {block_number}")]
+    SpentNullifierHash {
+        nullifier_hash: Field,
+        /// The block in which the nullifier hash was spent.
+        block_number: U256,
+    },
+    #[error("Nullifier hash {nullifier_hash} already used by pooled transaction {tx_hash}")]
+    PooledNullifierHash {
+        nullifier_hash: Field,
+        /// The hash of the pooled transaction carrying the nullifier hash.
+        tx_hash: B256,
+    },
+    #[error("Unsupported external nullifier version {version}")]
+    UnsupportedExternalNullifierVersion { version: Prefix },
+    #[error("PBH entrypoint {entrypoint} is not active")]
+    InactiveEntrypoint { entrypoint: Address },
+}
+
+impl PBHValidationError {
+    /// Returns the stable numeric code of the error.
+    ///
+    /// Codes are exposed to RPC clients and must never be changed or reused.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::InvalidRoot { .. } => 1001,
+            Self::InvalidExternalNullifierPeriod { .. } => 1002,
+            Self::InvalidExternalNullifierNonce { .. } => 1003,
+            Self::InvalidProof => 1004,
+            Self::ProofError { .. } => 1005,
+            Self::InvalidCalldata => 1006,
+            Self::MissingPbhPayload { .. } => 1007,
+            Self::InvalidSignatureAggregator { .. } => 1008,
+            Self::PBHCallTracerError => 1009,
+            Self::PbhGasLimitExceeded { .. } => 1010,
+            Self::DuplicateNullifierHash { .. } => 1011,
+            Self::SpentNullifierHash { .. } => 1012,
+            Self::PooledNullifierHash { .. } => 1013,
+            Self::UnsupportedExternalNullifierVersion { .. } => 1014,
+            Self::InactiveEntrypoint { .. } => 1015,
+        }
+    }
+}
+
+fn serialize_display<T: std::fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// A proof of inclusion of an identity commitment in the World ID merkle tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// The root of the merkle tree the proof was generated for.
+    pub root: Field,
+    /// The merkle proof of the identity commitment.
+    pub proof: semaphore_rs::poseidon_tree::Proof,
+}
+
+/// An error returned by [`PBHPayload::validate_batch`].
+#[derive(Error, Debug)]
+#[error("PBH payload {index}: {error}")]
+pub struct PBHBatchValidationError {
+    /// The index of the invalid payload within the batch.
+    pub index: usize,
+    /// The reason the payload is invalid.
+    #[source]
+    pub error: PBHValidationError,
 }
 
 /// The payload of a PBH transaction
 ///
 /// Contains the semaphore proof and relevant metadata
 /// required to to verify the pbh transaction.
-#[derive(Default, Clone, Debug, RlpEncodable, RlpDecodable, PartialEq, Eq)]
+#[derive(
+    Default, Clone, Debug, RlpEncodable, RlpDecodable, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
 pub struct PBHPayload {
     /// A string containing a prefix, the date marker, and the pbh nonce
     pub external_nullifier: ExternalNullifier,
@@ -95,38 +344,141 @@ pub struct PBHPayload {
     pub proof: Proof,
 }
 
+#[bon::bon]
 impl PBHPayload {
+    /// Generates a PBH payload proving that `identity` is included in the World ID merkle tree,
+    /// bound to the given `external_nullifier` and `signal`.
+    ///
+    /// If `compress` is set, the proof is emitted in compressed form.
+    ///
+    /// ```ignore
+    /// let payload = PBHPayload::generate(&identity, &inclusion_proof, external_nullifier, signal)
+    ///     .compress(true)
+    ///     .call()?;
+    /// ```
+    #[builder]
+    pub fn generate(
+        #[builder(start_fn)] identity: &Identity,
+        #[builder(start_fn)] inclusion_proof: &InclusionProof,
+        #[builder(start_fn)] external_nullifier: ExternalNullifier,
+        #[builder(start_fn)] signal: U256,
+        #[builder(default)] compress: bool,
+    ) -> Result<Self, PBHValidationError> {
+        let external_nullifier_hash = EncodedExternalNullifier::from(external_nullifier).0;
+
+        let proof = Proof::Full(generate_proof(
+            identity,
+            &inclusion_proof.proof,
+            external_nullifier_hash,
+            signal,
+        )?);
+        let proof = if compress {
+            proof.compress().ok_or(PBHValidationError::InvalidProof)?
+        } else {
+            proof
+        };
+
+        Ok(Self {
+            external_nullifier,
+            nullifier_hash: generate_nullifier_hash(identity, external_nullifier_hash),
+            root: inclusion_proof.root,
+            proof,
+        })
+    }
+
     /// Validates the PBH payload by validating the merkle root, external nullifier, and semaphore proof.
     /// Returns an error if any of the validations steps fail.
-    pub fn validate(
+    ///
+    /// The external nullifier period is checked against the time reported by `clock`, accepting
+    /// the previous period for `grace_period` after a period rollover.
+    pub fn validate<C: Clock + ?Sized>(
         &self,
         signal: U256,
-        valid_roots: &[Field],
+        valid_roots: &(impl RootSet + ?Sized),
         pbh_nonce_limit: u16,
+        grace_period: Duration,
+        clock: &C,
     ) -> Result<(), PBHValidationError> {
         self.validate_root(valid_roots)?;
 
-        let date = chrono::Utc::now();
-        self.validate_external_nullifier(date, pbh_nonce_limit)?;
-
-        let flat = self.proof.0.flatten();
-        let proof = if (flat[4] | flat[5] | flat[6] | flat[7]).is_zero() {
-            // proof is compressed
-            let compressed_flat = [flat[0], flat[1], flat[2], flat[3]];
-            let compressed_proof =
-                semaphore_rs_proof::compression::CompressedProof::from_flat(compressed_flat);
-            &semaphore_rs_proof::compression::decompress_proof(compressed_proof)
-                .ok_or(PBHValidationError::InvalidProof)?
-        } else {
-            &self.proof.0
-        };
+        self.validate_external_nullifier(clock.now(), pbh_nonce_limit, grace_period)?;
+
+        self.verify(signal)
+    }
+
+    /// Validates a batch of PBH payloads, each paired with the signal it was generated for.
+    ///
+    /// Performs the same checks as [`PBHPayload::validate`] on every payload, but verifies all
+    /// semaphore proofs with a single randomized batch check. Should the batch check fail, the
+    /// proofs are verified individually to find the offending payload.
+    ///
+    /// Returns an error with the index of the first invalid payload.
+    pub fn validate_batch<'a, C: Clock + ?Sized>(
+        payloads: impl IntoIterator<Item = (&'a PBHPayload, U256)>,
+        valid_roots: &(impl RootSet + ?Sized),
+        pbh_nonce_limit: u16,
+        grace_period: Duration,
+        clock: &C,
+    ) -> Result<(), PBHBatchValidationError> {
+        let now = clock.now();
+        let payloads: Vec<_> = payloads.into_iter().collect();
+
+        for (index, (payload, _)) in payloads.iter().enumerate() {
+            payload
+                .validate_root(valid_roots)
+                .and_then(|_| {
+                    payload.validate_external_nullifier(now, pbh_nonce_limit, grace_period)
+                })
+                .map_err(|error| PBHBatchValidationError { index, error })?;
+        }
 
+        Self::verify_batch(payloads)
+    }
+
+    /// Verifies the semaphore proofs of a batch of PBH payloads, each paired with the signal it
+    /// was generated for.
+    ///
+    /// Unlike [`PBHPayload::validate_batch`], neither the root nor the external nullifier of the
+    /// payloads are checked.
+    ///
+    /// Returns an error with the index of the first invalid proof.
+    pub fn verify_batch<'a>(
+        payloads: impl IntoIterator<Item = (&'a PBHPayload, U256)>,
+    ) -> Result<(), PBHBatchValidationError> {
+        let payloads: Vec<_> = payloads.into_iter().collect();
+
+        let entries = payloads
+            .iter()
+            .enumerate()
+            .map(|(index, (payload, signal))| {
+                payload
+                    .batch_entry(*signal)
+                    .map_err(|error| PBHBatchValidationError { index, error })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if entries.len() > 1 && batch::batch_verify(&entries) {
+            return Ok(());
+        }
+
+        payloads
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, (payload, signal))| {
+                payload
+                    .verify(*signal)
+                    .map_err(|error| PBHBatchValidationError { index, error })
+            })
+    }
+
+    /// Verifies the semaphore proof of the payload against the given signal.
+    fn verify(&self, signal: U256) -> Result<(), PBHValidationError> {
         if verify_proof(
             self.root,
             self.nullifier_hash,
             signal,
             EncodedExternalNullifier::from(self.external_nullifier).0,
-            proof,
+            &self.decompressed_proof()?,
             TREE_DEPTH,
         )? {
             Ok(())
@@ -135,11 +487,35 @@ impl PBHPayload {
         }
     }
 
+    /// Prepares the semaphore proof of the payload for batch verification.
+    fn batch_entry(&self, signal: U256) -> Result<batch::BatchEntry, PBHValidationError> {
+        batch::BatchEntry::new(
+            &self.decompressed_proof()?,
+            [
+                self.root,
+                self.nullifier_hash,
+                signal,
+                EncodedExternalNullifier::from(self.external_nullifier).0,
+            ],
+        )
+        .ok_or(PBHValidationError::InvalidProof)
+    }
+
+    /// Returns the semaphore proof of the payload, decompressing it if necessary.
+    fn decompressed_proof(&self) -> Result<semaphore_rs::protocol::Proof, PBHValidationError> {
+        self.proof
+            .decompress()
+            .ok_or(PBHValidationError::InvalidProof)
+    }
+
     /// Checks if the Merkle root exists in the list of valid roots.
     /// Returns an error if the root is not found.
-    pub fn validate_root(&self, valid_roots: &[Field]) -> Result<(), PBHValidationError> {
-        if !valid_roots.contains(&self.root) {
-            return Err(PBHValidationError::InvalidRoot);
+    pub fn validate_root(
+        &self,
+        valid_roots: &(impl RootSet + ?Sized),
+    ) -> Result<(), PBHValidationError> {
+        if !valid_roots.contains_root(&self.root) {
+            return Err(PBHValidationError::InvalidRoot { root: self.root });
         }
 
         Ok(())
@@ -151,17 +527,53 @@ impl PBHPayload {
         &self,
         date: chrono::DateTime<chrono::Utc>,
         pbh_nonce_limit: u16,
+        grace_period: Duration,
     ) -> Result<(), PBHValidationError> {
-        if self.external_nullifier.date_marker() != DateMarker::from(date) {
-            return Err(PBHValidationError::InvalidExternalNullifierPeriod);
-        }
+        self.validate_external_nullifier_period(date, grace_period)?;
 
         if self.external_nullifier.nonce >= pbh_nonce_limit {
-            return Err(PBHValidationError::InvalidExternalNullifierNonce);
+            return Err(PBHValidationError::InvalidExternalNullifierNonce {
+                nonce: self.external_nullifier.nonce,
+                limit: pbh_nonce_limit,
+            });
         }
 
         Ok(())
     }
+
+    /// Ensures the external nullifier is valid for the period containing `date`.
+    ///
+    /// The previous period is also accepted if `date` is within `grace_period` of the
+    /// period rollover.
+    pub fn validate_external_nullifier_period(
+        &self,
+        date: chrono::DateTime<chrono::Utc>,
+        grace_period: Duration,
+    ) -> Result<(), PBHValidationError> {
+        let invalid_period = || PBHValidationError::InvalidExternalNullifierPeriod {
+            external_nullifier: self.external_nullifier,
+            expected: self.external_nullifier.period.start(date.date_naive()),
+        };
+        let grace_period =
+            chrono::TimeDelta::from_std(grace_period).map_err(|_| invalid_period())?;
+
+        if self.is_in_period(date)
+            || date
+                .checked_sub_signed(grace_period)
+                .is_some_and(|date| self.is_in_period(date))
+        {
+            Ok(())
+        } else {
+            Err(invalid_period())
+        }
+    }
+
+    /// Returns whether the external nullifier belongs to the period containing `date`.
+    fn is_in_period(&self, date: chrono::DateTime<chrono::Utc>) -> bool {
+        let external_nullifier = &self.external_nullifier;
+        external_nullifier.period_start()
+            == Some(external_nullifier.period.start(date.date_naive()))
+    }
 }
 
 #[cfg(test)]
@@ -172,11 +584,24 @@ mod test {
     use test_case::test_case;
 
     use super::*;
+    use crate::{
+        clock::{FixedClock, SystemClock},
+        date_marker::DateMarker,
+        external_nullifier::Period,
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn full_proof() -> Proof {
+        Proof::from_flat(std::array::from_fn(|i| U256::from(i + 1)))
+    }
 
     #[test]
     // TODO: fuzz inputs
     fn encode_decode() {
-        let proof = Proof(semaphore_rs::protocol::Proof(
+        let proof = Proof::Full(semaphore_rs::protocol::Proof(
             (U256::from(1u64), U256::from(2u64)),
             (
                 [U256::from(3u64), U256::from(4u64)],
@@ -197,6 +622,84 @@ mod test {
         assert_eq!(pbh_payload, decoded);
     }
 
+    #[test_case(full_proof() ; "full")]
+    #[test_case(Proof::Compressed([U256::from(1), U256::from(2), U256::from(3), U256::from(4)]) ; "compressed")]
+    fn proof_rlp_roundtrip(proof: Proof) {
+        let mut out = vec![];
+        proof.encode(&mut out);
+        assert_eq!(out.len(), proof.length());
+
+        let decoded = Proof::decode(&mut out.as_slice()).unwrap();
+        assert_eq!(proof, decoded);
+    }
+
+    #[test_case(full_proof() ; "full")]
+    #[test_case(Proof::Compressed([U256::from(1), U256::from(2), U256::from(3), U256::from(4)]) ; "compressed")]
+    fn proof_abi_and_json_roundtrip(proof: Proof) {
+        assert_eq!(Proof::from_flat(proof.flatten()), proof);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<Proof>(&json).unwrap(), proof);
+    }
+
+    #[test]
+    fn zero_tail_proof_is_compressed_on_every_path() {
+        let flat = [1, 2, 3, 4, 0, 0, 0, 0].map(U256::from);
+        let expected = Proof::from_flat(flat);
+        assert!(matches!(expected, Proof::Compressed(_)));
+
+        // A 256 byte RLP proof
+        let mut out = vec![];
+        PackedProof::from(semaphore_rs::protocol::Proof::from_flat(flat))
+            .0
+            .encode(&mut out);
+        assert_eq!(Proof::decode(&mut out.as_slice()).unwrap(), expected);
+
+        // An eight word JSON proof
+        let json = serde_json::to_string(&flat).unwrap();
+        assert_eq!(serde_json::from_str::<Proof>(&json).unwrap(), expected);
+    }
+
+    #[test]
+    fn proof_invalid_rlp_length() {
+        let mut out = vec![];
+        [0u8; 100].encode(&mut out);
+        assert!(Proof::decode(&mut out.as_slice()).is_err());
+    }
+
+    #[test]
+    fn malformed_compressed_proof() {
+        // Coordinates outside of the base field can not be decompressed
+        let proof = Proof::Compressed([U256::MAX; 4]);
+        assert!(proof.decompress().is_none());
+
+        let pbh_payload = PBHPayload {
+            external_nullifier: ExternalNullifier::v1(1, 2025, 0),
+            nullifier_hash: Field::from(10u64),
+            root: Field::from(12u64),
+            proof,
+        };
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let res = pbh_payload.validate(U256::ZERO, &[pbh_payload.root], 10, Duration::ZERO, &clock);
+        assert!(matches!(res, Err(PBHValidationError::InvalidProof)));
+    }
+
+    #[test]
+    fn tampered_compressed_proof() {
+        let (root, payloads) = batch_payloads(1);
+        let (mut payload, signal) = payloads.into_iter().next().unwrap();
+        let Some(Proof::Compressed(mut words)) = payload.proof.compress() else {
+            panic!("proof should compress");
+        };
+        words[1] += U256::from(1);
+        payload.proof = Proof::Compressed(words);
+
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        assert!(payload
+            .validate(signal, &[root], 10, Duration::ZERO, &clock)
+            .is_err());
+    }
+
     #[test]
     fn serialize_compressed_proof() {
         let identity = semaphore_rs::identity::Identity::from_secret(&mut [1, 2, 3], None);
@@ -242,8 +745,8 @@ mod test {
             U256::ZERO,
             U256::ZERO,
         ];
-        let proof = semaphore_rs::protocol::Proof::from_flat(proof);
-        let proof = Proof(proof);
+        let proof = Proof::from_flat(proof);
+        assert!(matches!(proof, Proof::Compressed(_)));
 
         let pbh_payload = PBHPayload {
             root: tree.root(),
@@ -252,7 +755,212 @@ mod test {
             proof,
         };
 
-        pbh_payload.validate(signal, &[tree.root()], 10).unwrap();
+        pbh_payload
+            .validate(signal, &[tree.root()], 10, Duration::ZERO, &SystemClock)
+            .unwrap();
+    }
+
+    #[test]
+    fn validate_against_fixed_clock() {
+        let identity = semaphore_rs::identity::Identity::from_secret(&mut [1, 2, 3], None);
+        let mut tree = semaphore_rs::poseidon_tree::LazyPoseidonTree::new_with_dense_prefix(
+            30,
+            0,
+            &U256::ZERO,
+        );
+        tree = tree.update_with_mutation(0, &identity.commitment());
+        let merkle_proof = tree.proof(0);
+
+        // January 2025, regardless of the wall-clock time the test runs at
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let external_nullifier = ExternalNullifier::v1(1, 2025, 0);
+        let external_nullifier_hash = EncodedExternalNullifier::from(external_nullifier).0;
+        let signal = U256::ZERO;
+
+        let proof = semaphore_rs::protocol::generate_proof(
+            &identity,
+            &merkle_proof,
+            external_nullifier_hash,
+            signal,
+        )
+        .unwrap();
+        let nullifier_hash =
+            semaphore_rs::protocol::generate_nullifier_hash(&identity, external_nullifier_hash);
+
+        let pbh_payload = PBHPayload {
+            root: tree.root(),
+            external_nullifier,
+            nullifier_hash,
+            proof: Proof::Full(proof),
+        };
+
+        pbh_payload
+            .validate(signal, &[tree.root()], 10, Duration::ZERO, &clock)
+            .unwrap();
+
+        let next_month = FixedClock::new(Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap());
+        let res = pbh_payload.validate(signal, &[tree.root()], 10, Duration::ZERO, &next_month);
+        assert!(matches!(
+            res,
+            Err(PBHValidationError::InvalidExternalNullifierPeriod { .. })
+        ));
+    }
+
+    /// Generates `n` valid payloads for distinct identities in January 2025, paired with their signals.
+    fn batch_payloads(n: usize) -> (Field, Vec<(PBHPayload, U256)>) {
+        let identities: Vec<_> = (0..n)
+            .map(|i| semaphore_rs::identity::Identity::from_secret(&mut [i as u8, 2, 3], None))
+            .collect();
+        let mut tree = semaphore_rs::poseidon_tree::LazyPoseidonTree::new_with_dense_prefix(
+            30,
+            0,
+            &U256::ZERO,
+        );
+        for (i, identity) in identities.iter().enumerate() {
+            tree = tree.update_with_mutation(i, &identity.commitment());
+        }
+
+        let payloads = identities
+            .iter()
+            .enumerate()
+            .map(|(i, identity)| {
+                let external_nullifier = ExternalNullifier::v1(1, 2025, i as u16);
+                let external_nullifier_hash = EncodedExternalNullifier::from(external_nullifier).0;
+                let signal = U256::from(i);
+                let proof = semaphore_rs::protocol::generate_proof(
+                    identity,
+                    &tree.proof(i),
+                    external_nullifier_hash,
+                    signal,
+                )
+                .unwrap();
+                let nullifier_hash = semaphore_rs::protocol::generate_nullifier_hash(
+                    identity,
+                    external_nullifier_hash,
+                );
+                let payload = PBHPayload {
+                    root: tree.root(),
+                    external_nullifier,
+                    nullifier_hash,
+                    proof: Proof::Full(proof),
+                };
+                (payload, signal)
+            })
+            .collect();
+
+        (tree.root(), payloads)
+    }
+
+    #[test]
+    fn validate_batch() {
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let (root, payloads) = batch_payloads(4);
+
+        PBHPayload::validate_batch(
+            payloads.iter().map(|(payload, signal)| (payload, *signal)),
+            &[root],
+            10,
+            Duration::ZERO,
+            &clock,
+        )
+        .unwrap();
+
+        PBHPayload::validate_batch(std::iter::empty(), &[root], 10, Duration::ZERO, &clock)
+            .unwrap();
+    }
+
+    #[test]
+    fn validate_batch_invalid_proof() {
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let (root, payloads) = batch_payloads(4);
+
+        // A proof verified against the wrong signal must be attributed to its payload
+        let err = PBHPayload::validate_batch(
+            payloads.iter().enumerate().map(|(i, (payload, signal))| {
+                (
+                    payload,
+                    if i == 2 {
+                        *signal + U256::from(1)
+                    } else {
+                        *signal
+                    },
+                )
+            }),
+            &[root],
+            10,
+            Duration::ZERO,
+            &clock,
+        )
+        .unwrap_err();
+        assert_eq!(err.index, 2);
+        assert!(matches!(err.error, PBHValidationError::InvalidProof));
+    }
+
+    #[test]
+    fn validate_batch_invalid_external_nullifier() {
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let (root, payloads) = batch_payloads(3);
+
+        // The nonce of the payload at index 2 exceeds the limit
+        let err = PBHPayload::validate_batch(
+            payloads.iter().map(|(payload, signal)| (payload, *signal)),
+            &[root],
+            2,
+            Duration::ZERO,
+            &clock,
+        )
+        .unwrap_err();
+        assert_eq!(err.index, 2);
+        assert!(matches!(
+            err.error,
+            PBHValidationError::InvalidExternalNullifierNonce { .. }
+        ));
+    }
+
+    #[test_case(false ; "full")]
+    #[test_case(true ; "compressed")]
+    fn generate(compress: bool) {
+        let identity = semaphore_rs::identity::Identity::from_secret(&mut [4, 5, 6], None);
+        let mut tree = semaphore_rs::poseidon_tree::LazyPoseidonTree::new_with_dense_prefix(
+            30,
+            0,
+            &U256::ZERO,
+        );
+        tree = tree.update_with_mutation(0, &identity.commitment());
+        let inclusion_proof = InclusionProof {
+            root: tree.root(),
+            proof: tree.proof(0),
+        };
+
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let external_nullifier = ExternalNullifier::v1(1, 2025, 0);
+        let signal = U256::from(42);
+
+        let payload = PBHPayload::generate(&identity, &inclusion_proof, external_nullifier, signal)
+            .compress(compress)
+            .call()
+            .unwrap();
+
+        assert_eq!(matches!(payload.proof, Proof::Compressed(_)), compress);
+        payload
+            .validate(signal, &[tree.root()], 10, Duration::ZERO, &clock)
+            .unwrap();
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let (_, payloads) = batch_payloads(1);
+        let (payload, _) = &payloads[0];
+
+        let json = serde_json::to_value(payload).unwrap();
+        assert_eq!(
+            json["externalNullifier"],
+            serde_json::json!({"version": "v1", "year": 2025, "month": 1, "nonce": 0})
+        );
+        assert_eq!(json["proof"].as_array().unwrap().len(), 8);
+
+        let decoded: PBHPayload = serde_json::from_value(json).unwrap();
+        assert_eq!(&decoded, payload);
     }
 
     #[test]
@@ -277,7 +985,10 @@ mod test {
 
         let valid_roots = vec![Field::from(1u64), Field::from(2u64)];
         let res = pbh_payload.validate_root(&valid_roots);
-        assert!(matches!(res, Err(PBHValidationError::InvalidRoot)));
+        assert!(matches!(
+            res,
+            Err(PBHValidationError::InvalidRoot { root }) if root == Field::from(3u64)
+        ));
 
         Ok(())
     }
@@ -285,6 +996,9 @@ mod test {
     #[test_case(ExternalNullifier::v1(1, 2025, 0) ; "01-2025-0")]
     #[test_case(ExternalNullifier::v1(1, 2025, 1) ; "01-2025-1")]
     #[test_case(ExternalNullifier::v1(1, 2025, 29) ; "01-2025-29")]
+    #[test_case(ExternalNullifier::v2(Period::Monthly, date(2025, 1, 1), 0) ; "v2-monthly")]
+    #[test_case(ExternalNullifier::v2(Period::Weekly, date(2024, 12, 30), 0) ; "v2-weekly")]
+    #[test_case(ExternalNullifier::v2(Period::Daily, date(2025, 1, 1), 29) ; "v2-daily")]
     fn valid_external_nullifier(external_nullifier: ExternalNullifier) -> eyre::Result<()> {
         let pbh_nonce_limit = 30;
         let date = chrono::Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
@@ -294,12 +1008,16 @@ mod test {
             ..Default::default()
         };
 
-        pbh_payload.validate_external_nullifier(date, pbh_nonce_limit)?;
+        pbh_payload.validate_external_nullifier(date, pbh_nonce_limit, Duration::ZERO)?;
         Ok(())
     }
 
     #[test_case(ExternalNullifier::v1(1, 2024, 0) ; "01-2024-0")]
     #[test_case(ExternalNullifier::v1(2, 2025, 0) ; "02-2025-0")]
+    #[test_case(ExternalNullifier::v2(Period::Monthly, date(2024, 12, 1), 0) ; "v2-monthly")]
+    #[test_case(ExternalNullifier::v2(Period::Weekly, date(2024, 12, 29), 0) ; "v2-weekly")]
+    #[test_case(ExternalNullifier::v2(Period::Daily, date(2025, 1, 2), 0) ; "v2-daily")]
+    #[test_case(ExternalNullifier { day: 2, ..ExternalNullifier::v2(Period::Monthly, date(2025, 1, 1), 0) } ; "v2-monthly-misaligned")]
     fn invalid_external_nullifier_invalid_period(
         external_nullifier: ExternalNullifier,
     ) -> eyre::Result<()> {
@@ -311,15 +1029,51 @@ mod test {
             ..Default::default()
         };
 
-        let res = pbh_payload.validate_external_nullifier(date, pbh_nonce_limit);
+        let res = pbh_payload.validate_external_nullifier(date, pbh_nonce_limit, Duration::ZERO);
         assert!(matches!(
             res,
-            Err(PBHValidationError::InvalidExternalNullifierPeriod)
+            Err(PBHValidationError::InvalidExternalNullifierPeriod { .. })
         ));
 
         Ok(())
     }
 
+    #[test_case(Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap(), true ; "at rollover")]
+    #[test_case(Utc.with_ymd_and_hms(2025, 2, 1, 0, 4, 59).unwrap(), true ; "within grace period")]
+    #[test_case(Utc.with_ymd_and_hms(2025, 2, 1, 0, 5, 0).unwrap(), true ; "end of grace period")]
+    #[test_case(Utc.with_ymd_and_hms(2025, 2, 1, 0, 5, 1).unwrap(), false ; "after grace period")]
+    #[test_case(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap(), false ; "two periods later")]
+    fn external_nullifier_grace_period(
+        date: chrono::DateTime<Utc>,
+        valid: bool,
+    ) -> eyre::Result<()> {
+        let grace_period = Duration::from_secs(300);
+        let pbh_payload = PBHPayload {
+            external_nullifier: ExternalNullifier::v1(1, 2025, 0),
+            ..Default::default()
+        };
+
+        let res = pbh_payload.validate_external_nullifier(date, 30, grace_period);
+        assert_eq!(res.is_ok(), valid);
+
+        Ok(())
+    }
+
+    #[test]
+    fn grace_period_does_not_accept_future_periods() {
+        let date = Utc.with_ymd_and_hms(2025, 1, 31, 23, 59, 59).unwrap();
+        let pbh_payload = PBHPayload {
+            external_nullifier: ExternalNullifier::v1(2, 2025, 0),
+            ..Default::default()
+        };
+
+        let res = pbh_payload.validate_external_nullifier(date, 30, Duration::from_secs(300));
+        assert!(matches!(
+            res,
+            Err(PBHValidationError::InvalidExternalNullifierPeriod { .. })
+        ));
+    }
+
     #[test]
     fn invalid_external_nullifier_invalid_nonce() -> eyre::Result<()> {
         let pbh_nonce_limit = 30;
@@ -331,12 +1085,52 @@ mod test {
             ..Default::default()
         };
 
-        let res = pbh_payload.validate_external_nullifier(date, pbh_nonce_limit);
+        let res = pbh_payload.validate_external_nullifier(date, pbh_nonce_limit, Duration::ZERO);
         assert!(matches!(
             res,
-            Err(PBHValidationError::InvalidExternalNullifierNonce)
+            Err(PBHValidationError::InvalidExternalNullifierNonce {
+                nonce: 30,
+                limit: 30
+            })
         ));
 
         Ok(())
     }
+
+    #[test]
+    fn error_json() {
+        let date = chrono::Utc.with_ymd_and_hms(2025, 2, 12, 0, 0, 0).unwrap();
+        let pbh_payload = PBHPayload {
+            external_nullifier: ExternalNullifier::v1(1, 2025, 0),
+            ..Default::default()
+        };
+        let err = pbh_payload
+            .validate_external_nullifier(date, 30, Duration::ZERO)
+            .unwrap_err();
+
+        assert_eq!(err.code(), 1002);
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "reason": "invalid_external_nullifier_period",
+                "externalNullifier": {
+                    "version": "v1",
+                    "year": 2025,
+                    "month": 1,
+                    "nonce": 0,
+                },
+                "expected": "2025-02-01",
+            })
+        );
+
+        let err = PBHValidationError::MissingPbhPayload {
+            user_ops: 2,
+            payloads: 1,
+        };
+        assert_eq!(err.code(), 1007);
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({ "reason": "missing_pbh_payload", "userOps": 2, "payloads": 1 })
+        );
+    }
 }
@@ -1,10 +1,302 @@
-use alloy_sol_types::SolValue;
+use alloy_primitives::{address, bytes, keccak256, Address, B256, U256};
+use alloy_sol_types::{SolCall, SolValue};
+use reth_evm::Evm;
+use revm::context::result::ExecutionResult;
 use semaphore_rs::{hash_to_field, Field};
+use thiserror::Error;
 
-use crate::bindings::IEntryPoint::PackedUserOperation;
+use crate::bindings::{IEntryPoint::PackedUserOperation, IPBHEntryPoint::getSignalHashCall};
 
+/// The address of the canonical ERC-4337 v0.7 EntryPoint.
+pub const ENTRYPOINT_V07: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+/// The derivation of the signal a PBH payload for a UserOperation is generated for.
+///
+/// The derivation must match `getSignalHash` on the deployed PBHEntryPoint, otherwise every
+/// PBH proof is rejected either by the pool or on chain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UserOpSignal {
+    /// `abi.encodePacked(sender, nonce, callData).hashToField()`.
+    #[default]
+    V1,
+    /// `abi.encodePacked(userOpHash).hashToField()`, where `userOpHash` is the ERC-4337 v0.7
+    /// UserOperation hash for `entry_point` on `chain_id`, committing to the full UserOperation.
+    V2 { entry_point: Address, chain_id: u64 },
+}
+
+impl UserOpSignal {
+    /// Returns the derivation with the given version, or `None` if the version is unknown.
+    ///
+    /// `entry_point` and `chain_id` are only used by versions committing to the UserOperation
+    /// hash.
+    pub fn from_version(version: u8, entry_point: Address, chain_id: u64) -> Option<Self> {
+        match version {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2 {
+                entry_point,
+                chain_id,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the version of the derivation.
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 { .. } => 2,
+        }
+    }
+
+    /// Returns the signal for `user_op`.
+    pub fn signal(&self, user_op: &PackedUserOperation) -> Field {
+        match self {
+            Self::V1 => {
+                let encoded = SolValue::abi_encode_packed(&(
+                    &user_op.sender,
+                    &user_op.nonce,
+                    &user_op.callData,
+                ));
+                hash_to_field(encoded.as_slice())
+            }
+            Self::V2 {
+                entry_point,
+                chain_id,
+            } => hash_to_field(user_op_hash(user_op, *entry_point, *chain_id).as_slice()),
+        }
+    }
+}
+
+/// Returns the ERC-4337 v0.7 hash of `user_op`, as computed by `getUserOpHash` on the
+/// EntryPoint at `entry_point`.
+pub fn user_op_hash(user_op: &PackedUserOperation, entry_point: Address, chain_id: u64) -> B256 {
+    let packed = (
+        user_op.sender,
+        user_op.nonce,
+        keccak256(&user_op.initCode),
+        keccak256(&user_op.callData),
+        user_op.accountGasLimits,
+        user_op.preVerificationGas,
+        user_op.gasFees,
+        keccak256(&user_op.paymasterAndData),
+    )
+        .abi_encode_params();
+
+    keccak256((keccak256(packed), entry_point, chain_id).abi_encode_params())
+}
+
+/// The signal derivation of the pool does not match `getSignalHash` on a PBHEntryPoint.
+#[derive(Debug, Error)]
+pub enum UserOpSignalError {
+    #[error("getSignalHash on {entrypoint} returned {onchain}, expected {expected} for signal version {version}")]
+    Mismatch {
+        entrypoint: Address,
+        version: u8,
+        expected: Field,
+        onchain: Field,
+    },
+    #[error("failed to call getSignalHash on {entrypoint}: {reason}")]
+    Call { entrypoint: Address, reason: String },
+}
+
+/// The UserOperation used to compare the signal derivations. Every field is set, so that any
+/// two derivations committing to different fields disagree.
+fn probe_user_op() -> PackedUserOperation {
+    PackedUserOperation {
+        sender: Address::repeat_byte(0x11),
+        nonce: U256::from(7),
+        initCode: bytes!("01"),
+        callData: bytes!("deadbeef"),
+        accountGasLimits: B256::repeat_byte(0x02),
+        preVerificationGas: U256::from(3),
+        gasFees: B256::repeat_byte(0x04),
+        paymasterAndData: bytes!("05"),
+        signature: bytes!("06"),
+    }
+}
+
+/// Ensures `signal` derives the same signal as `getSignalHash` on the PBHEntryPoint at
+/// `entrypoint`, by calling it through `evm`.
+///
+/// Returns `Ok(false)` if no PBHEntryPoint is deployed at `entrypoint` yet, or if it predates
+/// `getSignalHash`, in which case the derivation can't be verified.
+pub fn verify_user_op_signal<E: Evm>(
+    evm: &mut E,
+    entrypoint: Address,
+    signal: UserOpSignal,
+) -> Result<bool, UserOpSignalError> {
+    let call_error = |reason: String| UserOpSignalError::Call { entrypoint, reason };
+    let user_op = probe_user_op();
+    let input = getSignalHashCall {
+        userOp: user_op.clone(),
+    }
+    .abi_encode();
+
+    let result = evm
+        .transact_system_call(Address::ZERO, entrypoint, input.into())
+        .map_err(|err| call_error(err.to_string()))?
+        .result;
+    let output = match result {
+        ExecutionResult::Success { output, .. } => output.into_data(),
+        // Implementations without `getSignalHash` revert without data, as they have no fallback
+        ExecutionResult::Revert { output, .. } if output.is_empty() => return Ok(false),
+        ExecutionResult::Revert { output, .. } => {
+            return Err(call_error(format!("reverted with {output}")))
+        }
+        ExecutionResult::Halt { reason, .. } => {
+            return Err(call_error(format!("halted with {reason:?}")))
+        }
+    };
+
+    // Calls to an account without code succeed without output
+    if output.is_empty() {
+        return Ok(false);
+    }
+
+    let onchain = getSignalHashCall::abi_decode_returns(&output)
+        .map_err(|err| call_error(err.to_string()))?;
+    let expected = signal.signal(&user_op);
+    if onchain != expected {
+        return Err(UserOpSignalError::Mismatch {
+            entrypoint,
+            version: signal.version(),
+            expected,
+            onchain,
+        });
+    }
+
+    Ok(true)
+}
+
+/// Returns the [`UserOpSignal::V1`] signal for `user_op`.
 pub fn hash_user_op(user_op: &PackedUserOperation) -> Field {
-    let hash = SolValue::abi_encode_packed(&(&user_op.sender, &user_op.nonce, &user_op.callData));
+    UserOpSignal::V1.signal(user_op)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{b256, fixed_bytes, uint, Bytes};
+    use reth::revm::database::StateProviderDatabase;
+    use reth_evm::ConfigureEvm;
+    use reth_optimism_node::OpEvmConfig;
+    use reth_provider::{ChainSpecProvider, StateProviderFactory};
+    use world_chain_test::mock::{ExtendedAccount, MockEthProvider};
+
+    use super::*;
+
+    /// A hand-assembled stub of `getSignalHash`, returning
+    /// `abi.encodePacked(sender, nonce, callData).hashToField()` of the ABI encoded
+    /// UserOperation without dispatching on the selector. It only exercises
+    /// [`verify_user_op_signal`] through the EVM; the V1 derivation is tied to the contract by
+    /// the constant shared by `v1_matches_entrypoint` and `test_getSignalHash_MatchesPool`.
+    const SIGNAL_HASH_V1: Bytes = bytes!(
+        "600435600401803560601b6000528060200135601452806060013501803590602001819060343760340160002060081c60005260206000f3"
+    );
+
+    const V1_ENTRYPOINT: Address = Address::repeat_byte(0x21);
+    const REVERTING_ENTRYPOINT: Address = Address::repeat_byte(0x22);
+    const UNDEPLOYED_ENTRYPOINT: Address = Address::repeat_byte(0x23);
+    const LEGACY_ENTRYPOINT: Address = Address::repeat_byte(0x24);
+
+    /// An implementation predating `getSignalHash`, which only dispatches `numPbhPerMonth()` and
+    /// reverts without data on any other selector.
+    const LEGACY_CODE: Bytes = bytes!("60003560e01c636713f63514601357600080fd5b00");
+
+    /// The UserOperation used in `test_getSignalHash_MatchesPool` of the PBHEntryPoint tests.
+    fn user_op() -> PackedUserOperation {
+        PackedUserOperation {
+            sender: Address::repeat_byte(0x11),
+            nonce: U256::from(7),
+            callData: bytes!("deadbeef"),
+            accountGasLimits: fixed_bytes!(
+                "000000000000000000000000000fffd30000000000000000000000000000C350"
+            ),
+            preVerificationGas: U256::from(500836),
+            gasFees: fixed_bytes!(
+                "0000000000000000000000003B9ACA0000000000000000000000000073140B60"
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn v1_matches_entrypoint() {
+        assert_eq!(
+            UserOpSignal::V1.signal(&user_op()),
+            uint!(0x3f6ba085d299f560037f96450f7792a12fffe0856c11a2ebd9be4ca9f8711c_U256)
+        );
+    }
+
+    #[test]
+    fn v2_commits_to_user_op_hash() {
+        let signal = UserOpSignal::V2 {
+            entry_point: ENTRYPOINT_V07,
+            chain_id: 480,
+        };
+
+        assert_eq!(
+            user_op_hash(&user_op(), ENTRYPOINT_V07, 480),
+            b256!("1067fc38c42f450bf22983f7d11cc74f62fb5ac059a7631595bce06229615548")
+        );
+        assert_eq!(
+            signal.signal(&user_op()),
+            uint!(0x4ae7be140e16ff203ebe6b188b47877931cded2c248e2c766ee66880d8c27_U256)
+        );
+
+        // Unlike V1, the signal changes with fields outside of the sender, nonce and calldata
+        let mut fee_bump = user_op();
+        fee_bump.gasFees = B256::ZERO;
+        assert_eq!(
+            UserOpSignal::V1.signal(&fee_bump),
+            UserOpSignal::V1.signal(&user_op())
+        );
+        assert_ne!(signal.signal(&fee_bump), signal.signal(&user_op()));
+    }
+
+    #[test]
+    fn version_round_trip() {
+        for version in [1, 2] {
+            let signal = UserOpSignal::from_version(version, ENTRYPOINT_V07, 480).unwrap();
+            assert_eq!(signal.version(), version);
+        }
+        assert_eq!(UserOpSignal::from_version(3, ENTRYPOINT_V07, 480), None);
+    }
+
+    #[test]
+    fn verify_signal_through_evm() {
+        let client = MockEthProvider::default();
+        client.add_account(
+            V1_ENTRYPOINT,
+            ExtendedAccount::new(0, U256::ZERO).with_bytecode(SIGNAL_HASH_V1),
+        );
+        client.add_account(
+            REVERTING_ENTRYPOINT,
+            ExtendedAccount::new(0, U256::ZERO).with_bytecode(bytes!("600160005260206000fd")),
+        );
+        client.add_account(
+            LEGACY_ENTRYPOINT,
+            ExtendedAccount::new(0, U256::ZERO).with_bytecode(LEGACY_CODE),
+        );
+        let state = client.latest().unwrap();
+        let evm_config = OpEvmConfig::optimism(client.chain_spec());
+        let mut evm =
+            evm_config.evm_with_env(StateProviderDatabase::new(&state), Default::default());
 
-    hash_to_field(hash.as_slice())
+        assert!(verify_user_op_signal(&mut evm, V1_ENTRYPOINT, UserOpSignal::V1).unwrap());
+        assert!(matches!(
+            verify_user_op_signal(
+                &mut evm,
+                V1_ENTRYPOINT,
+                UserOpSignal::from_version(2, ENTRYPOINT_V07, 480).unwrap()
+            ),
+            Err(UserOpSignalError::Mismatch { version: 2, .. })
+        ));
+        assert!(matches!(
+            verify_user_op_signal(&mut evm, REVERTING_ENTRYPOINT, UserOpSignal::V1),
+            Err(UserOpSignalError::Call { .. })
+        ));
+        assert!(!verify_user_op_signal(&mut evm, UNDEPLOYED_ENTRYPOINT, UserOpSignal::V1).unwrap());
+        // Deployments predating `getSignalHash` must not prevent the node from starting
+        assert!(!verify_user_op_signal(&mut evm, LEGACY_ENTRYPOINT, UserOpSignal::V1).unwrap());
+    }
 }
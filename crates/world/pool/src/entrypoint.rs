@@ -0,0 +1,306 @@
+//! The PBHEntryPoint deployments accepted by the pool and the payload builder.
+use std::{fmt, str::FromStr};
+
+use alloy_primitives::Address;
+
+use crate::eip4337::{UserOpSignal, ENTRYPOINT_V07};
+
+/// A PBHEntryPoint deployment and the signature aggregator of its PBH bundles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PbhEntrypoint {
+    /// The address of the PBHEntryPoint.
+    pub entrypoint: Address,
+    /// The address of the PBH signature aggregator.
+    pub signature_aggregator: Address,
+    /// The timestamp from which PBH transactions to the entrypoint are accepted.
+    pub activation_timestamp: u64,
+    /// The timestamp from which PBH transactions to the entrypoint are no longer accepted, if
+    /// the entrypoint is being retired.
+    pub deactivation_timestamp: Option<u64>,
+    /// The version of the [`UserOpSignal`] derived by `getSignalHash` on the entrypoint.
+    pub signal_version: u8,
+}
+
+impl PbhEntrypoint {
+    /// Creates an entrypoint which is active from genesis.
+    pub fn new(entrypoint: Address, signature_aggregator: Address) -> Self {
+        Self {
+            entrypoint,
+            signature_aggregator,
+            activation_timestamp: 0,
+            deactivation_timestamp: None,
+            signal_version: UserOpSignal::V1.version(),
+        }
+    }
+
+    /// Sets the timestamp from which the entrypoint is active.
+    pub fn with_activation_timestamp(mut self, activation_timestamp: u64) -> Self {
+        self.activation_timestamp = activation_timestamp;
+        self
+    }
+
+    /// Sets the timestamp from which the entrypoint is no longer active.
+    pub fn with_deactivation_timestamp(mut self, deactivation_timestamp: u64) -> Self {
+        self.deactivation_timestamp = Some(deactivation_timestamp);
+        self
+    }
+
+    /// Sets the version of the [`UserOpSignal`] derived by `getSignalHash` on the entrypoint.
+    pub fn with_signal_version(mut self, signal_version: u8) -> Self {
+        self.signal_version = signal_version;
+        self
+    }
+
+    /// Returns the [`UserOpSignal`] derived by `getSignalHash` on the entrypoint on `chain_id`,
+    /// or `None` if the signal version is unknown.
+    pub fn user_op_signal(&self, chain_id: u64) -> Option<UserOpSignal> {
+        UserOpSignal::from_version(self.signal_version, ENTRYPOINT_V07, chain_id)
+    }
+
+    /// Returns `true` if the entrypoint is active at `timestamp`.
+    pub fn is_active_at(&self, timestamp: u64) -> bool {
+        self.activation_timestamp <= timestamp
+            && self
+                .deactivation_timestamp
+                .is_none_or(|deactivation| timestamp < deactivation)
+    }
+}
+
+impl fmt::Display for PbhEntrypoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.entrypoint, self.signature_aggregator, self.activation_timestamp
+        )?;
+        if let Some(deactivation_timestamp) = self.deactivation_timestamp {
+            write!(f, ":{deactivation_timestamp}")?;
+        } else if self.signal_version != UserOpSignal::V1.version() {
+            f.write_str(":")?;
+        }
+        if self.signal_version != UserOpSignal::V1.version() {
+            write!(f, ":{}", self.signal_version)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PbhEntrypoint {
+    type Err = String;
+
+    /// Parses an entrypoint from
+    /// `<entrypoint>:<signature_aggregator>:<activation_timestamp>[:[<deactivation_timestamp>][:<signal_version>]]`.
+    ///
+    /// The deactivation timestamp may be left empty to only set the signal version, which
+    /// defaults to [`UserOpSignal::V1`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid PBH entrypoint `{s}`, expected `<entrypoint>:<signature_aggregator>:<activation_timestamp>[:[<deactivation_timestamp>][:<signal_version>]]`"
+            )
+        };
+
+        let mut parts = s.split(':');
+        let (
+            Some(entrypoint),
+            Some(signature_aggregator),
+            Some(activation_timestamp),
+            deactivation_timestamp,
+            signal_version,
+            None,
+        ) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        )
+        else {
+            return Err(invalid());
+        };
+
+        let signal_version = match signal_version {
+            Some(signal_version) => signal_version.parse().map_err(|_| invalid())?,
+            None => UserOpSignal::V1.version(),
+        };
+        if UserOpSignal::from_version(signal_version, ENTRYPOINT_V07, 0).is_none() {
+            return Err(format!(
+                "invalid PBH entrypoint `{s}`, unsupported signal version {signal_version}"
+            ));
+        }
+
+        Ok(Self {
+            entrypoint: entrypoint.parse().map_err(|_| invalid())?,
+            signature_aggregator: signature_aggregator.parse().map_err(|_| invalid())?,
+            activation_timestamp: activation_timestamp.parse().map_err(|_| invalid())?,
+            deactivation_timestamp: deactivation_timestamp
+                .filter(|timestamp| !timestamp.is_empty())
+                .map(|timestamp| timestamp.parse().map_err(|_| invalid()))
+                .transpose()?,
+            signal_version,
+        })
+    }
+}
+
+/// The set of PBHEntryPoint deployments which can be live at the same time.
+///
+/// During a migration the previous and the new deployment are both configured, the latter with
+/// an activation timestamp and the former usually with a deactivation timestamp. PBH
+/// transactions are accepted for every active entrypoint, and the nullifier hashes they carry
+/// are spent on the entrypoint they were sent to. As each deployment keeps its own record of
+/// spent nullifier hashes, a nullifier hash spent on any configured entrypoint is considered
+/// spent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PbhEntrypoints {
+    /// The entrypoints, ordered by activation timestamp.
+    entrypoints: Vec<PbhEntrypoint>,
+}
+
+impl PbhEntrypoints {
+    /// Creates a new set of entrypoints.
+    ///
+    /// If the same entrypoint is configured more than once, the first configuration is kept.
+    pub fn new(entrypoints: impl IntoIterator<Item = PbhEntrypoint>) -> Self {
+        let mut deduped: Vec<PbhEntrypoint> = vec![];
+        for entrypoint in entrypoints {
+            if !deduped
+                .iter()
+                .any(|existing| existing.entrypoint == entrypoint.entrypoint)
+            {
+                deduped.push(entrypoint);
+            }
+        }
+        deduped.sort_by_key(|entrypoint| entrypoint.activation_timestamp);
+
+        Self {
+            entrypoints: deduped,
+        }
+    }
+
+    /// Creates a set containing a single entrypoint which is active from genesis.
+    pub fn single(entrypoint: Address, signature_aggregator: Address) -> Self {
+        Self::new([PbhEntrypoint::new(entrypoint, signature_aggregator)])
+    }
+
+    /// Returns an iterator over all entrypoints, ordered by activation timestamp.
+    pub fn iter(&self) -> impl Iterator<Item = &PbhEntrypoint> {
+        self.entrypoints.iter()
+    }
+
+    /// Returns the entrypoint at `address` if it is active at `timestamp`.
+    pub fn active(&self, address: Address, timestamp: u64) -> Option<&PbhEntrypoint> {
+        self.entrypoints
+            .iter()
+            .find(|entrypoint| entrypoint.entrypoint == address)
+            .filter(|entrypoint| entrypoint.is_active_at(timestamp))
+    }
+
+    /// Returns the most recently activated entrypoint at `timestamp`.
+    pub fn latest(&self, timestamp: u64) -> Option<&PbhEntrypoint> {
+        self.entrypoints
+            .iter()
+            .rev()
+            .find(|entrypoint| entrypoint.is_active_at(timestamp))
+    }
+
+    /// Returns `true` if `address` is one of the entrypoints, regardless of its activation.
+    pub fn contains(&self, address: Address) -> bool {
+        self.entrypoints
+            .iter()
+            .any(|entrypoint| entrypoint.entrypoint == address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entrypoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entrypoints.is_empty()
+    }
+}
+
+impl FromIterator<PbhEntrypoint> for PbhEntrypoints {
+    fn from_iter<T: IntoIterator<Item = PbhEntrypoint>>(iter: T) -> Self {
+        Self::new(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: Address = Address::repeat_byte(1);
+    const NEW: Address = Address::repeat_byte(2);
+    const AGGREGATOR: Address = Address::repeat_byte(3);
+
+    #[test]
+    fn migration_window() {
+        let entrypoints = PbhEntrypoints::new([
+            PbhEntrypoint::new(NEW, AGGREGATOR).with_activation_timestamp(100),
+            PbhEntrypoint::new(OLD, AGGREGATOR),
+        ]);
+
+        assert!(entrypoints.active(OLD, 99).is_some());
+        assert!(entrypoints.active(NEW, 99).is_none());
+        assert_eq!(entrypoints.latest(99).unwrap().entrypoint, OLD);
+
+        assert!(entrypoints.active(OLD, 100).is_some());
+        assert!(entrypoints.active(NEW, 100).is_some());
+        assert_eq!(entrypoints.latest(100).unwrap().entrypoint, NEW);
+
+        assert!(entrypoints.contains(NEW));
+        assert!(entrypoints.active(AGGREGATOR, 100).is_none());
+    }
+
+    #[test]
+    fn deactivation() {
+        let entrypoints = PbhEntrypoints::new([
+            PbhEntrypoint::new(OLD, AGGREGATOR).with_deactivation_timestamp(200),
+            PbhEntrypoint::new(NEW, AGGREGATOR).with_activation_timestamp(100),
+        ]);
+
+        assert!(entrypoints.active(OLD, 199).is_some());
+        assert!(entrypoints.active(OLD, 200).is_none());
+        assert_eq!(entrypoints.latest(200).unwrap().entrypoint, NEW);
+        assert!(entrypoints.contains(OLD));
+    }
+
+    #[test]
+    fn parse_entrypoint() {
+        let entrypoint = PbhEntrypoint::new(NEW, AGGREGATOR).with_activation_timestamp(1700000000);
+        assert_eq!(
+            entrypoint.to_string().parse::<PbhEntrypoint>(),
+            Ok(entrypoint)
+        );
+
+        let v2 = entrypoint.with_signal_version(2);
+        assert_eq!(v2.to_string().parse::<PbhEntrypoint>(), Ok(v2));
+        assert_eq!(
+            format!("{NEW}:{AGGREGATOR}:1700000000::2").parse::<PbhEntrypoint>(),
+            Ok(v2)
+        );
+
+        let entrypoint = entrypoint.with_deactivation_timestamp(1800000000);
+        assert_eq!(
+            entrypoint.to_string().parse::<PbhEntrypoint>(),
+            Ok(entrypoint)
+        );
+        let v2 = entrypoint.with_signal_version(2);
+        assert_eq!(v2.to_string().parse::<PbhEntrypoint>(), Ok(v2));
+        assert_eq!(v2.user_op_signal(480).unwrap().version(), 2);
+
+        assert!(format!("{NEW}:{AGGREGATOR}")
+            .parse::<PbhEntrypoint>()
+            .is_err());
+        assert!(format!("{NEW}:{AGGREGATOR}:1:2:3")
+            .parse::<PbhEntrypoint>()
+            .is_err());
+        assert!(format!("{NEW}:{AGGREGATOR}:1:2:1:0")
+            .parse::<PbhEntrypoint>()
+            .is_err());
+        assert!(format!("{NEW}:{AGGREGATOR}:soon")
+            .parse::<PbhEntrypoint>()
+            .is_err());
+    }
+}
@@ -1,23 +1,113 @@
+use reth::transaction_pool::{
+    error::{InvalidPoolTransactionError, PoolTransactionError},
+    PoolTransaction, TransactionValidationOutcome,
+};
 use reth_db::DatabaseError;
 use reth_provider::ProviderError;
+use revm_primitives::B256;
+use thiserror::Error;
+use world_chain_pbh::payload::{PBHBatchValidationError, PBHValidationError};
 
-#[derive(Debug, thiserror::Error, PartialEq, Eq)]
-pub enum WorldChainTransactionPoolInvalid {
-    #[error("invalid external nullifier period")]
-    InvalidExternalNullifierPeriod,
-    #[error("invalid external nullifier nonce")]
-    InvalidExternalNullifierNonce,
-    #[error("invalid semaphore proof")]
-    InvalidSemaphoreProof,
-    #[error("duplicate tx hash")]
-    DuplicateTxHash,
-    #[error("invalid root")]
-    InvalidRoot,
-    #[error(transparent)]
-    MalformedSignature(#[from] alloy_rlp::Error),
+use crate::{quota::PbhQuotaError, simulation::SimulationError};
+
+/// The reason a transaction was rejected by the World Chain validator.
+///
+/// Transactions which can never become valid, such as PBH transactions carrying an invalid
+/// proof or malformed calldata, are reported as bad transactions so that the peers relaying them
+/// are penalised. Transactions which were valid when they were relayed but have since gone stale
+/// (e.g. an expired root or a nullifier hash spent in the meantime), or which only violate a
+/// local policy of this node, are rejected without penalising the peer.
+#[derive(Debug, Error)]
+pub enum WorldChainPoolTransactionError {
+    #[error("Conditional Validation Failed: {0}")]
+    ConditionalValidationFailed(B256),
+    #[error("PBH Transaction Validation Failed: {0}")]
+    PBH(#[from] PBHValidationError),
+    /// A PBH payload of a bundle is invalid. The index refers to the position of the UserOp
+    /// across all aggregated UserOps of the bundle.
+    #[error("PBH Transaction Validation Failed: {0}")]
+    PBHPayload(#[from] PBHBatchValidationError),
+    #[error("PBH Transaction Rejected: {0}")]
+    Quota(#[from] PbhQuotaError),
+    #[error("PBH Bundle Simulation Failed: {0}")]
+    Simulation(#[from] SimulationError),
+}
+
+impl WorldChainPoolTransactionError {
+    pub fn to_outcome<T: PoolTransaction>(self, tx: T) -> TransactionValidationOutcome<T> {
+        TransactionValidationOutcome::Invalid(tx, self.into())
+    }
+
+    /// Returns the stable numeric code of the error.
+    ///
+    /// Codes are exposed to RPC clients and must never be changed or reused.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::ConditionalValidationFailed(_) => 1301,
+            Self::PBH(error) => error.code(),
+            Self::PBHPayload(error) => error.error.code(),
+            Self::Quota(error) => error.code(),
+            Self::Simulation(error) => error.code(),
+        }
+    }
+
+    /// Returns the index of the offending UserOp of a PBH bundle, if the error concerns one.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            Self::PBHPayload(error) => Some(error.index),
+            Self::Simulation(error) => error.index(),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, thiserror::Error)]
+/// Returns `true` if `error` can only be caused by a transaction which was never valid.
+fn is_bad_pbh_transaction(error: &PBHValidationError) -> bool {
+    match error {
+        PBHValidationError::InvalidProof
+        | PBHValidationError::ProofError { .. }
+        | PBHValidationError::InvalidCalldata
+        | PBHValidationError::MissingPbhPayload { .. }
+        | PBHValidationError::InvalidSignatureAggregator { .. }
+        | PBHValidationError::DuplicateNullifierHash { .. } => true,
+        // Roots expire, periods roll over, nullifier hashes get spent and the PBH limits can be
+        // lowered while a transaction is propagated, the call tracer is a local failure, and
+        // peers may already accept newer external nullifier versions or entrypoints.
+        PBHValidationError::InvalidRoot { .. }
+        | PBHValidationError::InvalidExternalNullifierPeriod { .. }
+        | PBHValidationError::InvalidExternalNullifierNonce { .. }
+        | PBHValidationError::PBHCallTracerError
+        | PBHValidationError::PbhGasLimitExceeded { .. }
+        | PBHValidationError::SpentNullifierHash { .. }
+        | PBHValidationError::PooledNullifierHash { .. }
+        | PBHValidationError::UnsupportedExternalNullifierVersion { .. }
+        | PBHValidationError::InactiveEntrypoint { .. } => false,
+    }
+}
+
+impl From<WorldChainPoolTransactionError> for InvalidPoolTransactionError {
+    fn from(val: WorldChainPoolTransactionError) -> Self {
+        InvalidPoolTransactionError::Other(Box::new(val))
+    }
+}
+
+impl PoolTransactionError for WorldChainPoolTransactionError {
+    fn is_bad_transaction(&self) -> bool {
+        match self {
+            Self::PBH(error) => is_bad_pbh_transaction(error),
+            Self::PBHPayload(error) => is_bad_pbh_transaction(&error.error),
+            // Conditions depend on the current block, while quotas and simulation are local
+            // policies peers cannot be expected to enforce.
+            Self::ConditionalValidationFailed(_) | Self::Quota(_) | Self::Simulation(_) => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Debug, Error)]
 pub enum WorldChainTransactionPoolError {
     #[error(transparent)]
     Database(#[from] DatabaseError),
@@ -26,3 +116,81 @@ pub enum WorldChainTransactionPoolError {
     #[error("invalid entrypoint - {0}")]
     Initialization(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, U256};
+    use semaphore_rs::Field;
+    use world_chain_pbh::external_nullifier::ExternalNullifier;
+
+    use super::*;
+
+    #[test]
+    fn bad_transactions_are_penalised() {
+        let bad = [
+            WorldChainPoolTransactionError::from(PBHValidationError::InvalidProof),
+            WorldChainPoolTransactionError::from(PBHValidationError::InvalidCalldata),
+            WorldChainPoolTransactionError::from(PBHValidationError::InvalidSignatureAggregator {
+                aggregator: Address::ZERO,
+            }),
+            WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                index: 1,
+                error: PBHValidationError::DuplicateNullifierHash {
+                    nullifier_hash: Field::from(1u64),
+                },
+            }),
+        ];
+        for error in bad {
+            assert!(error.is_bad_transaction(), "{error}");
+        }
+    }
+
+    #[test]
+    fn stale_transactions_are_not_penalised() {
+        let stale = [
+            WorldChainPoolTransactionError::from(PBHValidationError::InvalidRoot {
+                root: Field::from(1u64),
+            }),
+            WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                index: 0,
+                error: PBHValidationError::InvalidExternalNullifierPeriod {
+                    external_nullifier: ExternalNullifier::v1(1, 2025, 0),
+                    expected: chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+                },
+            }),
+            WorldChainPoolTransactionError::from(PBHValidationError::SpentNullifierHash {
+                nullifier_hash: Field::from(1u64),
+                block_number: U256::from(1),
+            }),
+            WorldChainPoolTransactionError::from(PbhQuotaError::PbhPoolFull { limit: 1 }),
+            WorldChainPoolTransactionError::from(SimulationError::Reverted(Default::default())),
+            WorldChainPoolTransactionError::ConditionalValidationFailed(B256::ZERO),
+        ];
+        for error in stale {
+            assert!(!error.is_bad_transaction(), "{error}");
+        }
+    }
+
+    #[test]
+    fn codes_and_indices() {
+        let error = WorldChainPoolTransactionError::from(PBHBatchValidationError {
+            index: 3,
+            error: PBHValidationError::InvalidProof,
+        });
+        assert_eq!(error.code(), PBHValidationError::InvalidProof.code());
+        assert_eq!(error.index(), Some(3));
+
+        let error = WorldChainPoolTransactionError::from(SimulationError::CallGasLimitExceeded {
+            index: 2,
+            gas_limit: 2,
+            limit: 1,
+        });
+        assert_eq!(error.code(), 1202);
+        assert_eq!(error.index(), Some(2));
+
+        assert_eq!(
+            WorldChainPoolTransactionError::from(PbhQuotaError::PbhPoolFull { limit: 1 }).code(),
+            1103
+        );
+    }
+}
@@ -1,6 +1,6 @@
 #![warn(unused_crate_dependencies)]
 
-use ordering::WorldChainOrdering;
+use ordering::{WeightedPriorityPolicy, WorldChainOrdering};
 use reth::{
     api::FullNodeTypes,
     transaction_pool::{blobstore::DiskFileBlobStore, Pool, TransactionValidationTaskExecutor},
@@ -10,17 +10,26 @@ use validator::WorldChainTransactionValidator;
 
 pub mod bindings;
 pub mod eip4337;
+pub mod entrypoint;
 pub mod error;
+pub mod limits;
+pub mod maintain;
+pub mod multicall;
 pub mod noop;
+pub mod nullifier;
+pub mod nullifier_index;
 pub mod ordering;
+pub mod proof_cache;
+pub mod quota;
 pub mod root;
+pub mod simulation;
 pub mod tx;
 pub mod validator;
 
 /// Type alias for World Chain transaction pool
 pub type WorldChainTransactionPool<Client, S, T = WorldChainPooledTransaction> = Pool<
     TransactionValidationTaskExecutor<WorldChainTransactionValidator<Client, T>>,
-    WorldChainOrdering<WorldChainPooledTransaction>,
+    WorldChainOrdering<WorldChainPooledTransaction, WeightedPriorityPolicy>,
     S,
 >;
 
@@ -0,0 +1,344 @@
+//! Tracking of the PBH limits configured on the PBHEntryPoint.
+use std::sync::{
+    atomic::{AtomicU16, AtomicU64, Ordering},
+    Arc,
+};
+
+use alloy_consensus::TxReceipt;
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, Log, TxHash};
+use alloy_sol_types::SolEvent;
+use futures_util::{Stream, StreamExt};
+use reth::transaction_pool::TransactionPool;
+use reth_primitives::NodePrimitives;
+use reth_provider::{CanonStateNotification, ProviderResult, StateProvider, StateProviderFactory};
+use tracing::{debug, warn};
+
+use crate::{
+    bindings::IPBHEntryPoint::{NumPbhPerMonthSet, PBHEntryPointImplInitialized, PBHGasLimitSet},
+    tx::WorldChainPoolTransaction,
+    validator::{pbh_gas_limit, pbh_nonce_limit},
+};
+
+/// The PBH limits enforced by the PBHEntryPoint.
+#[derive(Debug, Default)]
+pub struct PbhLimits {
+    /// The maximum number of PBH transactions a single World ID can execute in a period.
+    nonce_limit: AtomicU16,
+    /// The maximum amount of gas a single PBH transaction can consume.
+    gas_limit: AtomicU64,
+}
+
+impl PbhLimits {
+    /// Creates limits of `nonce_limit` PBH transactions per period and `gas_limit` gas each.
+    pub const fn new(nonce_limit: u16, gas_limit: u64) -> Self {
+        Self {
+            nonce_limit: AtomicU16::new(nonce_limit),
+            gas_limit: AtomicU64::new(gas_limit),
+        }
+    }
+
+    /// Reads the limits from the storage of the PBHEntryPoint at `pbh_entrypoint`.
+    pub fn from_state(state: &impl StateProvider, pbh_entrypoint: Address) -> ProviderResult<Self> {
+        Ok(Self::new(
+            pbh_nonce_limit(state, pbh_entrypoint)?,
+            pbh_gas_limit(state, pbh_entrypoint)?,
+        ))
+    }
+
+    /// Returns the maximum number of PBH transactions a World ID can execute in a period.
+    pub fn nonce_limit(&self) -> u16 {
+        self.nonce_limit.load(Ordering::Relaxed)
+    }
+
+    /// Returns the maximum amount of gas a single PBH transaction can consume.
+    pub fn gas_limit(&self) -> u64 {
+        self.gas_limit.load(Ordering::Relaxed)
+    }
+
+    /// Sets the maximum number of PBH transactions a World ID can execute in a period.
+    pub fn set_nonce_limit(&self, nonce_limit: u16) {
+        self.nonce_limit.store(nonce_limit, Ordering::Relaxed);
+    }
+
+    /// Sets the maximum amount of gas a single PBH transaction can consume.
+    pub fn set_gas_limit(&self, gas_limit: u64) {
+        self.gas_limit.store(gas_limit, Ordering::Relaxed);
+    }
+
+    /// Overwrites the limits with the values in the storage of the PBHEntryPoint.
+    pub fn reload(
+        &self,
+        state: &impl StateProvider,
+        pbh_entrypoint: Address,
+    ) -> ProviderResult<()> {
+        let limits = Self::from_state(state, pbh_entrypoint)?;
+        self.set_nonce_limit(limits.nonce_limit());
+        self.set_gas_limit(limits.gas_limit());
+        Ok(())
+    }
+
+    /// Applies a `PBHEntryPointImplInitialized`, `NumPbhPerMonthSet` or `PBHGasLimitSet` event
+    /// emitted by the PBHEntryPoint at `pbh_entrypoint`.
+    ///
+    /// The initialization event carries both limits, so that an entrypoint initialized after the
+    /// node started does not keep enforcing the zero limits read from its empty storage.
+    ///
+    /// Returns `true` if the log updated one of the limits.
+    pub fn apply_log(&self, pbh_entrypoint: Address, log: &Log) -> bool {
+        if log.address != pbh_entrypoint {
+            return false;
+        }
+
+        match log.data.topics().first() {
+            Some(&PBHEntryPointImplInitialized::SIGNATURE_HASH) => {
+                let Ok(event) = PBHEntryPointImplInitialized::decode_log_data(&log.data) else {
+                    return false;
+                };
+                self.set_nonce_limit(event.numPbhPerMonth);
+                self.set_gas_limit(event.pbhGasLimit.saturating_to());
+                true
+            }
+            Some(&NumPbhPerMonthSet::SIGNATURE_HASH) => {
+                let Ok(event) = NumPbhPerMonthSet::decode_log_data(&log.data) else {
+                    return false;
+                };
+                self.set_nonce_limit(event.numPbhPerMonth);
+                true
+            }
+            Some(&PBHGasLimitSet::SIGNATURE_HASH) => {
+                let Ok(event) = PBHGasLimitSet::decode_log_data(&log.data) else {
+                    return false;
+                };
+                self.set_gas_limit(event.pbhGasLimit.saturating_to());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if `tx` is a PBH transaction which exceeds the limits.
+    pub fn is_exceeded_by<Tx: WorldChainPoolTransaction>(&self, tx: &Tx) -> bool {
+        let Some(payloads) = tx.pbh_payload() else {
+            return false;
+        };
+
+        let nonce_limit = self.nonce_limit();
+        tx.gas_limit() > self.gas_limit()
+            || payloads
+                .iter()
+                .any(|payload| payload.external_nullifier.nonce >= nonce_limit)
+    }
+}
+
+/// Returns the hashes of all pooled PBH transactions to the PBHEntryPoint at `pbh_entrypoint`
+/// which exceed `limits`.
+pub fn exceeding_pbh_transactions<Pool>(
+    pool: &Pool,
+    pbh_entrypoint: Address,
+    limits: &PbhLimits,
+) -> Vec<TxHash>
+where
+    Pool: TransactionPool<Transaction: WorldChainPoolTransaction>,
+{
+    pool.pooled_transactions()
+        .into_iter()
+        .filter(|tx| {
+            tx.transaction.to() == Some(pbh_entrypoint) && limits.is_exceeded_by(&tx.transaction)
+        })
+        .map(|tx| *tx.hash())
+        .collect()
+}
+
+/// Long running task that keeps the [`PbhLimits`] in sync with the PBHEntryPoint.
+///
+/// The limits are updated from the `PBHEntryPointImplInitialized`, `NumPbhPerMonthSet` and
+/// `PBHGasLimitSet` events in the receipts of every committed block, and reloaded from storage
+/// on reorgs. Whenever the limits change, pooled PBH transactions which no longer satisfy them
+/// are evicted.
+pub async fn maintain_pbh_limits<Client, N, Pool, St>(
+    client: Client,
+    pool: Pool,
+    limits: Arc<PbhLimits>,
+    pbh_entrypoint: Address,
+    mut events: St,
+) where
+    Client: StateProviderFactory,
+    N: NodePrimitives,
+    Pool: TransactionPool<Transaction: WorldChainPoolTransaction>,
+    St: Stream<Item = CanonStateNotification<N>> + Send + Unpin + 'static,
+{
+    while let Some(notification) = events.next().await {
+        let mut changed = false;
+
+        if notification.reverted().is_some() {
+            match client
+                .state_by_block_id(BlockId::latest())
+                .and_then(|state| limits.reload(&state, pbh_entrypoint))
+            {
+                Ok(()) => changed = true,
+                Err(err) => warn!(
+                    target: "world_chain::pool",
+                    %err,
+                    "Failed to reload PBH limits after reorg"
+                ),
+            }
+        } else {
+            for (_, receipts) in notification.committed().blocks_and_receipts() {
+                for log in receipts.iter().flat_map(|receipt| receipt.logs()) {
+                    changed |= limits.apply_log(pbh_entrypoint, log);
+                }
+            }
+        }
+
+        if !changed {
+            continue;
+        }
+
+        debug!(
+            target: "world_chain::pool",
+            nonce_limit = limits.nonce_limit(),
+            gas_limit = limits.gas_limit(),
+            "Updated PBH limits"
+        );
+
+        let exceeding = exceeding_pbh_transactions(&pool, pbh_entrypoint, &limits);
+        if !exceeding.is_empty() {
+            debug!(
+                target: "world_chain::pool",
+                count = exceeding.len(),
+                "Evicting PBH transactions exceeding the PBH limits"
+            );
+            pool.remove_transactions(exceeding);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, U256};
+    use alloy_sol_types::SolCall;
+    use reth::transaction_pool::TransactionPool;
+    use world_chain_pbh::{date_marker::DateMarker, external_nullifier::ExternalNullifier};
+    use world_chain_test::{
+        utils::{eip1559, eth_tx, pbh_bundle, user_op},
+        PBH_DEV_ENTRYPOINT,
+    };
+
+    use super::*;
+    use crate::validator::tests::{setup_with_validator, world_chain_validator};
+
+    #[test]
+    fn apply_limit_events() {
+        let limits = PbhLimits::new(30, 15_000_000);
+
+        let nonce_limit = NumPbhPerMonthSet { numPbhPerMonth: 5 }.encode_log_data();
+        let gas_limit = PBHGasLimitSet {
+            pbhGasLimit: U256::from(1_000_000),
+        }
+        .encode_log_data();
+
+        // Unrelated logs and logs emitted by other contracts are ignored
+        let other = address!("0000000000000000000000000000000000000001");
+        assert!(!limits.apply_log(
+            PBH_DEV_ENTRYPOINT,
+            &Log::new_unchecked(PBH_DEV_ENTRYPOINT, vec![], Default::default())
+        ));
+        assert!(!limits.apply_log(
+            PBH_DEV_ENTRYPOINT,
+            &Log {
+                address: other,
+                data: nonce_limit.clone(),
+            }
+        ));
+        assert_eq!(limits.nonce_limit(), 30);
+
+        assert!(limits.apply_log(
+            PBH_DEV_ENTRYPOINT,
+            &Log {
+                address: PBH_DEV_ENTRYPOINT,
+                data: nonce_limit,
+            }
+        ));
+        assert!(limits.apply_log(
+            PBH_DEV_ENTRYPOINT,
+            &Log {
+                address: PBH_DEV_ENTRYPOINT,
+                data: gas_limit,
+            }
+        ));
+        assert_eq!(limits.nonce_limit(), 5);
+        assert_eq!(limits.gas_limit(), 1_000_000);
+    }
+
+    #[test]
+    fn apply_initialization_event() {
+        // An entrypoint initialized after the node started has no limits in storage yet
+        let limits = PbhLimits::new(0, 0);
+
+        let initialized = PBHEntryPointImplInitialized {
+            worldId: Address::ZERO,
+            entryPoint: Address::ZERO,
+            numPbhPerMonth: 30,
+            pbhGasLimit: U256::from(15_000_000),
+            authorizedBuilders: vec![Address::ZERO],
+            owner: Address::ZERO,
+        }
+        .encode_log_data();
+        assert!(limits.apply_log(
+            PBH_DEV_ENTRYPOINT,
+            &Log {
+                address: PBH_DEV_ENTRYPOINT,
+                data: initialized,
+            }
+        ));
+        assert_eq!(limits.nonce_limit(), 30);
+        assert_eq!(limits.gas_limit(), 15_000_000);
+    }
+
+    #[tokio::test]
+    async fn lowered_limits_exceeded_by_pooled_transactions() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let validator = world_chain_validator();
+        let limits = validator.pbh_limits(PBH_DEV_ENTRYPOINT).unwrap().clone();
+        let pool = setup_with_validator(validator).await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let gas_limit = tx.gas_limit;
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let hash = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect("Failed to add transaction")
+            .hash;
+
+        assert!(exceeding_pbh_transactions(&pool, PBH_DEV_ENTRYPOINT, &limits).is_empty());
+
+        limits.set_gas_limit(gas_limit - 1);
+        assert_eq!(
+            exceeding_pbh_transactions(&pool, PBH_DEV_ENTRYPOINT, &limits),
+            vec![hash]
+        );
+
+        limits.set_gas_limit(gas_limit);
+        limits.set_nonce_limit(0);
+        assert_eq!(
+            exceeding_pbh_transactions(&pool, PBH_DEV_ENTRYPOINT, &limits),
+            vec![hash]
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! Maintenance of PBH transactions in the World Chain transaction pool.
+use std::time::Duration;
+
+use alloy_consensus::BlockHeader;
+use alloy_eips::BlockId;
+use alloy_primitives::TxHash;
+use futures_util::{Stream, StreamExt};
+use metrics::Counter;
+use metrics_derive::Metrics;
+use reth::transaction_pool::TransactionPool;
+use reth_primitives::NodePrimitives;
+use reth_provider::{
+    BlockReaderIdExt, CanonStateNotification, StateProvider, StateProviderFactory,
+};
+use tracing::{debug, warn};
+use world_chain_pbh::{
+    clock::{Clock, FixedClock},
+    payload::{PBHPayload, RootSet},
+};
+
+use crate::{
+    entrypoint::PbhEntrypoints, nullifier::is_nullifier_hash_spent, root::WorldChainRootValidator,
+    tx::WorldChainPoolTransaction,
+};
+
+/// Configuration for [`maintain_pbh_transactions`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaintainPbhConfig {
+    /// The duration after a period rollover during which PBH payloads for the previous period
+    /// are still considered valid.
+    pub grace_period: Duration,
+}
+
+/// The reason a pooled PBH transaction can no longer be included on chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StalePbhReason {
+    /// A payload references a World ID root which is no longer valid.
+    InvalidRoot,
+    /// A payload carries an external nullifier for a period which has expired.
+    ExpiredPeriod,
+    /// A payload carries a nullifier hash which has been spent on chain.
+    SpentNullifier,
+    /// The transaction is sent to a PBHEntryPoint which has been deactivated.
+    InactiveEntrypoint,
+}
+
+/// Long running task that evicts PBH transactions which can no longer be included on chain.
+///
+/// On every new canonical tip, all pooled PBH transactions are revalidated against the tip.
+/// Transactions sent to a deactivated entrypoint, or carrying a payload whose root is no longer
+/// valid, whose external nullifier period has expired (taking into account the configured grace
+/// period) or whose nullifier hash has been spent on any of the `pbh_entrypoints` are removed
+/// from the pool rather than being left to fail in the payload builder.
+///
+/// The roots are read from `root_validator`, which is kept up to date by
+/// [`maintain_roots`](crate::root::maintain_roots). A root expiring in the same block as the
+/// notification may therefore only be evicted on the following block.
+pub async fn maintain_pbh_transactions<Client, N, Pool, St>(
+    client: Client,
+    pool: Pool,
+    root_validator: WorldChainRootValidator<Client>,
+    pbh_entrypoints: PbhEntrypoints,
+    mut events: St,
+    config: MaintainPbhConfig,
+) where
+    Client: StateProviderFactory + BlockReaderIdExt,
+    N: NodePrimitives,
+    Pool: TransactionPool<Transaction: WorldChainPoolTransaction>,
+    St: Stream<Item = CanonStateNotification<N>> + Send + Unpin + 'static,
+{
+    let metrics = MaintainPbhMetrics::default();
+
+    while let Some(event) = events.next().await {
+        let timestamp = event.tip().header().timestamp();
+        let Some(clock) = FixedClock::from_timestamp(timestamp) else {
+            continue;
+        };
+
+        let state = match client.state_by_block_id(BlockId::latest()) {
+            Ok(state) => state,
+            Err(err) => {
+                warn!(
+                    target: "world_chain::pool",
+                    %err,
+                    "Failed to read the latest state, skipping PBH revalidation"
+                );
+                continue;
+            }
+        };
+
+        let stale = stale_pbh_transactions(
+            &pool,
+            &clock,
+            config.grace_period,
+            &*root_validator.valid_roots(),
+            &pbh_entrypoints,
+            &state,
+        );
+        if stale.is_empty() {
+            continue;
+        }
+
+        for (_, reason) in &stale {
+            metrics.record_eviction(*reason);
+        }
+
+        debug!(
+            target: "world_chain::pool",
+            count = stale.len(),
+            timestamp,
+            "Evicting stale PBH transactions"
+        );
+        pool.remove_transactions(stale.into_iter().map(|(hash, _)| hash).collect());
+    }
+}
+
+/// Returns the hashes of all pooled PBH transactions which can no longer be included on chain,
+/// along with the reason.
+///
+/// A transaction is stale if it is sent to an entrypoint which is no longer active at the time
+/// reported by `clock`, or if any of its payloads references a root not in `valid_roots`, has an
+/// external nullifier period which is no longer valid, or has a nullifier hash which is spent on
+/// any of the `pbh_entrypoints`.
+pub fn stale_pbh_transactions<Pool, C>(
+    pool: &Pool,
+    clock: &C,
+    grace_period: Duration,
+    valid_roots: &(impl RootSet + ?Sized),
+    pbh_entrypoints: &PbhEntrypoints,
+    state: &impl StateProvider,
+) -> Vec<(TxHash, StalePbhReason)>
+where
+    Pool: TransactionPool<Transaction: WorldChainPoolTransaction>,
+    C: Clock + ?Sized,
+{
+    let now = clock.now();
+    let timestamp = now.timestamp().max(0) as u64;
+    pool.pooled_transactions()
+        .into_iter()
+        .filter_map(|tx| {
+            let pbh_entrypoint = tx.transaction.to()?;
+            let payloads = tx.transaction.pbh_payload()?;
+            if pbh_entrypoints.active(pbh_entrypoint, timestamp).is_none() {
+                return Some((*tx.hash(), StalePbhReason::InactiveEntrypoint));
+            }
+
+            let reason = payloads.iter().find_map(|payload| {
+                if payload.validate_root(valid_roots).is_err() {
+                    return Some(StalePbhReason::InvalidRoot);
+                }
+
+                if payload
+                    .validate_external_nullifier_period(now, grace_period)
+                    .is_err()
+                {
+                    return Some(StalePbhReason::ExpiredPeriod);
+                }
+
+                is_spent(state, pbh_entrypoints, payload).then_some(StalePbhReason::SpentNullifier)
+            })?;
+
+            Some((*tx.hash(), reason))
+        })
+        .collect()
+}
+
+/// Returns `true` if the nullifier hash of `payload` has been spent.
+///
+/// Read errors are treated as unspent, leaving the transaction in the pool.
+fn is_spent(
+    state: &impl StateProvider,
+    pbh_entrypoints: &PbhEntrypoints,
+    payload: &PBHPayload,
+) -> bool {
+    is_nullifier_hash_spent(state, pbh_entrypoints, payload.nullifier_hash).unwrap_or_else(|err| {
+        warn!(
+            target: "world_chain::pool",
+            %err,
+            nullifier_hash = %payload.nullifier_hash,
+            "Failed to read the spent status of a nullifier hash"
+        );
+        false
+    })
+}
+
+/// Metrics for [`maintain_pbh_transactions`].
+#[derive(Metrics)]
+#[metrics(scope = "pbh_pool_maintenance")]
+struct MaintainPbhMetrics {
+    /// Total number of PBH transactions evicted because a root is no longer valid.
+    evicted_invalid_root: Counter,
+    /// Total number of PBH transactions evicted because an external nullifier period expired.
+    evicted_expired_period: Counter,
+    /// Total number of PBH transactions evicted because a nullifier hash was spent.
+    evicted_spent_nullifier: Counter,
+    /// Total number of PBH transactions evicted because their entrypoint was deactivated.
+    evicted_inactive_entrypoint: Counter,
+}
+
+impl MaintainPbhMetrics {
+    fn record_eviction(&self, reason: StalePbhReason) {
+        match reason {
+            StalePbhReason::InvalidRoot => self.evicted_invalid_root.increment(1),
+            StalePbhReason::ExpiredPeriod => self.evicted_expired_period.increment(1),
+            StalePbhReason::SpentNullifier => self.evicted_spent_nullifier.increment(1),
+            StalePbhReason::InactiveEntrypoint => self.evicted_inactive_entrypoint.increment(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy_primitives::Address;
+    use alloy_sol_types::SolCall;
+    use chrono::TimeZone;
+    use reth::transaction_pool::TransactionPool;
+    use semaphore_rs::Field;
+    use world_chain_pbh::{
+        clock::{Clock, FixedClock},
+        date_marker::DateMarker,
+        external_nullifier::ExternalNullifier,
+    };
+    use world_chain_test::{
+        mock::ExtendedAccount,
+        utils::{eip1559, eth_tx, pbh_bundle, user_op, TREE},
+        PBH_DEV_ENTRYPOINT,
+    };
+
+    use super::{stale_pbh_transactions, StalePbhReason};
+    use crate::{
+        entrypoint::{PbhEntrypoint, PbhEntrypoints},
+        nullifier::nullifier_hash_slot,
+        tx::WorldChainPoolTransaction,
+        validator::tests::{setup_with_validator, world_chain_validator},
+    };
+
+    #[tokio::test]
+    async fn evicts_expired_pbh_transactions() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let clock = FixedClock::new(
+            chrono::Utc
+                .with_ymd_and_hms(2025, 1, 31, 23, 59, 59)
+                .unwrap(),
+        );
+        let validator = world_chain_validator().with_clock(clock);
+        let client = validator.inner().client().clone();
+        let pbh_entrypoints = validator.pbh_entrypoints().clone();
+        let pool = setup_with_validator(validator).await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::v1(1, 2025, 0))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let hash = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect("Failed to add transaction")
+            .hash;
+
+        let grace_period = Duration::from_secs(60);
+        let valid_roots = vec![TREE.root()];
+
+        // Within the grace period the transaction is retained
+        let clock = FixedClock::new(chrono::Utc.with_ymd_and_hms(2025, 2, 1, 0, 1, 0).unwrap());
+        assert!(stale_pbh_transactions(
+            &pool,
+            &clock,
+            grace_period,
+            &valid_roots,
+            &pbh_entrypoints,
+            &client
+        )
+        .is_empty());
+
+        // Once the grace period has elapsed the transaction is expired
+        let clock = FixedClock::new(chrono::Utc.with_ymd_and_hms(2025, 2, 1, 0, 1, 1).unwrap());
+        assert_eq!(
+            stale_pbh_transactions(
+                &pool,
+                &clock,
+                grace_period,
+                &valid_roots,
+                &pbh_entrypoints,
+                &client
+            ),
+            vec![(hash, StalePbhReason::ExpiredPeriod)]
+        );
+    }
+
+    #[tokio::test]
+    async fn evicts_stale_pbh_transactions() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let validator = world_chain_validator();
+        let client = validator.inner().client().clone();
+        let pbh_entrypoints = validator.pbh_entrypoints().clone();
+        let pool = setup_with_validator(validator).await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let hash = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect("Failed to add transaction")
+            .hash;
+
+        let clock = FixedClock::new(chrono::Utc::now());
+        let valid_roots = vec![TREE.root()];
+        let no_roots: Vec<Field> = vec![];
+
+        assert!(stale_pbh_transactions(
+            &pool,
+            &clock,
+            Duration::ZERO,
+            &valid_roots,
+            &pbh_entrypoints,
+            &client
+        )
+        .is_empty());
+
+        // The root has aged out of the valid roots
+        assert_eq!(
+            stale_pbh_transactions(
+                &pool,
+                &clock,
+                Duration::ZERO,
+                &no_roots,
+                &pbh_entrypoints,
+                &client
+            ),
+            vec![(hash, StalePbhReason::InvalidRoot)]
+        );
+
+        // The nullifier hash has been spent by another transaction
+        let nullifier_hash = pool.pooled_transactions()[0]
+            .transaction
+            .pbh_payload()
+            .unwrap()[0]
+            .nullifier_hash;
+        client.add_account(
+            PBH_DEV_ENTRYPOINT,
+            ExtendedAccount::new(0, alloy_primitives::U256::ZERO).extend_storage(vec![(
+                nullifier_hash_slot(nullifier_hash),
+                alloy_primitives::U256::from(1),
+            )]),
+        );
+        assert_eq!(
+            stale_pbh_transactions(
+                &pool,
+                &clock,
+                Duration::ZERO,
+                &valid_roots,
+                &pbh_entrypoints,
+                &client
+            ),
+            vec![(hash, StalePbhReason::SpentNullifier)]
+        );
+
+        // The entrypoint has been deactivated
+        let deactivated =
+            PbhEntrypoints::new([PbhEntrypoint::new(PBH_DEV_ENTRYPOINT, Address::ZERO)
+                .with_deactivation_timestamp(clock.now().timestamp() as u64)]);
+        assert_eq!(
+            stale_pbh_transactions(
+                &pool,
+                &clock,
+                Duration::ZERO,
+                &valid_roots,
+                &deactivated,
+                &client
+            ),
+            vec![(hash, StalePbhReason::InactiveEntrypoint)]
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use alloy_primitives::Address;
+use alloy_sol_types::SolValue;
+use semaphore_rs::{hash_to_field, Field};
+
+use crate::bindings::IMulticall3::Call3;
+
+/// Returns the signal the PBH payload of a `pbhMulticall` transaction sent by `sender` must be
+/// proven for.
+///
+/// Matches `abi.encode(msg.sender, calls).hashToField()` in the PBHEntryPoint.
+pub fn hash_pbh_multicall(sender: Address, calls: &[Call3]) -> Field {
+    let hash = SolValue::abi_encode_params(&(sender, calls));
+
+    hash_to_field(hash.as_slice())
+}
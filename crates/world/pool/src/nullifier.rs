@@ -0,0 +1,144 @@
+//! Lookup of the PBH nullifier hashes spent on chain.
+use alloy_primitives::{keccak256, B256, U256};
+use reth_provider::{ProviderResult, StateProvider};
+use semaphore_rs::Field;
+
+use crate::entrypoint::PbhEntrypoints;
+
+/// The slot of the `nullifierHashes` mapping in the PBHEntryPoint contract.
+pub const PBH_NULLIFIER_HASHES_SLOT: U256 = U256::from_limbs([51, 0, 0, 0]);
+
+/// Returns the storage slot of `nullifier_hash` in the `nullifierHashes` mapping.
+///
+/// The mapping stores the block number in which the nullifier hash was spent, or zero if it is
+/// unspent.
+pub fn nullifier_hash_slot(nullifier_hash: Field) -> B256 {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(&nullifier_hash.to_be_bytes::<32>());
+    preimage[32..].copy_from_slice(&PBH_NULLIFIER_HASHES_SLOT.to_be_bytes::<32>());
+    keccak256(preimage)
+}
+
+/// Returns the number of the block in which `nullifier_hash` was spent on any of the
+/// `pbh_entrypoints`, or `None` if it is unspent on all of them.
+///
+/// Every PBHEntryPoint deployment keeps its own record of spent nullifier hashes, so a nullifier
+/// hash spent on one deployment must not be accepted by another one.
+pub fn nullifier_hash_spent_block(
+    state: &impl StateProvider,
+    pbh_entrypoints: &PbhEntrypoints,
+    nullifier_hash: Field,
+) -> ProviderResult<Option<U256>> {
+    let slot = nullifier_hash_slot(nullifier_hash);
+    for pbh_entrypoint in pbh_entrypoints.iter() {
+        let spent_block = state
+            .storage(pbh_entrypoint.entrypoint, slot)?
+            .filter(|block_number| !block_number.is_zero());
+        if spent_block.is_some() {
+            return Ok(spent_block);
+        }
+    }
+    Ok(None)
+}
+
+/// Returns `true` if `nullifier_hash` has been spent on any of the `pbh_entrypoints`.
+pub fn is_nullifier_hash_spent(
+    state: &impl StateProvider,
+    pbh_entrypoints: &PbhEntrypoints,
+    nullifier_hash: Field,
+) -> ProviderResult<bool> {
+    Ok(nullifier_hash_spent_block(state, pbh_entrypoints, nullifier_hash)?.is_some())
+}
+
+/// Returns the nullifier hashes which have not been spent on any of the `pbh_entrypoints`,
+/// preserving their order.
+///
+/// This is equivalent to calling `getUnspentNullifierHashes` on every PBHEntryPoint, but reads
+/// the `nullifierHashes` mappings directly instead of executing the contracts.
+pub fn unspent_nullifier_hashes(
+    state: &impl StateProvider,
+    pbh_entrypoints: &PbhEntrypoints,
+    nullifier_hashes: &[Field],
+) -> ProviderResult<Vec<Field>> {
+    let mut unspent = Vec::with_capacity(nullifier_hashes.len());
+    for &nullifier_hash in nullifier_hashes {
+        if !is_nullifier_hash_spent(state, pbh_entrypoints, nullifier_hash)? {
+            unspent.push(nullifier_hash);
+        }
+    }
+    Ok(unspent)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{hex, Address};
+    use world_chain_test::mock::{ExtendedAccount, MockEthProvider};
+
+    use super::*;
+    use crate::entrypoint::PbhEntrypoint;
+
+    #[test]
+    fn slot_matches_solidity_mapping_layout() {
+        // keccak256(abi.encode(uint256(1), uint256(51)))
+        assert_eq!(
+            nullifier_hash_slot(U256::from(1)),
+            keccak256(hex!(
+                "0000000000000000000000000000000000000000000000000000000000000001"
+                "0000000000000000000000000000000000000000000000000000000000000033"
+            ))
+        );
+    }
+
+    #[test]
+    fn unspent_preserves_order() {
+        let pbh_entrypoint = Address::with_last_byte(1);
+        let pbh_entrypoints = PbhEntrypoints::single(pbh_entrypoint, Address::ZERO);
+        let provider = MockEthProvider::default();
+        provider.add_account(
+            pbh_entrypoint,
+            ExtendedAccount::new(0, U256::ZERO)
+                .extend_storage([(nullifier_hash_slot(U256::from(2)), U256::from(10))]),
+        );
+
+        let hashes = [U256::from(1), U256::from(2), U256::from(3)];
+        assert_eq!(
+            nullifier_hash_spent_block(&provider, &pbh_entrypoints, hashes[1]).unwrap(),
+            Some(U256::from(10))
+        );
+        assert_eq!(
+            unspent_nullifier_hashes(&provider, &pbh_entrypoints, &hashes).unwrap(),
+            vec![hashes[0], hashes[2]]
+        );
+    }
+
+    #[test]
+    fn spent_on_any_entrypoint() {
+        let (old, new) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let pbh_entrypoints = PbhEntrypoints::new([
+            PbhEntrypoint::new(old, Address::ZERO).with_deactivation_timestamp(100),
+            PbhEntrypoint::new(new, Address::ZERO).with_activation_timestamp(100),
+        ]);
+        let provider = MockEthProvider::default();
+        provider.add_account(
+            old,
+            ExtendedAccount::new(0, U256::ZERO)
+                .extend_storage([(nullifier_hash_slot(U256::from(1)), U256::from(10))]),
+        );
+        provider.add_account(
+            new,
+            ExtendedAccount::new(0, U256::ZERO)
+                .extend_storage([(nullifier_hash_slot(U256::from(2)), U256::from(20))]),
+        );
+
+        // Nullifier hashes spent on the retired entrypoint can not be reused on the new one
+        let hashes = [U256::from(1), U256::from(2), U256::from(3)];
+        assert_eq!(
+            nullifier_hash_spent_block(&provider, &pbh_entrypoints, hashes[0]).unwrap(),
+            Some(U256::from(10))
+        );
+        assert_eq!(
+            unspent_nullifier_hashes(&provider, &pbh_entrypoints, &hashes).unwrap(),
+            vec![hashes[2]]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! A pool-wide index of the PBH nullifier hashes carried by pooled transactions.
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+use alloy_primitives::TxHash;
+use futures_util::StreamExt;
+use parking_lot::RwLock;
+use reth::transaction_pool::{FullTransactionEvent, PoolTransaction, TransactionPool};
+use semaphore_rs::Field;
+use tracing::debug;
+
+use crate::tx::WorldChainPoolTransaction;
+
+/// Decides which transaction is kept when two pooled transactions carry the same PBH nullifier
+/// hash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateNullifierPolicy {
+    /// The transaction which entered the pool first is kept, later duplicates are rejected.
+    #[default]
+    FirstSeen,
+    /// The transaction paying the higher priority fee is kept, the other one is rejected or
+    /// evicted. Ties are resolved in favor of the transaction which entered the pool first.
+    HigherTip,
+}
+
+impl DuplicateNullifierPolicy {
+    /// Returns `true` if a transaction paying `tip` should replace a pooled transaction
+    /// carrying the same nullifier hash and paying `existing_tip`.
+    pub fn replaces(&self, tip: u128, existing_tip: u128) -> bool {
+        match self {
+            Self::FirstSeen => false,
+            Self::HigherTip => tip > existing_tip,
+        }
+    }
+}
+
+impl fmt::Display for DuplicateNullifierPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FirstSeen => f.write_str("first_seen"),
+            Self::HigherTip => f.write_str("higher_tip"),
+        }
+    }
+}
+
+impl FromStr for DuplicateNullifierPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first_seen" => Ok(Self::FirstSeen),
+            "higher_tip" => Ok(Self::HigherTip),
+            _ => Err(format!(
+                "invalid duplicate nullifier policy `{s}`, expected `first_seen` or `higher_tip`"
+            )),
+        }
+    }
+}
+
+/// A pooled transaction claiming a nullifier hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullifierClaim {
+    /// The hash of the pooled transaction.
+    pub tx_hash: TxHash,
+    /// The priority fee paid by the pooled transaction.
+    pub tip: u128,
+}
+
+/// Maps the PBH nullifier hashes of pooled transactions to the transaction carrying them.
+///
+/// The index is consulted by the validator to reject transactions reusing the nullifier hash of
+/// another pooled transaction at admission, instead of leaving the conflict to the payload
+/// builder. It is kept in sync with the pool by [`maintain_nullifier_index`].
+#[derive(Debug, Default)]
+pub struct NullifierIndex {
+    claims: RwLock<Claims>,
+    policy: DuplicateNullifierPolicy,
+}
+
+/// The nullifier hash claims of pooled transactions, indexed in both directions so that a
+/// transaction leaving the pool releases its claims without scanning the whole index.
+#[derive(Debug, Default)]
+struct Claims {
+    by_nullifier_hash: HashMap<Field, NullifierClaim>,
+    by_tx_hash: HashMap<TxHash, Vec<Field>>,
+}
+
+impl Claims {
+    /// Removes all nullifier hashes claimed by the transaction `tx_hash`.
+    fn release(&mut self, tx_hash: &TxHash) {
+        for nullifier_hash in self.by_tx_hash.remove(tx_hash).unwrap_or_default() {
+            if self
+                .by_nullifier_hash
+                .get(&nullifier_hash)
+                .is_some_and(|claim| claim.tx_hash == *tx_hash)
+            {
+                self.by_nullifier_hash.remove(&nullifier_hash);
+            }
+        }
+    }
+}
+
+impl NullifierIndex {
+    /// Creates an empty index resolving duplicates according to `policy`.
+    pub fn new(policy: DuplicateNullifierPolicy) -> Self {
+        Self {
+            claims: Default::default(),
+            policy,
+        }
+    }
+
+    /// Returns the policy used to resolve duplicates.
+    pub fn policy(&self) -> DuplicateNullifierPolicy {
+        self.policy
+    }
+
+    /// Returns the pooled transaction claiming `nullifier_hash`, if any.
+    pub fn get(&self, nullifier_hash: &Field) -> Option<NullifierClaim> {
+        self.claims
+            .read()
+            .by_nullifier_hash
+            .get(nullifier_hash)
+            .copied()
+    }
+
+    /// Returns the first pooled transaction, other than `tx_hash`, which claims one of
+    /// `nullifier_hashes` and would not be replaced by a transaction paying `tip`, along with
+    /// the position of the conflicting nullifier hash.
+    pub fn conflict<'a>(
+        &self,
+        tx_hash: TxHash,
+        tip: u128,
+        nullifier_hashes: impl IntoIterator<Item = &'a Field>,
+    ) -> Option<(usize, NullifierClaim)> {
+        let claims = self.claims.read();
+        nullifier_hashes
+            .into_iter()
+            .enumerate()
+            .find_map(|(index, nullifier_hash)| {
+                claims
+                    .by_nullifier_hash
+                    .get(nullifier_hash)
+                    .filter(|claim| {
+                        claim.tx_hash != tx_hash && !self.policy.replaces(tip, claim.tip)
+                    })
+                    .map(|claim| (index, *claim))
+            })
+    }
+
+    /// Records the nullifier hashes of a transaction which entered the pool.
+    ///
+    /// Returns the hashes of the pooled transactions which lost a nullifier hash to the
+    /// transaction, or `[tx_hash]` if the transaction itself lost to an existing claim. Losing
+    /// transactions should be removed from the pool, and from the index through
+    /// [`Self::remove`].
+    pub fn insert<'a>(
+        &self,
+        tx_hash: TxHash,
+        tip: u128,
+        nullifier_hashes: impl IntoIterator<Item = &'a Field> + Clone,
+    ) -> Vec<TxHash> {
+        let mut guard = self.claims.write();
+        let claims = &mut *guard;
+
+        let mut replaced = vec![];
+        for nullifier_hash in nullifier_hashes.clone() {
+            let Some(claim) = claims.by_nullifier_hash.get(nullifier_hash) else {
+                continue;
+            };
+            if claim.tx_hash == tx_hash {
+                continue;
+            }
+            if !self.policy.replaces(tip, claim.tip) {
+                return vec![tx_hash];
+            }
+            if !replaced.contains(&claim.tx_hash) {
+                replaced.push(claim.tx_hash);
+            }
+        }
+
+        // Release every nullifier hash held by the replaced transactions
+        for replaced in &replaced {
+            claims.release(replaced);
+        }
+        let claimed = claims.by_tx_hash.entry(tx_hash).or_default();
+        for nullifier_hash in nullifier_hashes {
+            claims
+                .by_nullifier_hash
+                .insert(*nullifier_hash, NullifierClaim { tx_hash, tip });
+            if !claimed.contains(nullifier_hash) {
+                claimed.push(*nullifier_hash);
+            }
+        }
+
+        replaced
+    }
+
+    /// Removes all nullifier hashes claimed by the transaction `tx_hash`.
+    pub fn remove(&self, tx_hash: &TxHash) {
+        self.claims.write().release(tx_hash);
+    }
+
+    /// Returns the number of indexed nullifier hashes.
+    pub fn len(&self) -> usize {
+        self.claims.read().by_nullifier_hash.len()
+    }
+
+    /// Returns `true` if no nullifier hashes are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Long running task that keeps the [`NullifierIndex`] in sync with the pool.
+///
+/// Nullifier hashes are indexed when a transaction enters the pool, and released when it is
+/// mined, replaced, discarded or invalidated. Transactions losing a nullifier hash to another
+/// pooled transaction, e.g. because both were validated concurrently, are removed from the pool.
+pub async fn maintain_nullifier_index<Pool>(pool: Pool, index: Arc<NullifierIndex>)
+where
+    Pool: TransactionPool<Transaction: WorldChainPoolTransaction>,
+{
+    let mut events = pool.all_transactions_event_listener();
+    while let Some(event) = events.next().await {
+        match event {
+            FullTransactionEvent::Pending(tx_hash) | FullTransactionEvent::Queued(tx_hash, ..) => {
+                let Some(tx) = pool.get(&tx_hash) else {
+                    continue;
+                };
+                let Some(payloads) = tx.transaction.pbh_payload() else {
+                    continue;
+                };
+
+                let evicted = index.insert(
+                    tx_hash,
+                    tx.transaction.priority_fee_or_price(),
+                    payloads.iter().map(|payload| &payload.nullifier_hash),
+                );
+                if !evicted.is_empty() {
+                    debug!(
+                        target: "world_chain::pool",
+                        ?evicted,
+                        "Evicting PBH transactions with a duplicate nullifier hash"
+                    );
+                    for tx_hash in &evicted {
+                        index.remove(tx_hash);
+                    }
+                    pool.remove_transactions(evicted);
+                }
+            }
+            FullTransactionEvent::Mined { tx_hash, .. }
+            | FullTransactionEvent::Discarded(tx_hash)
+            | FullTransactionEvent::Invalid(tx_hash) => index.remove(&tx_hash),
+            FullTransactionEvent::Replaced { transaction, .. } => index.remove(transaction.hash()),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+
+    use super::*;
+
+    fn tx(n: u8) -> TxHash {
+        TxHash::with_last_byte(n)
+    }
+
+    #[test]
+    fn first_seen_rejects_duplicates() {
+        let index = NullifierIndex::new(DuplicateNullifierPolicy::FirstSeen);
+        let hashes = [U256::from(1), U256::from(2)];
+
+        assert!(index.insert(tx(1), 1, &hashes[..1]).is_empty());
+        assert_eq!(
+            index.conflict(tx(2), 100, &hashes),
+            Some((
+                0,
+                NullifierClaim {
+                    tx_hash: tx(1),
+                    tip: 1
+                }
+            ))
+        );
+        assert_eq!(index.insert(tx(2), 100, &hashes), vec![tx(2)]);
+        assert_eq!(index.get(&hashes[1]), None);
+
+        // Re-validation of the indexed transaction does not conflict with itself
+        assert_eq!(index.conflict(tx(1), 1, &hashes), None);
+    }
+
+    #[test]
+    fn higher_tip_replaces() {
+        let index = NullifierIndex::new(DuplicateNullifierPolicy::HigherTip);
+        let hashes = [U256::from(1), U256::from(2)];
+
+        assert!(index.insert(tx(1), 10, &hashes).is_empty());
+        assert!(index.conflict(tx(2), 10, &hashes[1..]).is_some());
+        assert!(index.conflict(tx(2), 11, &hashes[1..]).is_none());
+
+        assert_eq!(index.insert(tx(2), 11, &hashes[1..]), vec![tx(1)]);
+        // All nullifier hashes of the replaced transaction are released
+        assert_eq!(index.get(&hashes[0]), None);
+        assert_eq!(
+            index.get(&hashes[1]).map(|claim| claim.tx_hash),
+            Some(tx(2))
+        );
+    }
+
+    #[test]
+    fn remove_releases_nullifier_hashes() {
+        let index = NullifierIndex::default();
+        index.insert(tx(1), 0, &[U256::from(1), U256::from(2)]);
+        assert_eq!(index.len(), 2);
+
+        // Removing a transaction leaves the claims of other transactions untouched
+        index.insert(tx(2), 0, &[U256::from(3)]);
+        index.remove(&tx(1));
+        assert_eq!(index.len(), 1);
+        assert_eq!(
+            index.get(&U256::from(3)).map(|claim| claim.tx_hash),
+            Some(tx(2))
+        );
+
+        index.remove(&tx(2));
+        assert!(index.is_empty());
+        assert!(index.claims.read().by_tx_hash.is_empty());
+    }
+
+    #[test]
+    fn policy_from_str() {
+        for policy in [
+            DuplicateNullifierPolicy::FirstSeen,
+            DuplicateNullifierPolicy::HigherTip,
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+        assert!("last_seen".parse::<DuplicateNullifierPolicy>().is_err());
+    }
+}
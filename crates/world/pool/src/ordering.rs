@@ -1,14 +1,40 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
 use super::tx::WorldChainPoolTransaction;
+use alloy_primitives::TxHash;
+use parking_lot::Mutex;
 use reth::transaction_pool::{CoinbaseTipOrdering, Priority, TransactionOrdering};
 use revm_primitives::U256;
+use schnellru::{ByLength, LruMap};
+use world_chain_pbh::clock::{Clock, SystemClock};
+
+/// The number of transactions whose arrival time is retained by the [`WeightedPriorityPolicy`].
+const TRACKED_TRANSACTIONS: u32 = 1 << 16;
 
 /// Default ordering for the pool.
 ///
-/// The transactions are ordered by their coinbase tip.
-/// The higher the coinbase tip is, the higher the priority of the transaction.
+/// The priority of a transaction is determined by a [`PriorityPolicy`]. With the
+/// [`DefaultPriorityPolicy`] PBH transactions always take precedence, and transactions
+/// are then ordered by their coinbase tip.
 #[derive(Debug)]
-pub struct WorldChainOrdering<T> {
+pub struct WorldChainOrdering<T, P = DefaultPriorityPolicy> {
     inner: CoinbaseTipOrdering<T>,
+    policy: P,
+}
+
+impl<T, P> WorldChainOrdering<T, P> {
+    /// Creates a new ordering using the given priority policy.
+    pub fn new(policy: P) -> Self {
+        Self {
+            inner: CoinbaseTipOrdering::default(),
+            policy,
+        }
+    }
+
+    /// Returns the priority policy of the ordering.
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
 }
 
 /// Ordering is automatically derived.
@@ -17,14 +43,259 @@ pub struct WorldChainOrdering<T> {
 #[derive(Debug, Default, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct WorldChainPriority {
     is_pbh: bool,
+    weight: u64,
     effective_tip_per_gas: Option<U256>,
 }
 
-impl<T> TransactionOrdering for WorldChainOrdering<T>
+impl WorldChainPriority {
+    /// Creates a new priority with a weight of zero.
+    pub fn new(is_pbh: bool, effective_tip_per_gas: Option<U256>) -> Self {
+        Self {
+            is_pbh,
+            weight: 0,
+            effective_tip_per_gas,
+        }
+    }
+
+    /// Sets the weight of the priority.
+    ///
+    /// The weight is compared after the PBH status and before the effective tip.
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Returns `true` if the transaction is prioritised as a PBH transaction.
+    pub fn is_pbh(&self) -> bool {
+        self.is_pbh
+    }
+
+    /// Returns the weight assigned by the [`PriorityPolicy`].
+    pub fn weight(&self) -> u64 {
+        self.weight
+    }
+
+    /// Returns the effective tip per gas of the transaction at the base fee it was prioritised
+    /// at.
+    pub fn effective_tip_per_gas(&self) -> Option<U256> {
+        self.effective_tip_per_gas
+    }
+}
+
+/// Determines the priority of transactions in the pool.
+///
+/// The pool calls into the policy when a transaction is inserted and whenever the base fee
+/// changes, so stateful policies (e.g. time in pool or per-sender fairness) can keep their own
+/// bookkeeping behind interior mutability.
+pub trait PriorityPolicy<T>: Debug + Send + Sync + Unpin + 'static {
+    /// The priority value, higher values are included first.
+    type Value: Ord + Clone + Default + Debug + Send + Sync;
+
+    /// Returns the priority of the transaction at the given base fee.
+    fn priority(&self, transaction: &T, base_fee: u64) -> Priority<Self::Value>;
+}
+
+/// Orders PBH transactions before all other transactions, and then by effective tip per gas.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultPriorityPolicy;
+
+impl<T> PriorityPolicy<T> for DefaultPriorityPolicy
+where
+    T: WorldChainPoolTransaction + 'static,
+{
+    type Value = WorldChainPriority;
+
+    fn priority(&self, transaction: &T, base_fee: u64) -> Priority<Self::Value> {
+        let effective_tip_per_gas = transaction.effective_tip_per_gas(base_fee).map(U256::from);
+
+        Some(WorldChainPriority::new(
+            transaction.pbh_payload().is_some(),
+            effective_tip_per_gas,
+        ))
+        .into()
+    }
+}
+
+/// The properties of a PBH transaction weighed by the [`WeightedPriorityPolicy`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PbhWeightInputs {
+    /// The number of PBH payloads carried by the transaction.
+    pub ops: usize,
+    /// The highest external nullifier nonce of the PBH payloads of the transaction.
+    pub max_nonce: u16,
+    /// The number of seconds the transaction has spent in the pool.
+    pub seconds_in_pool: u64,
+}
+
+/// A priority policy that weighs PBH transactions by their size, their time in the pool and the
+/// PBH usage of the identities behind them.
+///
+/// PBH transactions which are not demoted by the gas limit per PBH payload always outrank
+/// non-PBH transactions. Among them, transactions are ordered by the sum of the enabled weights
+/// and then by effective tip per gas. With no weights or limits configured this behaves exactly
+/// like the [`DefaultPriorityPolicy`].
+///
+/// The time in the pool is measured from the first time the policy prioritised the
+/// transaction, and is re-evaluated whenever the pool recomputes the priorities of its
+/// transactions on a base fee change.
+#[derive(Debug, Clone)]
+pub struct WeightedPriorityPolicy {
+    /// The maximum gas limit per PBH payload for a transaction to be prioritised as PBH.
+    ///
+    /// Transactions exceeding this compete with non-PBH transactions on tip alone.
+    max_gas_per_pbh_op: Option<u64>,
+    /// Whether PBH transactions carrying more UserOps are preferred over smaller ones.
+    prefer_larger_bundles: bool,
+    /// The time a PBH transaction must spend in the pool to gain one unit of weight.
+    age_step: Option<Duration>,
+    /// The number of external nullifier nonces for which PBH transactions are preferred, the
+    /// lower the nonce the higher the weight.
+    fair_nonces: Option<u16>,
+    /// The clock used to measure the time transactions spend in the pool.
+    clock: Arc<dyn Clock>,
+    /// The unix timestamp at which each transaction was first prioritised.
+    first_seen: Arc<Mutex<LruMap<TxHash, i64>>>,
+}
+
+impl Default for WeightedPriorityPolicy {
+    fn default() -> Self {
+        Self {
+            max_gas_per_pbh_op: None,
+            prefer_larger_bundles: false,
+            age_step: None,
+            fair_nonces: None,
+            clock: Arc::new(SystemClock),
+            first_seen: Arc::new(Mutex::new(LruMap::new(ByLength::new(TRACKED_TRANSACTIONS)))),
+        }
+    }
+}
+
+impl WeightedPriorityPolicy {
+    /// Creates a policy without weights or limits, ordering like the [`DefaultPriorityPolicy`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Demotes PBH transactions whose gas limit exceeds `max_gas_per_pbh_op` per PBH payload to
+    /// non-PBH transactions.
+    pub fn with_max_gas_per_pbh_op(mut self, max_gas_per_pbh_op: u64) -> Self {
+        self.max_gas_per_pbh_op = Some(max_gas_per_pbh_op);
+        self
+    }
+
+    /// Sets whether PBH transactions gain one unit of weight per PBH payload they carry.
+    pub fn with_prefer_larger_bundles(mut self, prefer_larger_bundles: bool) -> Self {
+        self.prefer_larger_bundles = prefer_larger_bundles;
+        self
+    }
+
+    /// Makes PBH transactions gain one unit of weight for every `age_step` they spend in the
+    /// pool, so that they are not starved by a steady stream of higher paying transactions.
+    pub fn with_age_step(mut self, age_step: Duration) -> Self {
+        self.age_step = Some(age_step);
+        self
+    }
+
+    /// Makes PBH transactions whose highest external nullifier nonce is below `fair_nonces`
+    /// gain `fair_nonces - nonce` units of weight.
+    ///
+    /// Nullifier hashes can't be linked to an identity, but the nonce reveals how many PBH
+    /// transactions the identity behind a payload has sent in the current period. Preferring
+    /// low nonces shares the PBH blockspace among identities rather than letting a few heavy
+    /// users exhaust it.
+    pub fn with_fair_nonces(mut self, fair_nonces: u16) -> Self {
+        self.fair_nonces = Some(fair_nonces);
+        self
+    }
+
+    /// Sets the [`Clock`] used to measure the time transactions spend in the pool.
+    ///
+    /// Defaults to [`SystemClock`].
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Returns the number of seconds since the transaction `tx_hash` was first prioritised.
+    pub fn seconds_in_pool(&self, tx_hash: TxHash) -> u64 {
+        let now = self.clock.now().timestamp();
+        let first_seen = self
+            .first_seen
+            .lock()
+            .get_or_insert(tx_hash, || now)
+            .map_or(now, |first_seen| *first_seen);
+
+        now.saturating_sub(first_seen).max(0) as u64
+    }
+
+    /// Computes the priority of a transaction, carrying PBH payloads if `pbh` is set.
+    pub fn weigh(
+        &self,
+        pbh: Option<PbhWeightInputs>,
+        gas_limit: u64,
+        effective_tip_per_gas: Option<U256>,
+    ) -> WorldChainPriority {
+        let Some(pbh) = pbh else {
+            return WorldChainPriority::new(false, effective_tip_per_gas);
+        };
+
+        let within_gas_limit = self.max_gas_per_pbh_op.is_none_or(|max| {
+            let ops = pbh.ops.max(1) as u64;
+            gas_limit.div_ceil(ops) <= max
+        });
+
+        if !within_gas_limit {
+            return WorldChainPriority::new(false, effective_tip_per_gas);
+        }
+
+        let mut weight = 0u64;
+        if self.prefer_larger_bundles {
+            weight = weight.saturating_add(pbh.ops as u64);
+        }
+        if let Some(age_step) = self.age_step {
+            weight = weight.saturating_add(pbh.seconds_in_pool / age_step.as_secs().max(1));
+        }
+        if let Some(fair_nonces) = self.fair_nonces {
+            weight = weight.saturating_add(fair_nonces.saturating_sub(pbh.max_nonce) as u64);
+        }
+
+        WorldChainPriority::new(true, effective_tip_per_gas).with_weight(weight)
+    }
+}
+
+impl<T> PriorityPolicy<T> for WeightedPriorityPolicy
+where
+    T: WorldChainPoolTransaction + 'static,
+{
+    type Value = WorldChainPriority;
+
+    fn priority(&self, transaction: &T, base_fee: u64) -> Priority<Self::Value> {
+        let effective_tip_per_gas = transaction.effective_tip_per_gas(base_fee).map(U256::from);
+
+        let pbh = transaction.pbh_payload().map(|payloads| PbhWeightInputs {
+            ops: payloads.len(),
+            max_nonce: payloads
+                .iter()
+                .map(|payload| payload.external_nullifier.nonce)
+                .max()
+                .unwrap_or_default(),
+            seconds_in_pool: if self.age_step.is_some() {
+                self.seconds_in_pool(*transaction.hash())
+            } else {
+                0
+            },
+        });
+
+        Some(self.weigh(pbh, transaction.gas_limit(), effective_tip_per_gas)).into()
+    }
+}
+
+impl<T, P> TransactionOrdering for WorldChainOrdering<T, P>
 where
     T: WorldChainPoolTransaction + 'static,
+    P: PriorityPolicy<T>,
 {
-    type PriorityValue = WorldChainPriority;
+    type PriorityValue = P::Value;
     type Transaction = T;
 
     fn priority(
@@ -32,47 +303,107 @@ where
         transaction: &Self::Transaction,
         base_fee: u64,
     ) -> Priority<Self::PriorityValue> {
-        let effective_tip_per_gas = transaction.effective_tip_per_gas(base_fee).map(U256::from);
-
-        Some(WorldChainPriority {
-            is_pbh: transaction.pbh_payload().is_some(),
-            effective_tip_per_gas,
-        })
-        .into()
+        self.policy.priority(transaction, base_fee)
     }
 }
 
-impl<T> Clone for WorldChainOrdering<T> {
+impl<T, P: Clone> Clone for WorldChainOrdering<T, P> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            policy: self.policy.clone(),
         }
     }
 }
 
-impl<T> Default for WorldChainOrdering<T> {
+impl<T, P: Default> Default for WorldChainOrdering<T, P> {
     fn default() -> Self {
-        Self {
-            inner: CoinbaseTipOrdering::default(),
-        }
+        Self::new(P::default())
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
     use super::*;
 
+    use alloy_primitives::Address;
+    use alloy_sol_types::SolCall;
+    use chrono::{DateTime, Utc};
+    use proptest::prelude::*;
+    use reth::transaction_pool::{PoolTransaction, TransactionPool};
     use test_case::test_case;
+    use world_chain_pbh::{
+        date_marker::DateMarker, external_nullifier::ExternalNullifier, payload::PBHPayload,
+    };
+    use world_chain_test::utils::{eip1559, eth_tx, pbh_bundle, user_op};
+
+    use crate::{
+        tx::WorldChainPooledTransaction,
+        validator::tests::{setup_with_ordering, world_chain_validator},
+    };
+
+    /// A [`Clock`] which is advanced manually.
+    #[derive(Debug, Clone, Default)]
+    struct ManualClock(Arc<AtomicI64>);
+
+    impl ManualClock {
+        fn advance(&self, seconds: i64) {
+            self.0.fetch_add(seconds, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            DateTime::from_timestamp(self.0.load(Ordering::Relaxed), 0).unwrap()
+        }
+    }
+
+    fn ops(ops: usize) -> PbhWeightInputs {
+        PbhWeightInputs {
+            ops,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a transaction from `acc` paying `tip`, carrying a PBH payload for each nonce of
+    /// `pbh_nonces` if set.
+    async fn pooled_tx(
+        acc: u32,
+        tip: u128,
+        pbh_nonces: Option<&[u16]>,
+    ) -> WorldChainPooledTransaction {
+        let tx = eip1559()
+            .to(Address::ZERO)
+            .max_priority_fee_per_gas(tip)
+            .call();
+        let mut tx = WorldChainPooledTransaction::from(eth_tx(acc, tx).await);
+        if let Some(nonces) = pbh_nonces {
+            tx.set_pbh_payloads(
+                nonces
+                    .iter()
+                    .map(|nonce| PBHPayload {
+                        external_nullifier: ExternalNullifier::v1(1, 2025, *nonce),
+                        ..Default::default()
+                    })
+                    .collect(),
+            );
+        }
+        tx
+    }
 
     #[test]
     fn pbh_has_priority() {
         let pbh = WorldChainPriority {
             is_pbh: true,
+            weight: 0,
             effective_tip_per_gas: Some(U256::from(100u64)),
         };
 
         let no_pbh = WorldChainPriority {
             is_pbh: false,
+            weight: 0,
             effective_tip_per_gas: Some(U256::from(10000u64)),
         };
 
@@ -84,14 +415,241 @@ mod test {
     fn higher_tip_has_priority(is_pbh: bool) {
         let lower_tip = WorldChainPriority {
             is_pbh,
+            weight: 0,
             effective_tip_per_gas: Some(U256::from(100u64)),
         };
 
         let higher_tip = WorldChainPriority {
             is_pbh,
+            weight: 0,
             effective_tip_per_gas: Some(U256::from(10000u64)),
         };
 
         assert!(higher_tip > lower_tip);
     }
+
+    fn tip() -> impl Strategy<Value = Option<U256>> {
+        proptest::option::of(any::<u128>().prop_map(U256::from))
+    }
+
+    fn pbh_inputs() -> impl Strategy<Value = Option<PbhWeightInputs>> {
+        proptest::option::of((1usize..=32, any::<u16>(), any::<u64>()).prop_map(
+            |(ops, max_nonce, seconds_in_pool)| PbhWeightInputs {
+                ops,
+                max_nonce,
+                seconds_in_pool,
+            },
+        ))
+    }
+
+    proptest! {
+        #[test]
+        fn pbh_outranks_non_pbh(pbh_tip in tip(), tip in tip()) {
+            let pbh = WorldChainPriority::new(true, pbh_tip);
+            let no_pbh = WorldChainPriority::new(false, tip);
+
+            prop_assert!(pbh > no_pbh);
+        }
+
+        #[test]
+        fn same_class_ordered_by_tip(is_pbh: bool, a in tip(), b in tip()) {
+            let lhs = WorldChainPriority::new(is_pbh, a);
+            let rhs = WorldChainPriority::new(is_pbh, b);
+
+            prop_assert_eq!(lhs.cmp(&rhs), a.cmp(&b));
+        }
+
+        #[test]
+        fn unweighted_policy_matches_default(
+            pbh in pbh_inputs(),
+            gas_limit: u64,
+            tip in tip(),
+        ) {
+            let priority = WeightedPriorityPolicy::new().weigh(pbh, gas_limit, tip);
+
+            prop_assert_eq!(priority, WorldChainPriority::new(pbh.is_some(), tip));
+        }
+
+        #[test]
+        fn oversized_pbh_never_outranks_compliant_pbh(
+            max_gas_per_op in 1u64..=1_000_000,
+            ops in 1usize..=32,
+            excess in 1u64..=1_000_000,
+            oversized_tip in tip(),
+            compliant_tip in tip(),
+        ) {
+            let policy = WeightedPriorityPolicy::new().with_max_gas_per_pbh_op(max_gas_per_op);
+
+            let oversized = policy.weigh(
+                Some(self::ops(ops)),
+                max_gas_per_op * ops as u64 + excess,
+                oversized_tip,
+            );
+            let compliant =
+                policy.weigh(Some(self::ops(ops)), max_gas_per_op * ops as u64, compliant_tip);
+
+            prop_assert!(!oversized.is_pbh());
+            prop_assert!(compliant.is_pbh());
+            prop_assert!(compliant > oversized);
+        }
+
+        #[test]
+        fn larger_bundles_preferred_at_equal_tip(
+            smaller in 1usize..=32,
+            extra in 1usize..=32,
+            gas_limit: u64,
+            tip in tip(),
+        ) {
+            let policy = WeightedPriorityPolicy::new().with_prefer_larger_bundles(true);
+
+            let small = policy.weigh(Some(ops(smaller)), gas_limit, tip);
+            let large = policy.weigh(Some(ops(smaller + extra)), gas_limit, tip);
+
+            prop_assert!(large > small);
+        }
+
+        #[test]
+        fn older_pbh_preferred_at_equal_tip(
+            age_step in 1u64..=600,
+            seconds_in_pool in 0u64..=86_400,
+            steps in 1u64..=100,
+            gas_limit: u64,
+            tip in tip(),
+        ) {
+            let policy =
+                WeightedPriorityPolicy::new().with_age_step(Duration::from_secs(age_step));
+
+            let newer = PbhWeightInputs {
+                seconds_in_pool,
+                ..ops(1)
+            };
+            let older = PbhWeightInputs {
+                seconds_in_pool: seconds_in_pool + steps * age_step,
+                ..ops(1)
+            };
+
+            prop_assert!(
+                policy.weigh(Some(older), gas_limit, tip) > policy.weigh(Some(newer), gas_limit, tip)
+            );
+        }
+
+        #[test]
+        fn lower_nonce_preferred_at_equal_tip(
+            fair_nonces in 1u16..=u16::MAX,
+            nonce: u16,
+            higher: u16,
+            gas_limit: u64,
+            tip in tip(),
+        ) {
+            let policy = WeightedPriorityPolicy::new().with_fair_nonces(fair_nonces);
+            let nonce = nonce % fair_nonces;
+            let higher = nonce.saturating_add(1).max(higher);
+
+            let weigh = |max_nonce| {
+                policy.weigh(Some(PbhWeightInputs { max_nonce, ..ops(1) }), gas_limit, tip)
+            };
+            let (light, heavy) = (weigh(nonce), weigh(higher));
+
+            prop_assert!(light > heavy);
+        }
+
+        #[test]
+        fn priority_is_a_total_order(
+            a in (any::<bool>(), any::<u64>(), tip()),
+            b in (any::<bool>(), any::<u64>(), tip()),
+            c in (any::<bool>(), any::<u64>(), tip()),
+        ) {
+            let [a, b, c] = [a, b, c]
+                .map(|(is_pbh, weight, tip)| WorldChainPriority::new(is_pbh, tip).with_weight(weight));
+
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            if a <= b && b <= c {
+                prop_assert!(a <= c);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn policy_weighs_pooled_transactions() {
+        let clock = ManualClock::default();
+        let policy = WeightedPriorityPolicy::new()
+            .with_fair_nonces(8)
+            .with_age_step(Duration::from_secs(60))
+            .with_clock(clock.clone());
+        let ordering = WorldChainOrdering::<WorldChainPooledTransaction, _>::new(policy);
+
+        let plain = pooled_tx(0, 1_000_000, None).await;
+        let heavy = pooled_tx(1, 1_000, Some(&[0, 4])).await;
+        let light = pooled_tx(2, 1, Some(&[1])).await;
+        let priority = |tx: &WorldChainPooledTransaction| {
+            let Priority::Value(priority) = ordering.priority(tx, 0) else {
+                panic!("transaction has no priority");
+            };
+            priority
+        };
+
+        // The highest nonce of a bundle determines its weight
+        assert_eq!(priority(&heavy).weight(), 4);
+        assert_eq!(priority(&light).weight(), 7);
+        assert!(priority(&light) > priority(&heavy));
+        assert!(priority(&heavy) > priority(&plain));
+
+        // Time in the pool is measured from the first prioritisation
+        clock.advance(4 * 60);
+        let late = pooled_tx(3, 1, Some(&[1])).await;
+        assert_eq!(priority(&late).weight(), 7);
+        assert_eq!(priority(&heavy).weight(), 8);
+        assert_eq!(priority(&light).weight(), 11);
+        assert!(priority(&light) > priority(&heavy));
+        assert!(priority(&heavy) > priority(&late));
+    }
+
+    #[tokio::test]
+    async fn pool_orders_by_policy() {
+        let (light_op, light_proof) = user_op()
+            .acc(0)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+        let (heavy_op, heavy_proof) = user_op()
+            .acc(1)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                5,
+            ))
+            .call();
+        let bundle = |user_op, proof: PBHPayload, tip| {
+            eip1559()
+                .to(world_chain_test::PBH_DEV_ENTRYPOINT)
+                .max_priority_fee_per_gas(tip)
+                .input(pbh_bundle(vec![user_op], vec![proof.into()]).abi_encode())
+                .call()
+        };
+        let light = eth_tx(8, bundle(light_op, light_proof, 1)).await;
+        let heavy = eth_tx(9, bundle(heavy_op, heavy_proof, 1_000)).await;
+
+        for (policy, expected) in [
+            (
+                WeightedPriorityPolicy::new(),
+                [*heavy.hash(), *light.hash()],
+            ),
+            (
+                WeightedPriorityPolicy::new().with_fair_nonces(30),
+                [*light.hash(), *heavy.hash()],
+            ),
+        ] {
+            let pool =
+                setup_with_ordering(world_chain_validator(), WorldChainOrdering::new(policy)).await;
+            for tx in [&light, &heavy] {
+                pool.add_external_transaction(tx.clone().into())
+                    .await
+                    .expect("Failed to add transaction");
+            }
+
+            let best: Vec<_> = pool.best_transactions().map(|tx| *tx.hash()).collect();
+            assert_eq!(best, expected);
+        }
+    }
 }
@@ -0,0 +1,153 @@
+//! A cache of successfully verified PBH proofs.
+use metrics::Counter;
+use metrics_derive::Metrics;
+use parking_lot::Mutex;
+use schnellru::{ByLength, LruMap};
+use semaphore_rs::Field;
+use world_chain_pbh::{external_nullifier::EncodedExternalNullifier, payload::PBHPayload};
+
+/// The default number of verified proofs retained by the [`ProofCache`].
+pub const DEFAULT_PROOF_CACHE_SIZE: u32 = 10_000;
+
+/// Identifies a verified semaphore proof.
+///
+/// A proof is only valid for the exact public inputs it was verified against, so besides the
+/// `(nullifier_hash, root, signal)` triple the key also commits to the external nullifier and
+/// the proof itself. Otherwise a payload could reuse the verification of another payload
+/// sharing the same triple.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProofCacheKey {
+    nullifier_hash: Field,
+    root: Field,
+    signal: Field,
+    external_nullifier: Field,
+    proof: [Field; 8],
+}
+
+impl ProofCacheKey {
+    /// Creates the cache key of a PBH payload verified against `signal`.
+    pub fn new(payload: &PBHPayload, signal: Field) -> Self {
+        Self {
+            nullifier_hash: payload.nullifier_hash,
+            root: payload.root,
+            signal,
+            external_nullifier: EncodedExternalNullifier::from(payload.external_nullifier).0,
+            proof: payload.proof.flatten(),
+        }
+    }
+}
+
+/// A bounded LRU cache of PBH proofs which passed verification.
+///
+/// Proof verification is by far the most expensive part of validating a PBH transaction, and
+/// the same bundle is validated again on every re-submission, gossip arrival and pool
+/// revalidation. The cache only covers the proof itself; the root and the external nullifier
+/// depend on chain state and time, and must be checked on every validation.
+#[derive(Debug)]
+pub struct ProofCache {
+    inner: Mutex<LruMap<ProofCacheKey, ()>>,
+    metrics: ProofCacheMetrics,
+}
+
+impl ProofCache {
+    /// Creates a new [`ProofCache`] retaining up to `capacity` verified proofs.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            inner: Mutex::new(LruMap::new(ByLength::new(capacity))),
+            metrics: ProofCacheMetrics::default(),
+        }
+    }
+
+    /// Returns `true` if the proof identified by `key` has previously been verified.
+    pub fn contains(&self, key: &ProofCacheKey) -> bool {
+        let hit = self.inner.lock().get(key).is_some();
+        if hit {
+            self.metrics.hits.increment(1);
+        } else {
+            self.metrics.misses.increment(1);
+        }
+        hit
+    }
+
+    /// Records the proof identified by `key` as verified.
+    pub fn insert(&self, key: ProofCacheKey) {
+        self.inner.lock().insert(key, ());
+    }
+
+    /// Returns the number of verified proofs in the cache.
+    pub fn len(&self) -> usize {
+        self.inner.lock().len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ProofCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROOF_CACHE_SIZE)
+    }
+}
+
+/// Metrics for the [`ProofCache`].
+#[derive(Metrics)]
+#[metrics(scope = "pbh_proof_cache")]
+struct ProofCacheMetrics {
+    /// Total number of proofs which were found in the cache.
+    hits: Counter,
+    /// Total number of proofs which were not found in the cache.
+    misses: Counter,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+
+    use super::*;
+
+    fn key(nullifier_hash: u64) -> ProofCacheKey {
+        ProofCacheKey::new(
+            &PBHPayload {
+                nullifier_hash: U256::from(nullifier_hash),
+                ..Default::default()
+            },
+            U256::ZERO,
+        )
+    }
+
+    #[test]
+    fn contains_inserted() {
+        let cache = ProofCache::default();
+        assert!(!cache.contains(&key(1)));
+        cache.insert(key(1));
+        assert!(cache.contains(&key(1)));
+        assert!(!cache.contains(&key(2)));
+    }
+
+    #[test]
+    fn key_commits_to_signal() {
+        let payload = PBHPayload::default();
+        assert_ne!(
+            ProofCacheKey::new(&payload, U256::ZERO),
+            ProofCacheKey::new(&payload, U256::from(1))
+        );
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ProofCache::new(2);
+        cache.insert(key(1));
+        cache.insert(key(2));
+
+        // Touch the first entry so that the second is evicted
+        assert!(cache.contains(&key(1)));
+        cache.insert(key(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&key(1)));
+        assert!(!cache.contains(&key(2)));
+        assert!(cache.contains(&key(3)));
+    }
+}
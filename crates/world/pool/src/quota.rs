@@ -0,0 +1,459 @@
+//! Admission quotas for the PBH transactions held by the pool.
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use alloy_primitives::{Address, TxHash};
+use chrono::NaiveDate;
+use futures_util::StreamExt;
+use parking_lot::RwLock;
+use reth::transaction_pool::{FullTransactionEvent, PoolTransaction, TransactionPool};
+use thiserror::Error;
+use tracing::debug;
+use world_chain_pbh::{external_nullifier::Period, payload::PBHPayload};
+
+use crate::tx::WorldChainPoolTransaction;
+
+/// Identifies the rate limiting period of an external nullifier by its kind and first day.
+pub type PeriodKey = (Period, NaiveDate);
+
+/// Returns the periods of the external nullifiers of `payloads`, one per payload.
+pub fn payload_periods<'a>(payloads: impl IntoIterator<Item = &'a PBHPayload>) -> Vec<PeriodKey> {
+    payloads
+        .into_iter()
+        .filter_map(|payload| {
+            let external_nullifier = payload.external_nullifier;
+            Some((
+                external_nullifier.period,
+                external_nullifier.period_start()?,
+            ))
+        })
+        .collect()
+}
+
+/// Limits on the PBH transactions held by the pool.
+///
+/// A limit of `None` disables the quota.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PbhQuotas {
+    /// The maximum number of pooled PBH transactions sent by a single sender.
+    pub max_txs_per_sender: Option<usize>,
+    /// The maximum number of pooled PBH nullifier hashes with an external nullifier for the
+    /// same period.
+    pub max_nullifiers_per_period: Option<usize>,
+    /// The maximum number of pooled PBH transactions. Once reached, the PBH transactions paying
+    /// the lowest priority fee are evicted, independent of the limits of the pool itself.
+    pub max_pbh_txs: Option<usize>,
+}
+
+/// A PBH transaction was rejected because it exceeds a [`PbhQuotas`] limit.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PbhQuotaError {
+    #[error("Sender {sender} exceeds the quota of {limit} pooled PBH transactions")]
+    SenderQuotaExceeded { sender: Address, limit: usize },
+    #[error(
+        "Quota of {limit} pooled nullifier hashes exceeded for the {period} period of {start}"
+    )]
+    PeriodQuotaExceeded {
+        period: Period,
+        start: NaiveDate,
+        limit: usize,
+    },
+    #[error("PBH sub-pool is full with {limit} transactions paying a higher priority fee")]
+    PbhPoolFull { limit: usize },
+}
+
+impl PbhQuotaError {
+    /// Returns the stable numeric code of the error.
+    ///
+    /// Codes are exposed to RPC clients and must never be changed or reused.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::SenderQuotaExceeded { .. } => 1101,
+            Self::PeriodQuotaExceeded { .. } => 1102,
+            Self::PbhPoolFull { .. } => 1103,
+        }
+    }
+}
+
+/// A pooled PBH transaction accounted against the quotas.
+#[derive(Debug, Clone)]
+struct QuotaEntry {
+    sender: Address,
+    tip: u128,
+    periods: Vec<PeriodKey>,
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    txs: HashMap<TxHash, QuotaEntry>,
+    /// The accounted transactions ordered by priority fee, lowest first.
+    by_tip: BTreeSet<(u128, TxHash)>,
+    senders: HashMap<Address, usize>,
+    periods: HashMap<PeriodKey, usize>,
+}
+
+impl QuotaState {
+    fn insert(&mut self, tx_hash: TxHash, entry: QuotaEntry) {
+        *self.senders.entry(entry.sender).or_default() += 1;
+        for period in &entry.periods {
+            *self.periods.entry(*period).or_default() += 1;
+        }
+        self.by_tip.insert((entry.tip, tx_hash));
+        self.txs.insert(tx_hash, entry);
+    }
+
+    fn remove(&mut self, tx_hash: &TxHash) {
+        let Some(entry) = self.txs.remove(tx_hash) else {
+            return;
+        };
+        self.by_tip.remove(&(entry.tip, *tx_hash));
+        decrement(&mut self.senders, &entry.sender);
+        for period in &entry.periods {
+            decrement(&mut self.periods, period);
+        }
+    }
+
+    /// Returns the hash and priority fee of the pooled transaction paying the lowest priority
+    /// fee, excluding `tx_hash`.
+    fn lowest(&self, tx_hash: &TxHash) -> Option<(TxHash, u128)> {
+        self.by_tip
+            .iter()
+            .find(|(_, hash)| hash != tx_hash)
+            .map(|(tip, hash)| (*hash, *tip))
+    }
+
+    /// Checks the per sender and per period quotas for a transaction sent by `sender` and
+    /// carrying nullifier hashes for `periods`, not counting `tx_hash` itself if it is already
+    /// accounted for.
+    fn check_limits(
+        &self,
+        quotas: &PbhQuotas,
+        tx_hash: &TxHash,
+        sender: Address,
+        periods: &[PeriodKey],
+    ) -> Result<(), PbhQuotaError> {
+        let tracked = self.txs.get(tx_hash);
+
+        if let Some(limit) = quotas.max_txs_per_sender {
+            let pooled = self.senders.get(&sender).copied().unwrap_or_default();
+            let pooled = pooled - usize::from(tracked.is_some_and(|entry| entry.sender == sender));
+            if pooled >= limit {
+                return Err(PbhQuotaError::SenderQuotaExceeded { sender, limit });
+            }
+        }
+
+        if let Some(limit) = quotas.max_nullifiers_per_period {
+            let mut counts = HashMap::<PeriodKey, usize>::new();
+            for period in periods {
+                *counts.entry(*period).or_default() += 1;
+            }
+            for (period, count) in counts {
+                let pooled = self.periods.get(&period).copied().unwrap_or_default();
+                let own = tracked.map_or(0, |entry| {
+                    entry.periods.iter().filter(|p| **p == period).count()
+                });
+                if pooled - own + count > limit {
+                    return Err(PbhQuotaError::PeriodQuotaExceeded {
+                        period: period.0,
+                        start: period.1,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn decrement<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, usize>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+/// Accounts the PBH transactions held by the pool against the [`PbhQuotas`].
+///
+/// The tracker is consulted by the validator to reject PBH transactions exceeding a quota at
+/// admission, and kept in sync with the pool by [`maintain_pbh_quotas`].
+#[derive(Debug, Default)]
+pub struct PbhQuotaTracker {
+    quotas: PbhQuotas,
+    state: RwLock<QuotaState>,
+}
+
+impl PbhQuotaTracker {
+    /// Creates an empty tracker enforcing `quotas`.
+    pub fn new(quotas: PbhQuotas) -> Self {
+        Self {
+            quotas,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns the enforced quotas.
+    pub fn quotas(&self) -> PbhQuotas {
+        self.quotas
+    }
+
+    /// Checks whether a PBH transaction sent by `sender`, paying `tip` and carrying nullifier
+    /// hashes for `periods`, can be admitted to the pool.
+    ///
+    /// A transaction which is already accounted for, e.g. when it is revalidated, is not
+    /// counted twice. Transactions validated concurrently may all pass the check, the quotas
+    /// are enforced again when they enter the pool through [`Self::insert`].
+    pub fn check(
+        &self,
+        tx_hash: &TxHash,
+        sender: Address,
+        tip: u128,
+        periods: &[PeriodKey],
+    ) -> Result<(), PbhQuotaError> {
+        let state = self.state.read();
+        state.check_limits(&self.quotas, tx_hash, sender, periods)?;
+
+        if let Some(limit) = self.quotas.max_pbh_txs {
+            if !state.txs.contains_key(tx_hash)
+                && state.txs.len() >= limit
+                && state
+                    .lowest(tx_hash)
+                    .is_none_or(|(_, lowest_tip)| tip <= lowest_tip)
+            {
+                return Err(PbhQuotaError::PbhPoolFull { limit });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accounts a PBH transaction which entered the pool.
+    ///
+    /// Returns `[tx_hash]` if the transaction exceeds the per sender or per period quotas,
+    /// which happens when it was validated concurrently with other transactions counting
+    /// against the same quota. Otherwise returns the hashes of the pooled PBH transactions
+    /// paying the lowest priority fee which exceed the size limit of the PBH sub-pool. Returned
+    /// transactions should be removed from the pool, and from the tracker through
+    /// [`Self::remove`].
+    pub fn insert(
+        &self,
+        tx_hash: TxHash,
+        sender: Address,
+        tip: u128,
+        periods: Vec<PeriodKey>,
+    ) -> Vec<TxHash> {
+        let mut state = self.state.write();
+        if state
+            .check_limits(&self.quotas, &tx_hash, sender, &periods)
+            .is_err()
+        {
+            return vec![tx_hash];
+        }
+
+        state.remove(&tx_hash);
+        state.insert(
+            tx_hash,
+            QuotaEntry {
+                sender,
+                tip,
+                periods,
+            },
+        );
+
+        let Some(limit) = self.quotas.max_pbh_txs else {
+            return vec![];
+        };
+        let excess = state.txs.len().saturating_sub(limit);
+        state
+            .by_tip
+            .iter()
+            .take(excess)
+            .map(|(_, hash)| *hash)
+            .collect()
+    }
+
+    /// Stops accounting the transaction `tx_hash`.
+    pub fn remove(&self, tx_hash: &TxHash) {
+        self.state.write().remove(tx_hash);
+    }
+
+    /// Returns the number of accounted PBH transactions.
+    pub fn len(&self) -> usize {
+        self.state.read().txs.len()
+    }
+
+    /// Returns `true` if no PBH transactions are accounted.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Long running task that keeps the [`PbhQuotaTracker`] in sync with the pool.
+///
+/// PBH transactions are accounted when they enter the pool, and released when they are mined,
+/// replaced, discarded or invalidated. PBH transactions exceeding a per sender or per period
+/// quota, e.g. because they were validated concurrently, and PBH transactions exceeding the
+/// size limit of the PBH sub-pool are removed from the pool, the latter lowest priority fee
+/// first.
+pub async fn maintain_pbh_quotas<Pool>(pool: Pool, tracker: Arc<PbhQuotaTracker>)
+where
+    Pool: TransactionPool<Transaction: WorldChainPoolTransaction>,
+{
+    let mut events = pool.all_transactions_event_listener();
+    while let Some(event) = events.next().await {
+        match event {
+            FullTransactionEvent::Pending(tx_hash) | FullTransactionEvent::Queued(tx_hash, ..) => {
+                let Some(tx) = pool.get(&tx_hash) else {
+                    continue;
+                };
+                let Some(payloads) = tx.transaction.pbh_payload() else {
+                    continue;
+                };
+
+                let evicted = tracker.insert(
+                    tx_hash,
+                    tx.sender(),
+                    tx.transaction.priority_fee_or_price(),
+                    payload_periods(payloads),
+                );
+                if !evicted.is_empty() {
+                    debug!(
+                        target: "world_chain::pool",
+                        ?evicted,
+                        "Evicting PBH transactions exceeding the PBH quotas"
+                    );
+                    for tx_hash in &evicted {
+                        tracker.remove(tx_hash);
+                    }
+                    pool.remove_transactions(evicted);
+                }
+            }
+            FullTransactionEvent::Mined { tx_hash, .. }
+            | FullTransactionEvent::Discarded(tx_hash)
+            | FullTransactionEvent::Invalid(tx_hash) => tracker.remove(&tx_hash),
+            FullTransactionEvent::Replaced { transaction, .. } => {
+                tracker.remove(transaction.hash())
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(n: u8) -> TxHash {
+        TxHash::with_last_byte(n)
+    }
+
+    fn period(month: u32) -> PeriodKey {
+        (
+            Period::Monthly,
+            NaiveDate::from_ymd_opt(2025, month, 1).unwrap(),
+        )
+    }
+
+    #[test]
+    fn sender_quota() {
+        let tracker = PbhQuotaTracker::new(PbhQuotas {
+            max_txs_per_sender: Some(2),
+            ..Default::default()
+        });
+        let sender = Address::with_last_byte(1);
+
+        tracker.insert(tx(1), sender, 0, vec![]);
+        assert_eq!(tracker.check(&tx(2), sender, 0, &[]), Ok(()));
+        tracker.insert(tx(2), sender, 0, vec![]);
+
+        assert_eq!(
+            tracker.check(&tx(3), sender, 0, &[]),
+            Err(PbhQuotaError::SenderQuotaExceeded { sender, limit: 2 })
+        );
+        // Other senders and revalidated transactions are unaffected
+        assert_eq!(tracker.check(&tx(3), Address::ZERO, 0, &[]), Ok(()));
+        assert_eq!(tracker.check(&tx(2), sender, 0, &[]), Ok(()));
+
+        tracker.remove(&tx(1));
+        assert_eq!(tracker.check(&tx(3), sender, 0, &[]), Ok(()));
+    }
+
+    #[test]
+    fn period_quota() {
+        let tracker = PbhQuotaTracker::new(PbhQuotas {
+            max_nullifiers_per_period: Some(3),
+            ..Default::default()
+        });
+
+        tracker.insert(tx(1), Address::ZERO, 0, vec![period(1), period(1)]);
+        assert_eq!(
+            tracker.check(&tx(2), Address::ZERO, 0, &[period(1), period(2)]),
+            Ok(())
+        );
+        assert_eq!(
+            tracker.check(&tx(2), Address::ZERO, 0, &[period(1), period(1)]),
+            Err(PbhQuotaError::PeriodQuotaExceeded {
+                period: Period::Monthly,
+                start: period(1).1,
+                limit: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn insert_enforces_quotas() {
+        let tracker = PbhQuotaTracker::new(PbhQuotas {
+            max_txs_per_sender: Some(1),
+            max_nullifiers_per_period: Some(2),
+            ..Default::default()
+        });
+        let sender = Address::with_last_byte(1);
+
+        // Both transactions pass the check when validated concurrently
+        assert_eq!(tracker.check(&tx(1), sender, 0, &[]), Ok(()));
+        assert_eq!(tracker.check(&tx(2), sender, 0, &[]), Ok(()));
+        assert!(tracker.insert(tx(1), sender, 0, vec![]).is_empty());
+        assert_eq!(tracker.insert(tx(2), sender, 0, vec![]), vec![tx(2)]);
+
+        // Re-accounting a transaction does not count it twice
+        assert!(tracker.insert(tx(1), sender, 0, vec![]).is_empty());
+        assert_eq!(tracker.len(), 1);
+
+        assert!(tracker
+            .insert(tx(3), Address::ZERO, 0, vec![period(1), period(1)])
+            .is_empty());
+        assert_eq!(
+            tracker.insert(tx(4), Address::with_last_byte(2), 0, vec![period(1)]),
+            vec![tx(4)]
+        );
+        assert_eq!(tracker.len(), 2);
+    }
+
+    #[test]
+    fn pbh_pool_limit_evicts_lowest_tip() {
+        let tracker = PbhQuotaTracker::new(PbhQuotas {
+            max_pbh_txs: Some(2),
+            ..Default::default()
+        });
+
+        assert!(tracker.insert(tx(1), Address::ZERO, 10, vec![]).is_empty());
+        assert!(tracker.insert(tx(2), Address::ZERO, 20, vec![]).is_empty());
+
+        assert_eq!(
+            tracker.check(&tx(3), Address::ZERO, 10, &[]),
+            Err(PbhQuotaError::PbhPoolFull { limit: 2 })
+        );
+        assert_eq!(tracker.check(&tx(3), Address::ZERO, 11, &[]), Ok(()));
+        assert_eq!(
+            tracker.insert(tx(3), Address::ZERO, 11, vec![]),
+            vec![tx(1)]
+        );
+
+        tracker.remove(&tx(1));
+        assert_eq!(tracker.len(), 2);
+    }
+}
@@ -1,13 +1,22 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
 
 use alloy_consensus::{BlockHeader, Sealable};
-use alloy_primitives::{Address, U256};
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{Address, BlockNumber, U256};
+use futures_util::{Stream, StreamExt};
 use parking_lot::RwLock;
 use reth::api::Block;
-use reth_primitives::SealedBlock;
-use reth_provider::{BlockReaderIdExt, StateProviderFactory};
+use reth_primitives::{NodePrimitives, SealedBlock};
+use reth_provider::{
+    BlockReaderIdExt, CanonStateNotification, HeaderProvider, ProviderError, ProviderResult,
+    StateProvider, StateProviderFactory,
+};
 
 use semaphore_rs::Field;
+use world_chain_pbh::payload::RootSet;
 
 use super::error::WorldChainTransactionPoolError;
 
@@ -15,10 +24,100 @@ use super::error::WorldChainTransactionPoolError;
 ///
 /// [WorldID contract](https://github.com/worldcoin/world-id-state-bridge/blob/729d2346a3bb6bac003284bdcefc0cf12ece3f7d/src/abstract/WorldIDBridge.sol#L30)
 pub const LATEST_ROOT_SLOT: U256 = U256::from_limbs([1, 0, 0, 0]);
-/// Root Expiration Period
+/// The slot of the `ROOT_HISTORY_EXPIRY` in the
+///
+/// [WorldID contract](https://github.com/worldcoin/world-id-state-bridge/blob/729d2346a3bb6bac003284bdcefc0cf12ece3f7d/src/abstract/WorldIDBridge.sol#L36)
+pub const ROOT_HISTORY_EXPIRY_SLOT: U256 = U256::from_limbs([3, 0, 0, 0]);
+/// Root Expiration Period, used if the WorldID contract does not define a root history expiry.
 pub const ROOT_EXPIRATION_WINDOW: u64 = 60 * 60 * 24 * 7; // 1 Week
 
+/// Configuration of the [`RootProvider`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootConfig {
+    /// The slot of the latest root in the WorldID contract.
+    pub latest_root_slot: U256,
+    /// The slot of the root history expiry in the WorldID contract.
+    pub root_history_expiry_slot: U256,
+    /// Overrides the root history expiry of the WorldID contract, in seconds.
+    pub root_expiration_override: Option<u64>,
+}
+
+impl Default for RootConfig {
+    fn default() -> Self {
+        Self {
+            latest_root_slot: LATEST_ROOT_SLOT,
+            root_history_expiry_slot: ROOT_HISTORY_EXPIRY_SLOT,
+            root_expiration_override: None,
+        }
+    }
+}
+
+/// An immutable snapshot of the valid roots.
+///
+/// Snapshots are published by the [`RootProvider`] whenever the set of valid roots changes and
+/// shared through an [`Arc`], so that validating a root neither allocates nor contends with the
+/// provider reading state for a new block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidRoots {
+    /// The valid roots, for constant time membership checks.
+    set: HashSet<Field>,
+    /// The valid roots, ordered by the block in which they became the latest root.
+    ordered: Vec<Field>,
+}
+
+impl ValidRoots {
+    /// Returns `true` if `root` is valid.
+    pub fn contains(&self, root: &Field) -> bool {
+        self.set.contains(root)
+    }
+
+    /// Returns the valid roots, ordered by the block in which they became the latest root.
+    pub fn as_slice(&self) -> &[Field] {
+        &self.ordered
+    }
+
+    /// Returns the number of valid roots.
+    pub fn len(&self) -> usize {
+        self.ordered.len()
+    }
+
+    /// Returns `true` if there are no valid roots.
+    pub fn is_empty(&self) -> bool {
+        self.ordered.is_empty()
+    }
+}
+
+impl FromIterator<Field> for ValidRoots {
+    fn from_iter<I: IntoIterator<Item = Field>>(iter: I) -> Self {
+        let ordered: Vec<_> = iter.into_iter().collect();
+        Self {
+            set: ordered.iter().copied().collect(),
+            ordered,
+        }
+    }
+}
+
+impl RootSet for ValidRoots {
+    fn contains_root(&self, root: &Field) -> bool {
+        self.contains(root)
+    }
+}
+
+/// A root observed on the canonical chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RootEntry {
+    /// The root.
+    root: Field,
+    /// The timestamp of the most recent block in which this was the latest root.
+    last_seen: u64,
+}
+
 /// A provider for managing and validating World Chain roots.
+///
+/// Roots are tracked per canonical block: an entry is recorded for every block in which the
+/// latest root of the WorldID contract changed. When the chain reorgs, the entries recorded for
+/// blocks which are no longer canonical are dropped, so that roots which only existed on an
+/// orphaned branch are no longer accepted.
 #[derive(Debug, Clone)]
 pub struct RootProvider<Client>
 where
@@ -28,15 +127,22 @@ where
     world_id: Address,
     /// The client used to aquire account state from the database.
     client: Client,
-    /// A map of valid roots indexed by block timestamp.
-    valid_roots: BTreeMap<u64, Field>,
-    /// The timestamp of the latest valid root.
+    /// The roots observed on the canonical chain, indexed by the number of the block in which
+    /// they became the latest root.
+    valid_roots: BTreeMap<BlockNumber, RootEntry>,
+    /// The latest snapshot of the roots in `valid_roots`.
+    snapshot: Arc<ValidRoots>,
+    /// The most recent block committed to the provider.
+    tip: Option<BlockNumHash>,
+    /// The timestamp of the latest block.
     latest_valid_timestamp: u64,
-    /// The latest root
-    latest_root: Field,
+    /// The configuration of the provider.
+    config: RootConfig,
+    /// The duration in seconds for which a root remains valid after it was superseded, as
+    /// defined by the WorldID contract at the latest block.
+    expiration_window: u64,
 }
 
-/// TODO: Think through reorg scenarios
 impl<Client> RootProvider<Client>
 where
     Client: StateProviderFactory + BlockReaderIdExt,
@@ -46,35 +152,140 @@ where
     /// # Arguments
     ///
     /// * `client` - The client used to aquire account state from the database.
-    pub fn new(client: Client, world_id: Address) -> Result<Self, WorldChainTransactionPoolError> {
+    /// * `config` - The configuration of the provider.
+    pub fn new(
+        client: Client,
+        world_id: Address,
+        config: RootConfig,
+    ) -> Result<Self, WorldChainTransactionPoolError> {
         let mut this = Self {
             client,
             world_id,
             valid_roots: BTreeMap::new(),
+            snapshot: Default::default(),
+            tip: None,
             latest_valid_timestamp: 0,
-            latest_root: Field::ZERO,
+            config,
+            expiration_window: config
+                .root_expiration_override
+                .unwrap_or(ROOT_EXPIRATION_WINDOW),
         };
 
         // If we have a state provider, we can try to load the latest root from the state.
         if let Ok(latest) = this.client.last_block_number() {
             let block = this.client.block(latest.into())?;
             if let Some(block) = block {
-                if let Ok(state) = this.client.state_by_block_hash(block.header().hash_slow()) {
+                let hash = block.header().hash_slow();
+                if let Ok(state) = this.client.state_by_block_hash(hash) {
+                    if let Ok(expiration_window) = this.read_expiration_window(&state) {
+                        this.expiration_window = expiration_window;
+                    }
                     if let Ok(Some(latest_root)) =
-                        state.storage(this.world_id, LATEST_ROOT_SLOT.into())
+                        state.storage(this.world_id, this.config.latest_root_slot.into())
                     {
-                        this.latest_root = latest_root;
-                        this.valid_roots
-                            .insert(block.header().timestamp(), latest_root);
+                        let header = block.header();
+                        this.latest_valid_timestamp = header.timestamp();
+                        if let Err(e) =
+                            this.backfill(header.number(), header.timestamp(), latest_root)
+                        {
+                            tracing::warn!(
+                                "Failed to backfill roots, only the latest root is valid: {e}"
+                            );
+                        }
+                        this.insert(header.number(), header.timestamp(), latest_root);
+                        this.tip = Some(BlockNumHash::new(header.number(), hash));
                     }
                 }
             }
         }
+        this.update_snapshot();
         Ok(this)
     }
 
+    /// Loads the roots of the blocks within the expiration window preceding the tip
+    /// from historical state, so that a restarted node accepts the same roots as a long
+    /// running one.
+    ///
+    /// See [`root_changes`] for how the blocks updating the root are located.
+    fn backfill(
+        &mut self,
+        tip_number: BlockNumber,
+        tip_timestamp: u64,
+        tip_root: Field,
+    ) -> Result<(), WorldChainTransactionPoolError> {
+        let cutoff = tip_timestamp.saturating_sub(self.expiration_window);
+        let start = first_block_at_or_after(0, tip_number, cutoff, |number| {
+            Ok(self.header(number)?.timestamp())
+        })?;
+
+        let start_root = self.root_at(start)?;
+        let mut changes = vec![(start, start_root)];
+        changes.extend(root_changes(
+            (start, start_root),
+            (tip_number, Some(tip_root)),
+            |number| self.root_at(number),
+        )?);
+
+        let mut entries = BTreeMap::new();
+        for (i, &(number, root)) in changes.iter().enumerate() {
+            let Some(root) = root else {
+                continue;
+            };
+            // The root was the latest root up to the block preceding the next update
+            let last_seen = match changes.get(i + 1) {
+                Some((next, _)) => self.header(next - 1)?.timestamp(),
+                None => tip_timestamp,
+            };
+            entries.insert(number, RootEntry { root, last_seen });
+        }
+
+        self.valid_roots = entries;
+        Ok(())
+    }
+
+    /// Returns the header of the canonical block `number`.
+    fn header(
+        &self,
+        number: BlockNumber,
+    ) -> Result<<Client as HeaderProvider>::Header, WorldChainTransactionPoolError> {
+        Ok(self
+            .client
+            .header_by_number(number)?
+            .ok_or(ProviderError::HeaderNotFound(number.into()))?)
+    }
+
+    /// Returns the latest root of the WorldID contract after the canonical block `number`.
+    fn root_at(
+        &self,
+        number: BlockNumber,
+    ) -> Result<Option<Field>, WorldChainTransactionPoolError> {
+        Ok(self
+            .client
+            .history_by_block_number(number)?
+            .storage(self.world_id, self.config.latest_root_slot.into())?)
+    }
+
+    /// Reads the root expiration window from the WorldID contract, unless overridden.
+    ///
+    /// Falls back to [`ROOT_EXPIRATION_WINDOW`] if the contract does not define an expiry.
+    fn read_expiration_window(&self, state: &impl StateProvider) -> ProviderResult<u64> {
+        if let Some(expiration_window) = self.config.root_expiration_override {
+            return Ok(expiration_window);
+        }
+
+        Ok(state
+            .storage(self.world_id, self.config.root_history_expiry_slot.into())?
+            .filter(|expiry| !expiry.is_zero())
+            .map_or(ROOT_EXPIRATION_WINDOW, |expiry| expiry.saturating_to()))
+    }
+
     /// Commits any changes to the state.
     ///
+    /// Blocks are committed both through new head blocks and canonical state notifications,
+    /// which are not ordered with respect to each other. Blocks at or below the tip have
+    /// therefore either been committed already or are delivered late, and are ignored. Reorgs
+    /// are only applied through [`Self::on_canon_state_notification`].
+    ///
     /// # Arguments
     ///
     /// * `block` - The new block to be committed.
@@ -85,16 +296,25 @@ where
     where
         B: reth_primitives_traits::Block,
     {
+        if self.tip.is_some_and(|tip| block.number() <= tip.number) {
+            return Ok(());
+        }
+
         let state = self
             .client
             .state_by_block_hash(block.hash())
             .map_err(WorldChainTransactionPoolError::Provider)?;
         let root = state
-            .storage(self.world_id, LATEST_ROOT_SLOT.into())
+            .storage(self.world_id, self.config.latest_root_slot.into())
+            .map_err(WorldChainTransactionPoolError::Provider)?;
+        self.expiration_window = self
+            .read_expiration_window(&state)
             .map_err(WorldChainTransactionPoolError::Provider)?;
+
+        self.tip = Some(BlockNumHash::new(block.number(), block.hash()));
         self.latest_valid_timestamp = block.timestamp();
         if let Some(root) = root {
-            self.valid_roots.insert(block.timestamp(), root);
+            self.insert(block.number(), block.timestamp(), root);
         }
 
         self.prune_invalid();
@@ -102,25 +322,138 @@ where
         Ok(())
     }
 
+    /// Applies a canonical state notification.
+    ///
+    /// On a reorg, the roots recorded for the reverted blocks are dropped before the blocks of
+    /// the new chain are committed.
+    fn on_canon_state_notification<N>(
+        &mut self,
+        notification: &CanonStateNotification<N>,
+    ) -> Result<(), WorldChainTransactionPoolError>
+    where
+        N: NodePrimitives,
+    {
+        if let Some(reverted) = notification.reverted() {
+            let first = reverted.first();
+            self.revert_to(BlockNumHash::new(
+                first.number().saturating_sub(1),
+                first.parent_hash(),
+            ));
+        }
+
+        for block in notification.committed().blocks_iter() {
+            self.on_new_block(block.sealed_block())?;
+        }
+
+        Ok(())
+    }
+
+    /// Drops the roots recorded for blocks above the common ancestor `fork` of a reorg, which
+    /// becomes the new tip.
+    fn revert_to(&mut self, fork: BlockNumHash) {
+        self.valid_roots.split_off(&(fork.number + 1));
+        self.tip = Some(fork);
+    }
+
+    /// Records `root` as the latest root at the given block.
+    fn insert(&mut self, number: BlockNumber, timestamp: u64, root: Field) {
+        if let Some(entry) = self
+            .valid_roots
+            .last_entry()
+            .filter(|entry| entry.get().root == root)
+        {
+            let entry = entry.into_mut();
+            entry.last_seen = entry.last_seen.max(timestamp);
+            return;
+        }
+
+        self.valid_roots.insert(
+            number,
+            RootEntry {
+                root,
+                last_seen: timestamp,
+            },
+        );
+    }
+
     /// Prunes all roots from the cache that are not within the expiration window.
     fn prune_invalid(&mut self) {
-        if self.latest_valid_timestamp > ROOT_EXPIRATION_WINDOW {
-            self.valid_roots.retain(|timestamp, root| {
-                *timestamp >= self.latest_valid_timestamp - ROOT_EXPIRATION_WINDOW
-                    || *root == self.latest_root // Always keep the latest root
+        if self.latest_valid_timestamp > self.expiration_window {
+            let cutoff = self.latest_valid_timestamp - self.expiration_window;
+            let latest = self.valid_roots.keys().next_back().copied();
+            self.valid_roots.retain(|number, entry| {
+                entry.last_seen >= cutoff || Some(*number) == latest // Always keep the latest root
             });
         };
     }
 
-    /// Returns a vector of all valid roots.
-    ///
-    /// # Returns
-    ///
-    /// A `Vec<Field>` containing all valid roots.
-    // TODO: can this be a slice instead?
-    fn roots(&self) -> Vec<Field> {
-        self.valid_roots.values().cloned().collect()
+    /// Rebuilds the snapshot of the valid roots if they changed.
+    fn update_snapshot(&mut self) {
+        let roots = self.valid_roots.values().map(|entry| entry.root);
+        if !roots.clone().eq(self.snapshot.as_slice().iter().copied()) {
+            self.snapshot = Arc::new(roots.collect());
+        }
+    }
+
+    /// Returns the latest snapshot of the valid roots.
+    fn snapshot(&self) -> Arc<ValidRoots> {
+        self.snapshot.clone()
+    }
+}
+
+/// Returns the first block in `lo..=hi` with a timestamp of at least `timestamp`, or `hi` if
+/// there is none.
+fn first_block_at_or_after<E>(
+    mut lo: BlockNumber,
+    mut hi: BlockNumber,
+    timestamp: u64,
+    mut timestamp_at: impl FnMut(BlockNumber) -> Result<u64, E>,
+) -> Result<BlockNumber, E> {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if timestamp_at(mid)? < timestamp {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(lo)
+}
+
+/// Returns the blocks in `(from, to]` which updated the root, along with the root they set, in
+/// ascending order.
+///
+/// Instead of reading the root of every block, the range is bisected between blocks with
+/// differing roots, which takes a logarithmic number of reads per root update. This relies on
+/// the WorldID root never returning to a previous value.
+fn root_changes<E>(
+    from: (BlockNumber, Option<Field>),
+    to: (BlockNumber, Option<Field>),
+    mut root_at: impl FnMut(BlockNumber) -> Result<Option<Field>, E>,
+) -> Result<Vec<(BlockNumber, Option<Field>)>, E> {
+    fn bisect<E>(
+        (lo, lo_root): (BlockNumber, Option<Field>),
+        (hi, hi_root): (BlockNumber, Option<Field>),
+        root_at: &mut impl FnMut(BlockNumber) -> Result<Option<Field>, E>,
+        changes: &mut Vec<(BlockNumber, Option<Field>)>,
+    ) -> Result<(), E> {
+        if lo_root == hi_root || hi <= lo {
+            return Ok(());
+        }
+        if hi - lo == 1 {
+            changes.push((hi, hi_root));
+            return Ok(());
+        }
+
+        let mid = lo + (hi - lo) / 2;
+        let mid_root = root_at(mid)?;
+        bisect((lo, lo_root), (mid, mid_root), root_at, changes)?;
+        bisect((mid, mid_root), (hi, hi_root), root_at, changes)
     }
+
+    let mut changes = vec![];
+    bisect(from, to, &mut root_at, &mut changes)?;
+    Ok(changes)
 }
 
 /// A validator for World Chain roots.
@@ -131,6 +464,11 @@ where
 {
     /// The [`RootProvider`] used for caching and managing roots.
     cache: Arc<RwLock<RootProvider<Client>>>,
+    /// The latest snapshot of the valid roots published by the [`RootProvider`].
+    ///
+    /// Kept separately from the provider so that validation never waits for the provider to
+    /// read the state of a new block.
+    roots: Arc<RwLock<Arc<ValidRoots>>>,
 }
 
 impl<Client> WorldChainRootValidator<Client>
@@ -143,13 +481,33 @@ where
     ///
     /// * `client` - The client used for state and block operations.
     pub fn new(client: Client, world_id: Address) -> Result<Self, WorldChainTransactionPoolError> {
-        let cache = RootProvider::new(client, world_id)?;
+        Self::with_config(client, world_id, RootConfig::default())
+    }
+
+    /// Creates a new [`WorldChainRootValidator`] instance with the given [`RootConfig`].
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client used for state and block operations.
+    /// * `config` - The configuration of the root provider.
+    pub fn with_config(
+        client: Client,
+        world_id: Address,
+        config: RootConfig,
+    ) -> Result<Self, WorldChainTransactionPoolError> {
+        let cache = RootProvider::new(client, world_id, config)?;
 
         Ok(Self {
+            roots: Arc::new(RwLock::new(cache.snapshot())),
             cache: Arc::new(RwLock::new(cache)),
         })
     }
 
+    /// Returns the duration in seconds for which a root remains valid after it was superseded.
+    pub fn expiration_window(&self) -> u64 {
+        self.cache.read().expiration_window
+    }
+
     /// Validates a given root.
     ///
     /// # Arguments
@@ -160,7 +518,7 @@ where
     ///
     /// A boolean indicating whether the root is valid.
     pub fn validate_root(&self, root: Field) -> bool {
-        self.cache.read().roots().contains(&root)
+        self.roots.read().contains(&root)
     }
 
     /// Commits a new block to the validator.
@@ -172,21 +530,71 @@ where
     where
         B: reth_primitives_traits::Block,
     {
-        if let Err(e) = self.cache.write().on_new_block(block) {
+        if let Err(e) = self.update(|cache| cache.on_new_block(block)) {
             tracing::error!("Failed to commit new block: {e}");
         }
     }
 
+    /// Applies a canonical state notification to the validator, dropping the roots of reverted
+    /// blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The canonical state notification.
+    pub fn on_canon_state_notification<N>(&self, notification: &CanonStateNotification<N>)
+    where
+        N: NodePrimitives,
+    {
+        if let Err(e) = self.update(|cache| cache.on_canon_state_notification(notification)) {
+            tracing::error!("Failed to apply canonical state notification: {e}");
+        }
+    }
+
+    /// Returns the latest snapshot of the valid roots.
+    pub fn valid_roots(&self) -> Arc<ValidRoots> {
+        self.roots.read().clone()
+    }
+
+    /// Returns a vector of all valid roots, ordered by the block in which they became the
+    /// latest root.
     pub fn roots(&self) -> Vec<Field> {
-        self.cache.read().roots()
+        self.valid_roots().as_slice().to_vec()
+    }
+
+    /// Applies `f` to the [`RootProvider`] and publishes the resulting snapshot of the valid
+    /// roots.
+    fn update<T>(&self, f: impl FnOnce(&mut RootProvider<Client>) -> T) -> T {
+        let mut cache = self.cache.write();
+        let result = f(&mut cache);
+        cache.update_snapshot();
+        *self.roots.write() = cache.snapshot();
+        result
+    }
+}
+
+/// Long running task that keeps the roots of a [`WorldChainRootValidator`] in sync with the
+/// canonical chain, including reorgs.
+pub async fn maintain_roots<Client, N, St>(
+    validator: WorldChainRootValidator<Client>,
+    mut events: St,
+) where
+    Client: StateProviderFactory + BlockReaderIdExt,
+    N: NodePrimitives,
+    St: Stream<Item = CanonStateNotification<N>> + Send + Unpin + 'static,
+{
+    while let Some(notification) = events.next().await {
+        validator.on_canon_state_notification(&notification);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use alloy_primitives::{address, Address};
-    use reth_primitives::Header;
-    use reth_provider::test_utils::{ExtendedAccount, MockEthProvider};
+    use reth_primitives::{EthPrimitives, Header, RecoveredBlock};
+    use reth_provider::{
+        test_utils::{ExtendedAccount, MockEthProvider},
+        BlockReader, Chain, ExecutionOutcome,
+    };
 
     /// Devnet World ID for testing
     const DEV_WORLD_ID: Address = address!("5FbDB2315678afecb367f032d93F642f64180aa3");
@@ -202,10 +610,12 @@ mod tests {
 
     fn add_block_with_root_with_timestamp(
         validator: &WorldChainRootValidator<MockEthProvider>,
+        number: u64,
         timestamp: u64,
         root: Field,
-    ) {
+    ) -> SealedBlock<<MockEthProvider as BlockReader>::Block> {
         let header = Header {
+            number,
             timestamp,
             ..Default::default()
         };
@@ -226,6 +636,7 @@ mod tests {
             .add_block(block.hash_slow(), block.clone());
         let block = SealedBlock::seal_slow(block);
         validator.on_new_block(&block);
+        block
     }
 
     #[test]
@@ -233,20 +644,257 @@ mod tests {
         let validator = world_chain_root_validator()?;
         let root_1 = Field::from(1u64);
         let timestamp = 1000000000;
-        add_block_with_root_with_timestamp(&validator, timestamp, root_1);
+        add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
         assert!(validator.validate_root(root_1));
         let root_2 = Field::from(2u64);
-        add_block_with_root_with_timestamp(&validator, timestamp + 604800 + 1, root_2);
+        add_block_with_root_with_timestamp(&validator, 2, timestamp + 604800 + 1, root_2);
         assert!(validator.validate_root(root_2));
         assert!(!validator.validate_root(root_1));
         let root_3 = Field::from(3u64);
-        add_block_with_root_with_timestamp(&validator, timestamp + 604800 + 604800, root_3);
+        add_block_with_root_with_timestamp(&validator, 3, timestamp + 604800 + 604800, root_3);
+        assert!(validator.validate_root(root_3));
+        assert!(validator.validate_root(root_2));
+        assert!(!validator.validate_root(root_1));
+        Ok(())
+    }
+
+    #[test]
+    fn unchanged_root_stays_valid() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        let timestamp = 1000000000;
+        add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
+        // The root is still the latest root a week later
+        add_block_with_root_with_timestamp(&validator, 2, timestamp + 604800, root_1);
+        add_block_with_root_with_timestamp(&validator, 3, timestamp + 604800 + 1, root_2);
+        assert!(validator.validate_root(root_1));
+        assert!(validator.validate_root(root_2));
+        assert_eq!(validator.roots(), vec![root_1, root_2]);
+        Ok(())
+    }
+
+    #[test]
+    fn reorg_drops_orphaned_roots() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2, root_3) = (Field::from(1u64), Field::from(2u64), Field::from(3u64));
+        let timestamp = 1000000000;
+        let block_1 = add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
+        add_block_with_root_with_timestamp(&validator, 2, timestamp + 2, root_2);
+        assert!(validator.validate_root(root_2));
+
+        // A competing block at height 2 is ignored until the reorg is applied
+        let competing = add_block_with_root_with_timestamp(&validator, 2, timestamp + 3, root_3);
+        assert!(validator.validate_root(root_2));
+        assert!(!validator.validate_root(root_3));
+
+        // The competing block replaces the block which introduced `root_2`
+        validator.update(|cache| cache.revert_to(BlockNumHash::new(1, block_1.hash())));
+        validator.on_new_block(&competing);
+        assert!(validator.validate_root(root_1));
+        assert!(!validator.validate_root(root_2));
         assert!(validator.validate_root(root_3));
+        Ok(())
+    }
+
+    #[test]
+    fn reorg_notification_drops_orphaned_roots() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2, root_3, root_4) = (
+            Field::from(1u64),
+            Field::from(2u64),
+            Field::from(3u64),
+            Field::from(4u64),
+        );
+        let timestamp = 1000000000;
+        add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
+        let block_2 = add_block_with_root_with_timestamp(&validator, 2, timestamp + 2, root_2);
+        let block_3 = add_block_with_root_with_timestamp(&validator, 3, timestamp + 4, root_3);
+        let competing = add_block_with_root_with_timestamp(&validator, 2, timestamp + 3, root_4);
+        assert_eq!(validator.roots(), vec![root_1, root_2, root_3]);
+
+        let chain = |blocks: Vec<SealedBlock<_>>| {
+            Arc::new(Chain::<EthPrimitives>::new(
+                blocks
+                    .into_iter()
+                    .map(|block| RecoveredBlock::new_sealed(block, vec![])),
+                ExecutionOutcome::default(),
+                None,
+            ))
+        };
+        validator.on_canon_state_notification(&CanonStateNotification::Reorg {
+            old: chain(vec![block_2, block_3]),
+            new: chain(vec![competing]),
+        });
+        assert_eq!(validator.roots(), vec![root_1, root_4]);
+        Ok(())
+    }
+
+    #[test]
+    fn late_block_does_not_revert() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        let timestamp = 1000000000;
+        let block_1 = add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
+        add_block_with_root_with_timestamp(&validator, 2, timestamp + 2, root_2);
+
+        // Block 1 is delivered again by the other source after block 2 was committed
+        validator.on_new_block(&block_1);
+        assert_eq!(validator.roots(), vec![root_1, root_2]);
+        assert_eq!(validator.cache.read().latest_valid_timestamp, timestamp + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn revert_drops_reverted_roots() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        let timestamp = 1000000000;
+        let block_1 = add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
+        add_block_with_root_with_timestamp(&validator, 2, timestamp + 2, root_2);
+
+        validator.update(|cache| cache.revert_to(BlockNumHash::new(1, block_1.hash())));
+        assert_eq!(validator.roots(), vec![root_1]);
+
+        // Committing the new branch restores the roots of its blocks
+        add_block_with_root_with_timestamp(&validator, 2, timestamp + 4, root_1);
+        add_block_with_root_with_timestamp(&validator, 3, timestamp + 6, root_2);
+        assert_eq!(validator.roots(), vec![root_1, root_2]);
+        Ok(())
+    }
+
+    #[test]
+    fn recommitting_block_is_idempotent() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        add_block_with_root_with_timestamp(&validator, 1, 1000000000, root_1);
+        let block = add_block_with_root_with_timestamp(&validator, 2, 1000000002, root_2);
+
+        validator.on_new_block(&block);
+        assert_eq!(validator.roots(), vec![root_1, root_2]);
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_is_shared_until_roots_change() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        add_block_with_root_with_timestamp(&validator, 1, 1000000000, root_1);
+        let snapshot = validator.valid_roots();
+
+        // A block which does not change the root keeps the snapshot
+        add_block_with_root_with_timestamp(&validator, 2, 1000000002, root_1);
+        assert!(Arc::ptr_eq(&snapshot, &validator.valid_roots()));
+
+        add_block_with_root_with_timestamp(&validator, 3, 1000000004, root_2);
+        let updated = validator.valid_roots();
+        assert!(!Arc::ptr_eq(&snapshot, &updated));
+        assert_eq!(updated.as_slice(), &[root_1, root_2]);
+        assert!(updated.contains(&root_1) && updated.contains(&root_2));
+
+        // Snapshots taken before the update are unaffected
+        assert_eq!(snapshot.as_slice(), &[root_1]);
+        Ok(())
+    }
+
+    #[test]
+    fn expiration_window_from_contract() -> eyre::Result<()> {
+        let validator = world_chain_root_validator()?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        let timestamp = 1000000000;
+        add_block_with_root_with_timestamp(&validator, 1, timestamp, root_1);
+        assert_eq!(validator.expiration_window(), ROOT_EXPIRATION_WINDOW);
+
+        // The contract shortens the root history expiry to an hour
+        let block = AlloyBlock {
+            header: Header {
+                number: 2,
+                timestamp: timestamp + 3601,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let client = validator.cache.read().client().clone();
+        client.add_account(
+            DEV_WORLD_ID,
+            ExtendedAccount::new(0, U256::ZERO).extend_storage(vec![
+                (LATEST_ROOT_SLOT.into(), root_2),
+                (ROOT_HISTORY_EXPIRY_SLOT.into(), U256::from(3600)),
+            ]),
+        );
+        client.add_block(block.hash_slow(), block.clone());
+        validator.on_new_block(&SealedBlock::seal_slow(block));
+
+        assert_eq!(validator.expiration_window(), 3600);
+        assert!(validator.validate_root(root_2));
+        assert!(!validator.validate_root(root_1));
+        Ok(())
+    }
+
+    #[test]
+    fn expiration_window_override() -> eyre::Result<()> {
+        let config = RootConfig {
+            root_expiration_override: Some(10),
+            ..Default::default()
+        };
+        let validator =
+            WorldChainRootValidator::with_config(MockEthProvider::default(), DEV_WORLD_ID, config)?;
+        let (root_1, root_2) = (Field::from(1u64), Field::from(2u64));
+        add_block_with_root_with_timestamp(&validator, 1, 1000000000, root_1);
+        add_block_with_root_with_timestamp(&validator, 2, 1000000011, root_2);
+
+        assert_eq!(validator.expiration_window(), 10);
         assert!(validator.validate_root(root_2));
         assert!(!validator.validate_root(root_1));
         Ok(())
     }
 
+    #[test]
+    fn root_changes_bisects_history() {
+        // The root is updated in blocks 10, 11 and 700 of a 1000 block history
+        let root_at = |number: BlockNumber| -> Option<Field> {
+            match number {
+                0..10 => None,
+                10 => Some(Field::from(1u64)),
+                11..700 => Some(Field::from(2u64)),
+                _ => Some(Field::from(3u64)),
+            }
+        };
+
+        let mut reads = 0;
+        let changes = root_changes((0, root_at(0)), (1000, root_at(1000)), |number| {
+            reads += 1;
+            Ok::<_, ()>(root_at(number))
+        })
+        .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                (10, Some(Field::from(1u64))),
+                (11, Some(Field::from(2u64))),
+                (700, Some(Field::from(3u64))),
+            ]
+        );
+        assert!(reads < 50, "{reads} reads");
+    }
+
+    #[test]
+    fn root_changes_without_updates() {
+        let root = Some(Field::from(1u64));
+        let changes =
+            root_changes((0, root), (1000, root), |_| Err::<Option<Field>, _>(())).unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn first_block_at_or_after_timestamp() {
+        let timestamp_at = |number: BlockNumber| Ok::<_, ()>(1000 + number * 2);
+        assert_eq!(first_block_at_or_after(0, 100, 0, timestamp_at), Ok(0));
+        assert_eq!(first_block_at_or_after(0, 100, 1011, timestamp_at), Ok(6));
+        assert_eq!(first_block_at_or_after(0, 100, 1012, timestamp_at), Ok(6));
+        assert_eq!(first_block_at_or_after(0, 100, 5000, timestamp_at), Ok(100));
+    }
+
     impl<Client> WorldChainRootValidator<Client>
     where
         Client: StateProviderFactory + BlockReaderIdExt,
@@ -0,0 +1,829 @@
+//! Opt-in simulation of PBH bundles against the latest state.
+//!
+//! The bundle is executed through the EVM as it would be in the next block, while the validation
+//! phase of every UserOp is traced and checked against the ERC-7562 opcode and storage access
+//! rules.
+//! Bundles which revert, violate the rules or exceed the per-op gas limits are rejected before
+//! they receive PBH priority.
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+
+use alloy_consensus::BlockHeader;
+use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_sol_types::{SolCall, SolInterface};
+use reth::{
+    chainspec::EthChainSpec, revm::database::StateProviderDatabase,
+    transaction_pool::PoolTransaction,
+};
+use reth_evm::{ConfigureEvm, Evm};
+use reth_optimism_forks::OpHardforks;
+use reth_optimism_node::{OpEvmConfig, OpNextBlockEnvAttributes};
+use reth_optimism_primitives::OpTransactionSigned;
+use reth_primitives::Header;
+use reth_provider::{BlockReaderIdExt, StateProviderFactory};
+use revm::{
+    bytecode::opcode,
+    context::{result::ExecutionResult, ContextTr},
+    interpreter::{
+        interpreter::EthInterpreter, interpreter_types::Jumps, CallInputs, CallOutcome,
+        CreateInputs, CreateOutcome, Interpreter,
+    },
+    Inspector,
+};
+use thiserror::Error;
+use world_chain_pbh::clock::{Clock, SystemClock};
+
+use crate::{
+    bindings::{
+        IAccount::validateUserOpCall,
+        IEntryPoint::{IEntryPointErrors, PackedUserOperation, UserOpsPerAggregator},
+        IPaymaster::validatePaymasterUserOpCall,
+    },
+    eip4337::ENTRYPOINT_V07,
+};
+
+/// The number of slots following a slot derived from an entity's address which are associated
+/// with the entity, as defined by ERC-7562.
+const ASSOCIATED_SLOT_RANGE: u64 = 128;
+
+/// Opcodes which must not be used during the validation phase of a UserOp (ERC-7562 OP-011).
+///
+/// `CREATE2` is only allowed when deploying the sender, which happens before the account is
+/// validated and is therefore not traced.
+const FORBIDDEN_OPCODES: &[u8] = &[
+    opcode::ORIGIN,
+    opcode::GASPRICE,
+    opcode::BLOCKHASH,
+    opcode::COINBASE,
+    opcode::TIMESTAMP,
+    opcode::NUMBER,
+    opcode::DIFFICULTY,
+    opcode::GASLIMIT,
+    opcode::BALANCE,
+    opcode::SELFBALANCE,
+    opcode::BASEFEE,
+    opcode::BLOBHASH,
+    opcode::BLOBBASEFEE,
+    opcode::CREATE,
+    opcode::CREATE2,
+    opcode::INVALID,
+    opcode::SELFDESTRUCT,
+];
+
+/// Opcodes which may follow `GAS` during the validation phase of a UserOp (ERC-7562 OP-012).
+const CALL_OPCODES: &[u8] = &[
+    opcode::CALL,
+    opcode::CALLCODE,
+    opcode::DELEGATECALL,
+    opcode::STATICCALL,
+];
+
+/// Configuration of the PBH bundle simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationConfig {
+    /// The ERC-4337 EntryPoint wrapped by the PBHEntryPoint, whose own code is exempt from the
+    /// validation rules.
+    pub entry_point: Address,
+    /// The maximum verification gas limit of a single UserOp.
+    pub max_verification_gas: Option<u64>,
+    /// The maximum call gas limit of a single UserOp.
+    pub max_call_gas: Option<u64>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            entry_point: ENTRYPOINT_V07,
+            max_verification_gas: None,
+            max_call_gas: None,
+        }
+    }
+}
+
+/// A PBH bundle was rejected by the simulation.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SimulationError {
+    #[error("UserOp {index} verification gas limit {gas_limit} exceeds the maximum of {limit}")]
+    VerificationGasLimitExceeded {
+        index: usize,
+        gas_limit: u128,
+        limit: u64,
+    },
+    #[error("UserOp {index} call gas limit {gas_limit} exceeds the maximum of {limit}")]
+    CallGasLimitExceeded {
+        index: usize,
+        gas_limit: u128,
+        limit: u64,
+    },
+    #[error("UserOp {index} failed: {reason}")]
+    FailedOp { index: U256, reason: String },
+    #[error("bundle reverted: {0}")]
+    Reverted(Bytes),
+    #[error("bundle halted: {0}")]
+    Halted(String),
+    #[error("validation of UserOp of {sender} uses forbidden opcode {opcode:#04x} in {address}")]
+    ForbiddenOpcode {
+        sender: Address,
+        address: Address,
+        opcode: u8,
+    },
+    #[error("validation of UserOp of {sender} accesses slot {slot} of {address}")]
+    ForbiddenStorageAccess {
+        sender: Address,
+        address: Address,
+        slot: U256,
+    },
+    #[error("failed to simulate bundle: {0}")]
+    Simulation(String),
+}
+
+impl SimulationError {
+    /// Returns the stable numeric code of the error.
+    ///
+    /// Codes are exposed to RPC clients and must never be changed or reused.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::VerificationGasLimitExceeded { .. } => 1201,
+            Self::CallGasLimitExceeded { .. } => 1202,
+            Self::FailedOp { .. } => 1203,
+            Self::Reverted(_) => 1204,
+            Self::Halted(_) => 1205,
+            Self::ForbiddenOpcode { .. } => 1206,
+            Self::ForbiddenStorageAccess { .. } => 1207,
+            Self::Simulation(_) => 1208,
+        }
+    }
+
+    /// Returns the index of the UserOp which failed the simulation, if known.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            Self::VerificationGasLimitExceeded { index, .. }
+            | Self::CallGasLimitExceeded { index, .. } => Some(*index),
+            Self::FailedOp { index, .. } => (*index).try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Simulates PBH bundles before they are admitted to the pool.
+pub trait BundleSimulator<Tx>: Debug + Send + Sync {
+    /// Simulates the PBH bundle `tx` carrying the aggregated UserOps `bundle`.
+    fn simulate(&self, tx: &Tx, bundle: &[UserOpsPerAggregator]) -> Result<(), SimulationError>;
+}
+
+/// Returns the `(verificationGasLimit, callGasLimit)` packed into the `accountGasLimits` of
+/// `user_op`.
+pub fn unpack_gas_limits(user_op: &PackedUserOperation) -> (u128, u128) {
+    let (verification_gas_limit, call_gas_limit) = user_op.accountGasLimits.split_at(16);
+    (
+        u128::from_be_bytes(verification_gas_limit.try_into().unwrap()),
+        u128::from_be_bytes(call_gas_limit.try_into().unwrap()),
+    )
+}
+
+/// Checks the gas limits of every UserOp of `bundle` against the maxima of `config`.
+pub fn check_user_op_gas_limits(
+    bundle: &[UserOpsPerAggregator],
+    config: &SimulationConfig,
+) -> Result<(), SimulationError> {
+    let user_ops = bundle
+        .iter()
+        .flat_map(|aggregated_ops| &aggregated_ops.userOps);
+    for (index, user_op) in user_ops.enumerate() {
+        let (verification_gas_limit, call_gas_limit) = unpack_gas_limits(user_op);
+
+        if let Some(limit) = config
+            .max_verification_gas
+            .filter(|limit| verification_gas_limit > *limit as u128)
+        {
+            return Err(SimulationError::VerificationGasLimitExceeded {
+                index,
+                gas_limit: verification_gas_limit,
+                limit,
+            });
+        }
+
+        if let Some(limit) = config
+            .max_call_gas
+            .filter(|limit| call_gas_limit > *limit as u128)
+        {
+            return Err(SimulationError::CallGasLimitExceeded {
+                index,
+                gas_limit: call_gas_limit,
+                limit,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the revert `output` of a bundle, surfacing the UserOp which caused the EntryPoint
+/// to revert.
+pub fn decode_revert(output: Bytes) -> SimulationError {
+    match IEntryPointErrors::abi_decode(&output) {
+        Ok(IEntryPointErrors::FailedOp(err)) => SimulationError::FailedOp {
+            index: err.opIndex,
+            reason: err.reason,
+        },
+        Ok(IEntryPointErrors::FailedOpWithRevert(err)) => SimulationError::FailedOp {
+            index: err.opIndex,
+            reason: format!("{} {}", err.reason, err.inner),
+        },
+        Err(_) => SimulationError::Reverted(output),
+    }
+}
+
+/// A validation call of the EntryPoint into an account or paymaster.
+#[derive(Debug, Clone, Copy)]
+struct ValidationFrame {
+    /// The sender of the UserOp being validated.
+    sender: Address,
+    /// The call depth of the validation call.
+    depth: usize,
+}
+
+/// Traces the validation phase of the UserOps in a bundle and records the first violation of
+/// the ERC-7562 opcode and storage access rules.
+///
+/// The rules applied are those for unstaked entities: during validation a UserOp may only access
+/// the storage of its sender and the storage associated with the sender in other contracts.
+#[derive(Debug)]
+pub struct ValidationTracer {
+    entry_point: Address,
+    depth: usize,
+    frame: Option<ValidationFrame>,
+    previous_opcode: Option<u8>,
+    /// The first 32 bytes of the input of an executing `KECCAK256`.
+    pending_keccak: Option<[u8; 32]>,
+    /// Slots derived from the address of a sender, along with the sender.
+    associated_slots: BTreeMap<U256, Address>,
+    violation: Option<SimulationError>,
+}
+
+impl ValidationTracer {
+    pub fn new(entry_point: Address) -> Self {
+        Self {
+            entry_point,
+            depth: 0,
+            frame: None,
+            previous_opcode: None,
+            pending_keccak: None,
+            associated_slots: BTreeMap::new(),
+            violation: None,
+        }
+    }
+
+    /// Returns the first violation of the validation rules.
+    pub fn into_result(self) -> Result<(), SimulationError> {
+        self.violation.map_or(Ok(()), Err)
+    }
+
+    fn in_validation(&self) -> bool {
+        self.frame.is_some()
+    }
+
+    fn violate(&mut self, violation: SimulationError) {
+        self.violation.get_or_insert(violation);
+    }
+
+    fn enter_call(&mut self, caller: Address, target: Address, input: &[u8]) {
+        self.depth += 1;
+        if self.frame.is_some() || caller != self.entry_point || input.len() < 4 {
+            return;
+        }
+
+        let selector: [u8; 4] = input[..4].try_into().unwrap();
+        let sender = match selector {
+            validateUserOpCall::SELECTOR => Some(target),
+            validatePaymasterUserOpCall::SELECTOR => validatePaymasterUserOpCall::abi_decode(input)
+                .ok()
+                .map(|call| call.userOp.sender),
+            _ => None,
+        };
+
+        self.frame = sender.map(|sender| ValidationFrame {
+            sender,
+            depth: self.depth,
+        });
+        self.previous_opcode = None;
+    }
+
+    fn exit_call(&mut self) {
+        if self.frame.is_some_and(|frame| frame.depth == self.depth) {
+            self.frame = None;
+        }
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    fn on_opcode(&mut self, address: Address, op: u8) {
+        let Some(frame) = self.frame else {
+            return;
+        };
+        let previous_opcode = self.previous_opcode.replace(op);
+        if address == self.entry_point {
+            return;
+        }
+
+        let gas_not_followed_by_call =
+            previous_opcode == Some(opcode::GAS) && !CALL_OPCODES.contains(&op);
+        if gas_not_followed_by_call || FORBIDDEN_OPCODES.contains(&op) {
+            self.violate(SimulationError::ForbiddenOpcode {
+                sender: frame.sender,
+                address,
+                opcode: if gas_not_followed_by_call {
+                    opcode::GAS
+                } else {
+                    op
+                },
+            });
+        }
+    }
+
+    fn on_storage_access(&mut self, address: Address, slot: U256) {
+        let Some(frame) = self.frame else {
+            return;
+        };
+        if address == self.entry_point || address == frame.sender {
+            return;
+        }
+
+        let sender_slot = U256::from_be_bytes(frame.sender.into_word().0);
+        let associated = slot == sender_slot
+            || self
+                .associated_slots
+                .range(slot.saturating_sub(U256::from(ASSOCIATED_SLOT_RANGE))..=slot)
+                .any(|(_, sender)| *sender == frame.sender);
+
+        if !associated {
+            self.violate(SimulationError::ForbiddenStorageAccess {
+                sender: frame.sender,
+                address,
+                slot,
+            });
+        }
+    }
+
+    fn on_keccak(&mut self, preimage: [u8; 32], hash: U256) {
+        let Some(frame) = self.frame else {
+            return;
+        };
+        if preimage == frame.sender.into_word().0 {
+            self.associated_slots.insert(hash, frame.sender);
+        }
+    }
+}
+
+impl<CTX: ContextTr> Inspector<CTX, EthInterpreter> for ValidationTracer {
+    fn step(&mut self, interp: &mut Interpreter<EthInterpreter>, _context: &mut CTX) {
+        if !self.in_validation() {
+            return;
+        }
+
+        let op = interp.bytecode.opcode();
+        let address = interp.input.target_address;
+        self.on_opcode(address, op);
+
+        let stack = interp.stack.data();
+        match op {
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Some(slot) = stack.last() {
+                    self.on_storage_access(address, *slot);
+                }
+            }
+            opcode::KECCAK256 if stack.len() >= 2 => {
+                let offset = stack[stack.len() - 1].saturating_to::<usize>();
+                let size = stack[stack.len() - 2].saturating_to::<usize>();
+                if size >= 32 && offset.saturating_add(32) <= interp.memory.len() {
+                    let mut preimage = [0u8; 32];
+                    preimage.copy_from_slice(&*interp.memory.slice_len(offset, 32));
+                    self.pending_keccak = Some(preimage);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter<EthInterpreter>, _context: &mut CTX) {
+        if let Some(preimage) = self.pending_keccak.take() {
+            if let Some(hash) = interp.stack.data().last() {
+                self.on_keccak(preimage, *hash);
+            }
+        }
+    }
+
+    fn call(&mut self, context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let input = inputs.input.bytes(context);
+        self.enter_call(inputs.caller, inputs.target_address, &input);
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, _outcome: &mut CallOutcome) {
+        self.exit_call();
+    }
+
+    fn create(&mut self, _context: &mut CTX, _inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        self.depth += 1;
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut CTX,
+        _inputs: &CreateInputs,
+        _outcome: &mut CreateOutcome,
+    ) {
+        self.exit_call();
+    }
+}
+
+/// A [`BundleSimulator`] executing PBH bundles through the EVM on top of the latest state, in
+/// the environment of the next block.
+#[derive(Debug, Clone)]
+pub struct EvmBundleSimulator<Client, ChainSpec> {
+    client: Client,
+    evm_config: OpEvmConfig<ChainSpec>,
+    config: SimulationConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl<Client, ChainSpec> EvmBundleSimulator<Client, ChainSpec> {
+    pub fn new(
+        client: Client,
+        evm_config: OpEvmConfig<ChainSpec>,
+        config: SimulationConfig,
+    ) -> Self {
+        Self {
+            client,
+            evm_config,
+            config,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Sets the [`Clock`] used to derive the timestamp of the next block.
+    ///
+    /// Defaults to [`SystemClock`].
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+}
+
+impl<Client, ChainSpec, Tx> BundleSimulator<Tx> for EvmBundleSimulator<Client, ChainSpec>
+where
+    Client: StateProviderFactory + BlockReaderIdExt<Header = Header> + Debug + Send + Sync,
+    ChainSpec: EthChainSpec<Header = Header> + OpHardforks + 'static,
+    Tx: PoolTransaction<Consensus = OpTransactionSigned>,
+{
+    fn simulate(&self, tx: &Tx, bundle: &[UserOpsPerAggregator]) -> Result<(), SimulationError> {
+        check_user_op_gas_limits(bundle, &self.config)?;
+
+        let simulation_error =
+            |err: &dyn std::fmt::Display| SimulationError::Simulation(err.to_string());
+        let header = self
+            .client
+            .latest_header()
+            .map_err(|err| simulation_error(&err))?
+            .ok_or_else(|| SimulationError::Simulation("latest header not found".to_string()))?;
+        let state = self
+            .client
+            .state_by_block_hash(header.hash())
+            .map_err(|err| simulation_error(&err))?;
+
+        // The bundle is included at the earliest in the next block, whose timestamp is not known
+        // yet. Validation rules forbid accessing the block environment, so an estimate suffices.
+        let now = self.clock.now().timestamp().max(0) as u64;
+        let attributes = OpNextBlockEnvAttributes {
+            timestamp: now.max(header.timestamp() + 1),
+            suggested_fee_recipient: header.beneficiary(),
+            prev_randao: B256::ZERO,
+            gas_limit: header.gas_limit(),
+            parent_beacon_block_root: header.parent_beacon_block_root(),
+            extra_data: Default::default(),
+        };
+        let mut evm_env = self
+            .evm_config
+            .next_evm_env(header.header(), &attributes)
+            .map_err(|err| simulation_error(&err))?;
+        // The bundle may be queued behind other pooled transactions of the bundler
+        evm_env.cfg_env.disable_nonce_check = true;
+
+        let mut tracer = ValidationTracer::new(self.config.entry_point);
+        let result = self
+            .evm_config
+            .evm_with_env_and_inspector(StateProviderDatabase::new(&state), evm_env, &mut tracer)
+            .transact(tx.clone_into_consensus())
+            .map_err(|err| simulation_error(&err))?
+            .result;
+
+        match result {
+            ExecutionResult::Success { .. } => tracer.into_result(),
+            ExecutionResult::Revert { output, .. } => Err(decode_revert(output)),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(SimulationError::Halted(format!("{reason:?}")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{bytes, hex, keccak256};
+    use alloy_sol_types::SolError;
+    use reth_primitives::Block;
+    use reth_provider::ChainSpecProvider;
+    use world_chain_pbh::clock::FixedClock;
+    use world_chain_test::{
+        mock::{ExtendedAccount, MockEthProvider},
+        utils::{account, eip1559, eth_tx},
+    };
+
+    use super::*;
+    use crate::bindings::IEntryPoint::FailedOp;
+
+    const SENDER: Address = Address::repeat_byte(0x11);
+    const TOKEN: Address = Address::repeat_byte(0x22);
+    const PAYMASTER: Address = Address::repeat_byte(0x33);
+    const ORACLE: Address = Address::repeat_byte(0x44);
+
+    const PARENT_TIMESTAMP: u64 = 1_700_000_000;
+
+    /// A stand-in for the EntryPoint, calling `SENDER` with its own calldata. It reverts unless
+    /// it executes in block 2 at timestamp `PARENT_TIMESTAMP + 12`, and if the call fails.
+    const ENTRY_POINT_CODE: Bytes = bytes!(
+        "4360021415603e5742636553f10c1415603e573660006000376000600036600060007311111111111111111111111111111111111111115af115603e57005b60006000fd"
+    );
+
+    /// Reads `balances[msg.sender]`, with `balances` at slot 3.
+    const TOKEN_CODE: Bytes = bytes!("3360005260036020526040600020545000");
+
+    /// Reads slot 0.
+    const ORACLE_CODE: Bytes = bytes!("6000545000");
+
+    /// An account which reads its own slot 0 and then static calls `callee` during validation.
+    fn account_code(callee: Address) -> Bytes {
+        [
+            &hex!("60005450600060006000600073")[..],
+            callee.as_slice(),
+            &hex!("5afa5000"),
+        ]
+        .concat()
+        .into()
+    }
+
+    /// Simulates a transaction to the stand-in EntryPoint validating a UserOp of an account
+    /// deployed with `code`, on top of block 1 at `PARENT_TIMESTAMP`.
+    async fn simulate_user_op(code: Bytes, now: u64) -> Result<(), SimulationError> {
+        let client = MockEthProvider::default();
+        client.add_account(account(0), ExtendedAccount::new(0, U256::MAX));
+        for (address, code) in [
+            (ENTRYPOINT_V07, ENTRY_POINT_CODE),
+            (SENDER, code),
+            (TOKEN, TOKEN_CODE),
+            (ORACLE, ORACLE_CODE),
+        ] {
+            client.add_account(
+                address,
+                ExtendedAccount::new(0, U256::ZERO).with_bytecode(code),
+            );
+        }
+        let header = Header {
+            number: 1,
+            timestamp: PARENT_TIMESTAMP,
+            gas_limit: 20_000_000,
+            ..Default::default()
+        };
+        client.add_block(
+            B256::with_last_byte(1),
+            Block {
+                header,
+                body: Default::default(),
+            },
+        );
+
+        let simulator = EvmBundleSimulator::new(
+            client.clone(),
+            OpEvmConfig::optimism(client.chain_spec()),
+            SimulationConfig::default(),
+        )
+        .with_clock(FixedClock::from_timestamp(now).unwrap());
+        let tx = eip1559()
+            .to(ENTRYPOINT_V07)
+            .input(validate_user_op())
+            .call();
+        simulator.simulate(&eth_tx(0, tx).await, &[])
+    }
+
+    fn validate_user_op() -> Vec<u8> {
+        validateUserOpCall {
+            userOp: PackedUserOperation {
+                sender: SENDER,
+                ..Default::default()
+            },
+            userOpHash: B256::ZERO,
+            missingAccountFunds: U256::ZERO,
+        }
+        .abi_encode()
+    }
+
+    fn gas_limits(verification_gas_limit: u128, call_gas_limit: u128) -> B256 {
+        let mut packed = [0u8; 32];
+        packed[..16].copy_from_slice(&verification_gas_limit.to_be_bytes());
+        packed[16..].copy_from_slice(&call_gas_limit.to_be_bytes());
+        B256::from(packed)
+    }
+
+    #[test]
+    fn forbidden_opcodes_in_validation() {
+        let mut tracer = ValidationTracer::new(ENTRYPOINT_V07);
+
+        // Outside of the validation phase any opcode is allowed
+        tracer.on_opcode(SENDER, opcode::TIMESTAMP);
+        tracer.enter_call(SENDER, ENTRYPOINT_V07, &[]);
+        tracer.on_opcode(ENTRYPOINT_V07, opcode::NUMBER);
+
+        // GAS is allowed if immediately followed by a call
+        tracer.enter_call(ENTRYPOINT_V07, SENDER, &validate_user_op());
+        tracer.on_opcode(SENDER, opcode::GAS);
+        tracer.on_opcode(SENDER, opcode::CALL);
+        tracer.on_opcode(ENTRYPOINT_V07, opcode::TIMESTAMP);
+        tracer.exit_call();
+        tracer.on_opcode(ENTRYPOINT_V07, opcode::BALANCE);
+        tracer.exit_call();
+
+        let mut violating = ValidationTracer::new(ENTRYPOINT_V07);
+        violating.enter_call(ENTRYPOINT_V07, SENDER, &validate_user_op());
+        violating.on_opcode(SENDER, opcode::GAS);
+        violating.on_opcode(SENDER, opcode::ADD);
+        violating.on_opcode(SENDER, opcode::TIMESTAMP);
+
+        assert_eq!(tracer.into_result(), Ok(()));
+        assert_eq!(
+            violating.into_result(),
+            Err(SimulationError::ForbiddenOpcode {
+                sender: SENDER,
+                address: SENDER,
+                opcode: opcode::GAS,
+            })
+        );
+    }
+
+    #[test]
+    fn storage_access_in_validation() {
+        let mut tracer = ValidationTracer::new(ENTRYPOINT_V07);
+
+        // Calls to the paymaster are validated against the storage of the sender
+        let validate_paymaster_user_op = validatePaymasterUserOpCall {
+            userOp: PackedUserOperation {
+                sender: SENDER,
+                ..Default::default()
+            },
+            userOpHash: B256::ZERO,
+            maxCost: U256::ZERO,
+        }
+        .abi_encode();
+        tracer.enter_call(ENTRYPOINT_V07, PAYMASTER, &validate_paymaster_user_op);
+
+        // `balances[sender]` of a token, with `balances` at slot 3
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(SENDER.into_word().as_slice());
+        preimage[63] = 3;
+        let balance_slot = U256::from_be_bytes(keccak256(preimage).0);
+
+        tracer.on_keccak(preimage[..32].try_into().unwrap(), balance_slot);
+        tracer.on_storage_access(SENDER, U256::from(7));
+        tracer.on_storage_access(TOKEN, balance_slot);
+        tracer.on_storage_access(TOKEN, balance_slot + U256::from(ASSOCIATED_SLOT_RANGE));
+        tracer.on_storage_access(TOKEN, U256::from_be_bytes(SENDER.into_word().0));
+        tracer.exit_call();
+
+        // After the validation phase the storage of any contract can be accessed
+        tracer.on_storage_access(TOKEN, U256::ZERO);
+        assert_eq!(tracer.into_result(), Ok(()));
+
+        let mut violating = ValidationTracer::new(ENTRYPOINT_V07);
+        violating.enter_call(ENTRYPOINT_V07, SENDER, &validate_user_op());
+        violating.on_keccak(preimage[..32].try_into().unwrap(), balance_slot);
+        violating.on_storage_access(PAYMASTER, U256::ZERO);
+        violating.on_storage_access(TOKEN, balance_slot + U256::from(ASSOCIATED_SLOT_RANGE + 1));
+
+        assert_eq!(
+            violating.into_result(),
+            Err(SimulationError::ForbiddenStorageAccess {
+                sender: SENDER,
+                address: PAYMASTER,
+                slot: U256::ZERO,
+            })
+        );
+    }
+
+    #[test]
+    fn user_op_gas_limits() {
+        let bundle = [UserOpsPerAggregator {
+            userOps: vec![
+                PackedUserOperation {
+                    accountGasLimits: gas_limits(100_000, 50_000),
+                    ..Default::default()
+                },
+                PackedUserOperation {
+                    accountGasLimits: gas_limits(500_000, 50_000),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }];
+
+        assert_eq!(unpack_gas_limits(&bundle[0].userOps[1]), (500_000, 50_000));
+        assert_eq!(
+            check_user_op_gas_limits(&bundle, &SimulationConfig::default()),
+            Ok(())
+        );
+        assert_eq!(
+            check_user_op_gas_limits(
+                &bundle,
+                &SimulationConfig {
+                    max_verification_gas: Some(200_000),
+                    ..Default::default()
+                }
+            ),
+            Err(SimulationError::VerificationGasLimitExceeded {
+                index: 1,
+                gas_limit: 500_000,
+                limit: 200_000,
+            })
+        );
+        assert_eq!(
+            check_user_op_gas_limits(
+                &bundle,
+                &SimulationConfig {
+                    max_call_gas: Some(10_000),
+                    ..Default::default()
+                }
+            ),
+            Err(SimulationError::CallGasLimitExceeded {
+                index: 0,
+                gas_limit: 50_000,
+                limit: 10_000,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_failed_op() {
+        let output = FailedOp {
+            opIndex: U256::from(1),
+            reason: "AA23 reverted".to_string(),
+        }
+        .abi_encode();
+
+        assert_eq!(
+            decode_revert(output.into()),
+            SimulationError::FailedOp {
+                index: U256::from(1),
+                reason: "AA23 reverted".to_string(),
+            }
+        );
+        assert_eq!(
+            decode_revert(Bytes::from_static(&[0xde, 0xad])),
+            SimulationError::Reverted(Bytes::from_static(&[0xde, 0xad]))
+        );
+    }
+
+    #[tokio::test]
+    async fn simulate_compliant_user_op() {
+        assert_eq!(
+            simulate_user_op(account_code(TOKEN), PARENT_TIMESTAMP + 12).await,
+            Ok(())
+        );
+
+        // The bundle is simulated in the next block, at least one second after its parent
+        assert_eq!(
+            simulate_user_op(account_code(TOKEN), PARENT_TIMESTAMP - 12).await,
+            Err(SimulationError::Reverted(Bytes::new()))
+        );
+    }
+
+    #[tokio::test]
+    async fn simulate_user_op_violating_validation_rules() {
+        assert_eq!(
+            simulate_user_op(bytes!("425000"), PARENT_TIMESTAMP + 12).await,
+            Err(SimulationError::ForbiddenOpcode {
+                sender: SENDER,
+                address: SENDER,
+                opcode: opcode::TIMESTAMP,
+            })
+        );
+        assert_eq!(
+            simulate_user_op(account_code(ORACLE), PARENT_TIMESTAMP + 12).await,
+            Err(SimulationError::ForbiddenStorageAccess {
+                sender: SENDER,
+                address: ORACLE,
+                slot: U256::ZERO,
+            })
+        );
+    }
+}
@@ -4,10 +4,7 @@ use alloy_consensus::BlobTransactionValidationError;
 use alloy_eips::{eip7594::BlobTransactionSidecarVariant, eip7702::SignedAuthorization, Typed2718};
 use alloy_primitives::{Bytes, TxHash};
 use alloy_rpc_types::{erc4337::TransactionConditional, AccessList};
-use reth::transaction_pool::{
-    error::{InvalidPoolTransactionError, PoolTransactionError},
-    EthBlobTransactionSidecar, EthPoolTransaction, PoolTransaction, TransactionValidationOutcome,
-};
+use reth::transaction_pool::{EthBlobTransactionSidecar, EthPoolTransaction, PoolTransaction};
 use reth_optimism_node::txpool::{
     conditional::MaybeConditionalTransaction, estimated_da_size::DataAvailabilitySized,
     interop::MaybeInteropTransaction, OpPooledTransaction, OpPooledTx,
@@ -17,8 +14,7 @@ use reth_primitives::{kzg::KzgSettings, Recovered};
 use reth_primitives_traits::InMemorySize;
 use revm_primitives::{Address, TxKind, B256, U256};
 use std::borrow::Cow;
-use thiserror::Error;
-use world_chain_pbh::payload::{PBHPayload, PBHValidationError};
+use world_chain_pbh::payload::PBHPayload;
 
 #[derive(Debug, Clone)]
 pub struct WorldChainPooledTransaction {
@@ -251,39 +247,6 @@ impl PoolTransaction for WorldChainPooledTransaction {
     }
 }
 
-#[derive(Debug, Error)]
-pub enum WorldChainPoolTransactionError {
-    #[error("Conditional Validation Failed: {0}")]
-    ConditionalValidationFailed(B256),
-    #[error("PBH Transaction Validation Failed: {0}")]
-    PBH(#[from] PBHValidationError),
-}
-
-impl WorldChainPoolTransactionError {
-    pub fn to_outcome<T: PoolTransaction>(self, tx: T) -> TransactionValidationOutcome<T> {
-        TransactionValidationOutcome::Invalid(tx, self.into())
-    }
-}
-
-impl From<WorldChainPoolTransactionError> for InvalidPoolTransactionError {
-    fn from(val: WorldChainPoolTransactionError) -> Self {
-        InvalidPoolTransactionError::Other(Box::new(val))
-    }
-}
-
-//TODO: double check this?
-impl PoolTransactionError for WorldChainPoolTransactionError {
-    fn is_bad_transaction(&self) -> bool {
-        // TODO: double check if invalid transaction should be penalized, we could also make this a match statement
-        // If all errors should not be penalized, we can just return false
-        false
-    }
-
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-}
-
 impl From<OpPooledTransaction> for WorldChainPooledTransaction {
     fn from(tx: OpPooledTransaction) -> Self {
         Self {
@@ -1,34 +1,48 @@
 //! World Chain transaction pool types
 use std::{
-    collections::HashSet,
-    sync::{
-        atomic::{AtomicU16, AtomicU64, Ordering},
-        Arc,
-    },
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
 };
 
 use super::{root::WorldChainRootValidator, tx::WorldChainPoolTransaction};
 use crate::{
-    bindings::{IPBHEntryPoint, IPBHEntryPoint::PBHPayload},
-    error::WorldChainTransactionPoolError,
-    tx::WorldChainPoolTransactionError,
+    bindings::{IEntryPoint::UserOpsPerAggregator, IPBHEntryPoint},
+    eip4337::UserOpSignal,
+    entrypoint::{PbhEntrypoint, PbhEntrypoints},
+    error::{WorldChainPoolTransactionError, WorldChainTransactionPoolError},
+    limits::PbhLimits,
+    nullifier::nullifier_hash_spent_block,
+    nullifier_index::NullifierIndex,
+    proof_cache::{ProofCache, ProofCacheKey},
+    quota::{payload_periods, PbhQuotaTracker},
+    simulation::BundleSimulator,
 };
 use alloy_eips::BlockId;
 use alloy_primitives::Address;
 use alloy_sol_types::{SolCall, SolValue};
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
-use reth::transaction_pool::{
-    validate::ValidTransaction, TransactionOrigin, TransactionValidationOutcome,
-    TransactionValidator,
+use reth::{
+    chainspec::EthChainSpec,
+    transaction_pool::{
+        validate::ValidTransaction, TransactionOrigin, TransactionValidationOutcome,
+        TransactionValidator,
+    },
 };
 use reth_optimism_forks::OpHardforks;
 use reth_optimism_node::txpool::OpTransactionValidator;
 use reth_optimism_primitives::OpTransactionSigned;
 use reth_primitives::{Block, SealedBlock};
-use reth_provider::{BlockReaderIdExt, ChainSpecProvider, StateProviderFactory};
+use reth_provider::{
+    BlockReaderIdExt, ChainSpecProvider, ProviderResult, StateProvider, StateProviderFactory,
+};
 use revm_primitives::U256;
 use tracing::{info, warn};
-use world_chain_pbh::payload::{PBHPayload as PbhPayload, PBHValidationError};
+use world_chain_pbh::{
+    abi::PBHPayload,
+    clock::{Clock, SystemClock},
+    external_nullifier::Prefix,
+    payload::{PBHBatchValidationError, PBHPayload as PbhPayload, PBHValidationError},
+};
 
 /// The slot of the `pbh_gas_limit` in the PBHEntryPoint contract.
 pub const PBH_GAS_LIMIT_SLOT: U256 = U256::from_limbs([53, 0, 0, 0]);
@@ -42,6 +56,27 @@ pub const PBH_NONCE_LIMIT_OFFSET: u32 = 160;
 /// Max u16
 pub const MAX_U16: U256 = U256::from_limbs([0xFFFF, 0, 0, 0]);
 
+/// Reads the maximum number of PBH transactions a single World ID can execute in a period from
+/// the PBHEntryPoint at `pbh_entrypoint`.
+pub fn pbh_nonce_limit(state: &impl StateProvider, pbh_entrypoint: Address) -> ProviderResult<u16> {
+    // The `num_pbh_txs` storage is in a packed slot at a 160 bit offset consuming 16 bits.
+    Ok(((state
+        .storage(pbh_entrypoint, PBH_NONCE_LIMIT_SLOT.into())?
+        .unwrap_or_default()
+        >> PBH_NONCE_LIMIT_OFFSET)
+        & MAX_U16)
+        .to())
+}
+
+/// Reads the maximum amount of gas a single PBH transaction can consume from the PBHEntryPoint
+/// at `pbh_entrypoint`.
+pub fn pbh_gas_limit(state: &impl StateProvider, pbh_entrypoint: Address) -> ProviderResult<u64> {
+    Ok(state
+        .storage(pbh_entrypoint, PBH_GAS_LIMIT_SLOT.into())?
+        .unwrap_or_default()
+        .saturating_to())
+}
+
 /// Validator for World Chain transactions.
 #[derive(Debug, Clone)]
 pub struct WorldChainTransactionValidator<Client, Tx>
@@ -52,68 +87,243 @@ where
     inner: OpTransactionValidator<Client, Tx>,
     /// Validates World ID proofs contain a valid root in the WorldID account.
     root_validator: WorldChainRootValidator<Client>,
-    /// The maximum number of PBH transactions a single World ID can execute in a given month.
-    max_pbh_nonce: Arc<AtomicU16>,
-    /// The maximum amount of gas a single PBH transaction can consume.
-    max_pbh_gas_limit: Arc<AtomicU64>,
-    /// The address of the entrypoint for all PBH transactions.
-    pbh_entrypoint: Address,
-    /// The address of the World ID PBH signature aggregator.
-    pbh_signature_aggregator: Address,
+    /// The PBH nonce and gas limits configured on each PBHEntryPoint.
+    pbh_limits: HashMap<Address, Arc<PbhLimits>>,
+    /// The PBHEntryPoints accepting PBH transactions, along with their signature aggregators.
+    pbh_entrypoints: PbhEntrypoints,
+    /// The time source used to validate the external nullifier period of PBH payloads.
+    clock: Arc<dyn Clock>,
+    /// The duration after a period rollover during which PBH payloads for the previous
+    /// period are still accepted.
+    pbh_grace_period: Duration,
+    /// Whether PBH payloads with a [`Prefix::V2`] external nullifier are accepted.
+    external_nullifier_v2: bool,
+    /// Cache of PBH proofs which have already been verified.
+    proof_cache: Arc<ProofCache>,
+    /// Index of the nullifier hashes carried by pooled transactions.
+    nullifier_index: Arc<NullifierIndex>,
+    /// Accounts pooled PBH transactions against the PBH admission quotas.
+    quota_tracker: Arc<PbhQuotaTracker>,
+    /// The derivation of the signal of the PBH payload of each UserOperation, by entrypoint.
+    user_op_signals: HashMap<Address, UserOpSignal>,
+    /// Simulates PBH bundles before admitting them, if enabled.
+    bundle_simulator: Option<Arc<dyn BundleSimulator<Tx>>>,
 }
 
 impl<Client, Tx> WorldChainTransactionValidator<Client, Tx>
 where
-    Client: ChainSpecProvider<ChainSpec: OpHardforks>
+    Client: ChainSpecProvider<ChainSpec: EthChainSpec + OpHardforks>
         + StateProviderFactory
         + BlockReaderIdExt<Block = reth_primitives::Block<OpTransactionSigned>>,
     Tx: WorldChainPoolTransaction,
 {
-    /// Create a new [`WorldChainTransactionValidator`].
+    /// Create a new [`WorldChainTransactionValidator`] accepting PBH transactions to a single
+    /// PBHEntryPoint.
     pub fn new(
         inner: OpTransactionValidator<Client, Tx>,
         root_validator: WorldChainRootValidator<Client>,
         pbh_entrypoint: Address,
         pbh_signature_aggregator: Address,
     ) -> Result<Self, WorldChainTransactionPoolError> {
-        let state = inner.client().state_by_block_id(BlockId::latest())?;
-        // The `num_pbh_txs` storage is in a packed slot at a 160 bit offset consuming 16 bits.
-        let max_pbh_nonce: u16 = ((state
-            .storage(pbh_entrypoint, PBH_NONCE_LIMIT_SLOT.into())?
-            .unwrap_or_default()
-            >> PBH_NONCE_LIMIT_OFFSET)
-            & MAX_U16)
-            .to();
-        let max_pbh_gas_limit: u64 = state
-            .storage(pbh_entrypoint, PBH_GAS_LIMIT_SLOT.into())?
-            .unwrap_or_default()
-            .to();
+        Self::with_entrypoints(
+            inner,
+            root_validator,
+            PbhEntrypoints::single(pbh_entrypoint, pbh_signature_aggregator),
+        )
+    }
 
-        if max_pbh_nonce == 0 && max_pbh_gas_limit == 0 {
-            warn!(
-                %pbh_entrypoint,
-                %pbh_signature_aggregator,
-                "WorldChainTransactionValidator Initialized with PBH Disabled - Failed to fetch PBH nonce and gas limit from PBHEntryPoint. Defaulting to 0."
-            )
-        } else {
-            info!(
-                %max_pbh_gas_limit,
-                %max_pbh_nonce,
-                %pbh_entrypoint,
-                %pbh_signature_aggregator,
-                "WorldChainTransactionValidator Initialized with PBH Enabled"
-            )
+    /// Create a new [`WorldChainTransactionValidator`] accepting PBH transactions to each of the
+    /// `pbh_entrypoints` once they are active.
+    pub fn with_entrypoints(
+        inner: OpTransactionValidator<Client, Tx>,
+        root_validator: WorldChainRootValidator<Client>,
+        pbh_entrypoints: PbhEntrypoints,
+    ) -> Result<Self, WorldChainTransactionPoolError> {
+        let state = inner.client().state_by_block_id(BlockId::latest())?;
+        let chain_id = inner.client().chain_spec().chain_id();
+        let mut pbh_limits = HashMap::new();
+        let mut user_op_signals = HashMap::new();
+        for entrypoint in pbh_entrypoints.iter() {
+            let PbhEntrypoint {
+                entrypoint: pbh_entrypoint,
+                signature_aggregator: pbh_signature_aggregator,
+                activation_timestamp,
+                signal_version,
+                ..
+            } = *entrypoint;
+            let user_op_signal = entrypoint.user_op_signal(chain_id).ok_or_else(|| {
+                WorldChainTransactionPoolError::Initialization(format!(
+                    "unsupported signal version {signal_version} of {pbh_entrypoint}"
+                ))
+            })?;
+            user_op_signals.insert(pbh_entrypoint, user_op_signal);
+
+            let limits = PbhLimits::from_state(&state, pbh_entrypoint)?;
+            let max_pbh_nonce = limits.nonce_limit();
+            let max_pbh_gas_limit = limits.gas_limit();
+
+            if max_pbh_nonce == 0 && max_pbh_gas_limit == 0 {
+                warn!(
+                    %pbh_entrypoint,
+                    %pbh_signature_aggregator,
+                    %activation_timestamp,
+                    "WorldChainTransactionValidator Initialized with PBH Disabled - Failed to fetch PBH nonce and gas limit from PBHEntryPoint. Defaulting to 0."
+                )
+            } else {
+                info!(
+                    %max_pbh_gas_limit,
+                    %max_pbh_nonce,
+                    %pbh_entrypoint,
+                    %pbh_signature_aggregator,
+                    %activation_timestamp,
+                    %signal_version,
+                    "WorldChainTransactionValidator Initialized with PBH Enabled"
+                )
+            }
+            pbh_limits.insert(pbh_entrypoint, Arc::new(limits));
         }
+
         Ok(Self {
             inner,
             root_validator,
-            max_pbh_nonce: Arc::new(AtomicU16::new(max_pbh_nonce)),
-            max_pbh_gas_limit: Arc::new(AtomicU64::new(max_pbh_gas_limit)),
-            pbh_entrypoint,
-            pbh_signature_aggregator,
+            pbh_limits,
+            pbh_entrypoints,
+            clock: Arc::new(SystemClock),
+            pbh_grace_period: Duration::ZERO,
+            external_nullifier_v2: false,
+            proof_cache: Arc::new(ProofCache::default()),
+            nullifier_index: Arc::new(NullifierIndex::default()),
+            quota_tracker: Arc::new(PbhQuotaTracker::default()),
+            user_op_signals,
+            bundle_simulator: None,
         })
     }
 
+    /// Sets the [`Clock`] used to validate the external nullifier period of PBH payloads.
+    ///
+    /// Defaults to [`SystemClock`].
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the grace period after a period rollover during which PBH payloads with an
+    /// external nullifier for the previous period are still accepted.
+    ///
+    /// Defaults to [`Duration::ZERO`].
+    pub fn with_pbh_grace_period(mut self, pbh_grace_period: Duration) -> Self {
+        self.pbh_grace_period = pbh_grace_period;
+        self
+    }
+
+    /// Sets whether PBH payloads with a [`Prefix::V2`] external nullifier are accepted.
+    ///
+    /// This must only be enabled once the PBHEntryPoint verifies V2 external nullifiers, as the
+    /// bundles carrying them revert on chain otherwise. Defaults to `false`.
+    pub fn with_external_nullifier_v2(mut self, external_nullifier_v2: bool) -> Self {
+        self.external_nullifier_v2 = external_nullifier_v2;
+        self
+    }
+
+    /// Sets the [`ProofCache`] used to skip the verification of previously verified proofs.
+    ///
+    /// Defaults to a cache of [`DEFAULT_PROOF_CACHE_SIZE`](crate::proof_cache::DEFAULT_PROOF_CACHE_SIZE)
+    /// proofs.
+    pub fn with_proof_cache(mut self, proof_cache: ProofCache) -> Self {
+        self.proof_cache = Arc::new(proof_cache);
+        self
+    }
+
+    /// Sets the [`NullifierIndex`] used to reject transactions reusing the nullifier hash of
+    /// another pooled transaction.
+    ///
+    /// The index must be kept in sync with the pool through
+    /// [`maintain_nullifier_index`](crate::nullifier_index::maintain_nullifier_index).
+    pub fn with_nullifier_index(mut self, nullifier_index: Arc<NullifierIndex>) -> Self {
+        self.nullifier_index = nullifier_index;
+        self
+    }
+
+    /// Returns the [`NullifierIndex`] of the validator.
+    pub fn nullifier_index(&self) -> &Arc<NullifierIndex> {
+        &self.nullifier_index
+    }
+
+    /// Sets the [`PbhQuotaTracker`] used to enforce the PBH admission quotas.
+    ///
+    /// The tracker must be kept in sync with the pool through
+    /// [`maintain_pbh_quotas`](crate::quota::maintain_pbh_quotas). Defaults to a tracker
+    /// without quotas.
+    pub fn with_quota_tracker(mut self, quota_tracker: Arc<PbhQuotaTracker>) -> Self {
+        self.quota_tracker = quota_tracker;
+        self
+    }
+
+    /// Returns the [`PbhQuotaTracker`] of the validator.
+    pub fn quota_tracker(&self) -> &Arc<PbhQuotaTracker> {
+        &self.quota_tracker
+    }
+
+    /// Sets the [`BundleSimulator`] used to reject PBH bundles which would fail on chain.
+    ///
+    /// Bundles are only simulated once all other checks passed. Defaults to no simulation.
+    pub fn with_bundle_simulator(
+        mut self,
+        bundle_simulator: impl BundleSimulator<Tx> + 'static,
+    ) -> Self {
+        self.bundle_simulator = Some(Arc::new(bundle_simulator));
+        self
+    }
+
+    /// Sets the [`PbhLimits`] enforced on PBH transactions to `pbh_entrypoint`.
+    ///
+    /// The limits must be kept in sync with the chain through
+    /// [`maintain_pbh_limits`](crate::limits::maintain_pbh_limits). Defaults to the limits read
+    /// from the PBHEntryPoint on creation.
+    pub fn with_pbh_limits(mut self, pbh_entrypoint: Address, pbh_limits: Arc<PbhLimits>) -> Self {
+        self.pbh_limits.insert(pbh_entrypoint, pbh_limits);
+        self
+    }
+
+    /// Returns the [`PbhLimits`] enforced on PBH transactions to `pbh_entrypoint`.
+    pub fn pbh_limits(&self, pbh_entrypoint: Address) -> Option<&Arc<PbhLimits>> {
+        self.pbh_limits.get(&pbh_entrypoint)
+    }
+
+    /// Returns the PBHEntryPoints accepting PBH transactions.
+    pub fn pbh_entrypoints(&self) -> &PbhEntrypoints {
+        &self.pbh_entrypoints
+    }
+
+    /// Returns the [`UserOpSignal`] derived by `getSignalHash` on `entrypoint`.
+    fn user_op_signal(&self, entrypoint: &PbhEntrypoint) -> UserOpSignal {
+        self.user_op_signals
+            .get(&entrypoint.entrypoint)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the [`PbhLimits`] of `entrypoint`, or limits rejecting every PBH transaction if
+    /// the limits of the entrypoint are unknown.
+    fn limits(&self, entrypoint: &PbhEntrypoint) -> &PbhLimits {
+        static DISABLED: PbhLimits = PbhLimits::new(0, 0);
+        self.pbh_limits
+            .get(&entrypoint.entrypoint)
+            .map_or(&DISABLED, Arc::as_ref)
+    }
+
+    /// Ensures the version of the external nullifier of `payload` is accepted.
+    fn validate_external_nullifier_version(
+        &self,
+        payload: &PbhPayload,
+    ) -> Result<(), PBHValidationError> {
+        let version = payload.external_nullifier.version;
+        if version == Prefix::V2 && !self.external_nullifier_v2 {
+            return Err(PBHValidationError::UnsupportedExternalNullifierVersion { version });
+        }
+        Ok(())
+    }
+
     /// Get a reference to the inner transaction validator.
     pub fn inner(&self) -> &OpTransactionValidator<Client, Tx> {
         &self.inner
@@ -126,9 +336,10 @@ where
         &self,
         origin: TransactionOrigin,
         tx: Tx,
+        entrypoint: &PbhEntrypoint,
     ) -> TransactionValidationOutcome<Tx> {
         // Ensure that the tx is a valid OP transaction and return early if invalid
-        let mut tx_outcome = self.inner.validate_one(origin, tx.clone()).await;
+        let tx_outcome = self.inner.validate_one(origin, tx.clone()).await;
         if !tx_outcome.is_valid() {
             return tx_outcome;
         }
@@ -139,22 +350,25 @@ where
                 .to_outcome(tx);
         };
 
-        if !calldata
+        if let Some(aggregated_ops) = calldata
             ._0
             .iter()
-            .all(|aggregator| aggregator.aggregator == self.pbh_signature_aggregator)
+            .find(|aggregated_ops| aggregated_ops.aggregator != entrypoint.signature_aggregator)
         {
             return WorldChainPoolTransactionError::from(
-                PBHValidationError::InvalidSignatureAggregator,
+                PBHValidationError::InvalidSignatureAggregator {
+                    aggregator: aggregated_ops.aggregator,
+                },
             )
             .to_outcome(tx);
         }
 
-        // Validate all proofs associated with each UserOp
+        // Decode the PBH payloads associated with each UserOp
+        let user_op_signal = self.user_op_signal(entrypoint);
         let mut aggregated_payloads = vec![];
-        let mut seen_nullifier_hashes = HashSet::new();
+        let mut signals = vec![];
 
-        for aggregated_ops in calldata._0 {
+        for aggregated_ops in &calldata._0 {
             let buff = aggregated_ops.signature.as_ref();
             let pbh_payloads = match <Vec<PBHPayload>>::abi_decode(buff) {
                 Ok(pbh_payloads) => pbh_payloads,
@@ -167,44 +381,215 @@ where
             };
 
             if pbh_payloads.len() != aggregated_ops.userOps.len() {
-                return WorldChainPoolTransactionError::from(PBHValidationError::MissingPbhPayload)
+                return WorldChainPoolTransactionError::from(
+                    PBHValidationError::MissingPbhPayload {
+                        user_ops: aggregated_ops.userOps.len(),
+                        payloads: pbh_payloads.len(),
+                    },
+                )
+                .to_outcome(tx);
+            }
+
+            for (payload, op) in pbh_payloads.into_iter().zip(&aggregated_ops.userOps) {
+                let Ok(payload) = PbhPayload::try_from(payload) else {
+                    return WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                        index: aggregated_payloads.len(),
+                        error: PBHValidationError::InvalidCalldata,
+                    })
                     .to_outcome(tx);
+                };
+                aggregated_payloads.push(payload);
+                signals.push(user_op_signal.signal(op));
             }
+        }
+
+        let tx_outcome =
+            self.validate_pbh_payloads(tx, tx_outcome, entrypoint, aggregated_payloads, signals);
+        self.simulate_pbh_bundle(tx_outcome, &calldata._0)
+    }
+
+    /// Simulates a valid PBH bundle with the [`BundleSimulator`], if one is configured.
+    fn simulate_pbh_bundle(
+        &self,
+        tx_outcome: TransactionValidationOutcome<Tx>,
+        bundle: &[UserOpsPerAggregator],
+    ) -> TransactionValidationOutcome<Tx> {
+        let Some(bundle_simulator) = &self.bundle_simulator else {
+            return tx_outcome;
+        };
+        let TransactionValidationOutcome::Valid {
+            transaction: ValidTransaction::Valid(tx),
+            ..
+        } = &tx_outcome
+        else {
+            return tx_outcome;
+        };
+
+        match bundle_simulator.simulate(tx, bundle) {
+            Ok(()) => tx_outcome,
+            Err(err) => WorldChainPoolTransactionError::from(err).to_outcome(tx.clone()),
+        }
+    }
+
+    /// Validates a PBH multicall transaction, which carries a single PBH payload for the calls
+    /// of an EOA.
+    ///
+    /// If the transaction is valid marks it for priority inclusion
+    pub async fn validate_pbh_multicall(
+        &self,
+        origin: TransactionOrigin,
+        tx: Tx,
+        entrypoint: &PbhEntrypoint,
+    ) -> TransactionValidationOutcome<Tx> {
+        // Ensure that the tx is a valid OP transaction and return early if invalid
+        let tx_outcome = self.inner.validate_one(origin, tx.clone()).await;
+        if !tx_outcome.is_valid() {
+            return tx_outcome;
+        }
+
+        let Ok(calldata) = IPBHEntryPoint::pbhMulticallCall::abi_decode(tx.input()) else {
+            return WorldChainPoolTransactionError::from(PBHValidationError::InvalidCalldata)
+                .to_outcome(tx);
+        };
+        let Ok(payload) = PbhPayload::try_from(calldata.payload) else {
+            return WorldChainPoolTransactionError::from(PBHValidationError::InvalidCalldata)
+                .to_outcome(tx);
+        };
+        // The PBHEntryPoint refuses to call itself
+        if calldata
+            .calls
+            .iter()
+            .any(|call| call.target == entrypoint.entrypoint)
+        {
+            return WorldChainPoolTransactionError::from(PBHValidationError::InvalidCalldata)
+                .to_outcome(tx);
+        }
+        let signal = crate::multicall::hash_pbh_multicall(tx.sender(), &calldata.calls);
+
+        self.validate_pbh_payloads(tx, tx_outcome, entrypoint, vec![payload], vec![signal])
+    }
 
-            let valid_roots = self.root_validator.roots();
-
-            let payloads: Vec<PbhPayload> = match pbh_payloads
-                .into_par_iter()
-                .zip(aggregated_ops.userOps)
-                .map(|(payload, op)| {
-                    let signal = crate::eip4337::hash_user_op(&op);
-                    let Ok(payload) = PbhPayload::try_from(payload) else {
-                        return Err(PBHValidationError::InvalidCalldata.into());
-                    };
-                    payload.validate(
-                        signal,
-                        &valid_roots,
-                        self.max_pbh_nonce.load(Ordering::Relaxed),
-                    )?;
-                    Ok::<PbhPayload, WorldChainPoolTransactionError>(payload)
+    /// Validates the PBH payloads of a transaction, each paired with the signal it was
+    /// generated for, and attaches them to the transaction if they are valid.
+    ///
+    /// `tx_outcome` must be the valid outcome of the inner validator. Errors carry the index of
+    /// the offending payload, which is always zero for PBH multicall transactions.
+    fn validate_pbh_payloads(
+        &self,
+        tx: Tx,
+        mut tx_outcome: TransactionValidationOutcome<Tx>,
+        entrypoint: &PbhEntrypoint,
+        aggregated_payloads: Vec<PbhPayload>,
+        signals: Vec<U256>,
+    ) -> TransactionValidationOutcome<Tx> {
+        // Validate the root and external nullifier of every payload
+        let valid_roots = self.root_validator.valid_roots();
+        let now = self.clock.now();
+        let pbh_nonce_limit = self.limits(entrypoint).nonce_limit();
+        for (index, payload) in aggregated_payloads.iter().enumerate() {
+            if let Err(error) = self
+                .validate_external_nullifier_version(payload)
+                .and_then(|_| payload.validate_root(&*valid_roots))
+                .and_then(|_| {
+                    payload.validate_external_nullifier(now, pbh_nonce_limit, self.pbh_grace_period)
                 })
-                .collect::<Result<Vec<PbhPayload>, WorldChainPoolTransactionError>>()
             {
-                Ok(payloads) => payloads,
-                Err(err) => return err.to_outcome(tx),
-            };
+                return WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                    index,
+                    error,
+                })
+                .to_outcome(tx);
+            }
+        }
 
-            // Now check for duplicate nullifier_hashes
-            for payload in &payloads {
-                if !seen_nullifier_hashes.insert(payload.nullifier_hash) {
-                    return WorldChainPoolTransactionError::from(
-                        PBHValidationError::DuplicateNullifierHash,
-                    )
-                    .to_outcome(tx);
+        // Reject nullifier hashes which have already been spent on any of the entrypoints
+        let state = match self.inner.client().state_by_block_id(BlockId::latest()) {
+            Ok(state) => state,
+            Err(err) => return TransactionValidationOutcome::Error(*tx.hash(), Box::new(err)),
+        };
+        for (index, payload) in aggregated_payloads.iter().enumerate() {
+            match nullifier_hash_spent_block(&state, &self.pbh_entrypoints, payload.nullifier_hash)
+            {
+                Ok(None) => {}
+                Ok(Some(block_number)) => {
+                    return WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                        index,
+                        error: PBHValidationError::SpentNullifierHash {
+                            nullifier_hash: payload.nullifier_hash,
+                            block_number,
+                        },
+                    })
+                    .to_outcome(tx)
                 }
+                Err(err) => return TransactionValidationOutcome::Error(*tx.hash(), Box::new(err)),
+            }
+        }
+
+        // Verify all proofs which have not been verified before at once
+        let (indices, unverified): (Vec<_>, Vec<_>) = aggregated_payloads
+            .iter()
+            .zip(signals)
+            .enumerate()
+            .map(|(index, (payload, signal))| {
+                (
+                    index,
+                    ((payload, signal), ProofCacheKey::new(payload, signal)),
+                )
+            })
+            .filter(|(_, (_, key))| !self.proof_cache.contains(key))
+            .unzip();
+        if let Err(err) = PbhPayload::verify_batch(unverified.iter().map(|(payload, _)| *payload)) {
+            // The batch index refers to the unverified payloads only
+            return WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                index: indices[err.index],
+                error: err.error,
+            })
+            .to_outcome(tx);
+        }
+        for (_, key) in unverified {
+            self.proof_cache.insert(key);
+        }
+
+        // Now check for duplicate nullifier_hashes
+        let mut seen_nullifier_hashes = HashSet::new();
+        for (index, payload) in aggregated_payloads.iter().enumerate() {
+            if !seen_nullifier_hashes.insert(payload.nullifier_hash) {
+                return WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                    index,
+                    error: PBHValidationError::DuplicateNullifierHash {
+                        nullifier_hash: payload.nullifier_hash,
+                    },
+                })
+                .to_outcome(tx);
             }
+        }
 
-            aggregated_payloads.extend(payloads);
+        // Check for nullifier hashes claimed by other pooled transactions
+        if let Some((index, claim)) = self.nullifier_index.conflict(
+            *tx.hash(),
+            tx.priority_fee_or_price(),
+            aggregated_payloads
+                .iter()
+                .map(|payload| &payload.nullifier_hash),
+        ) {
+            return WorldChainPoolTransactionError::from(PBHBatchValidationError {
+                index,
+                error: PBHValidationError::PooledNullifierHash {
+                    nullifier_hash: aggregated_payloads[index].nullifier_hash,
+                    tx_hash: claim.tx_hash,
+                },
+            })
+            .to_outcome(tx);
+        }
+
+        // Enforce the PBH admission quotas
+        if let Err(err) = self.quota_tracker.check(
+            tx.hash(),
+            tx.sender(),
+            tx.priority_fee_or_price(),
+            &payload_periods(&aggregated_payloads),
+        ) {
+            return WorldChainPoolTransactionError::from(err).to_outcome(tx);
         }
 
         if let TransactionValidationOutcome::Valid {
@@ -222,30 +607,40 @@ where
         &self,
         origin: TransactionOrigin,
         tx: Tx,
+        entrypoint: &PbhEntrypoint,
     ) -> TransactionValidationOutcome<Tx> {
-        if tx.gas_limit() > self.max_pbh_gas_limit.load(Ordering::Relaxed) {
-            return WorldChainPoolTransactionError::from(PBHValidationError::PbhGasLimitExceeded)
-                .to_outcome(tx);
+        let max_pbh_gas_limit = self.limits(entrypoint).gas_limit();
+        if tx.gas_limit() > max_pbh_gas_limit {
+            return WorldChainPoolTransactionError::from(PBHValidationError::PbhGasLimitExceeded {
+                gas_limit: tx.gas_limit(),
+                limit: max_pbh_gas_limit,
+            })
+            .to_outcome(tx);
         }
 
-        let function_signature: [u8; 4] = tx
-            .input()
-            .get(..4)
-            .and_then(|bytes| bytes.try_into().ok())
-            .unwrap_or_default();
-
-        match function_signature {
+        match function_selector(tx.input()) {
             IPBHEntryPoint::handleAggregatedOpsCall::SELECTOR => {
-                self.validate_pbh_bundle(origin, tx).await
+                self.validate_pbh_bundle(origin, tx, entrypoint).await
+            }
+            IPBHEntryPoint::pbhMulticallCall::SELECTOR => {
+                self.validate_pbh_multicall(origin, tx, entrypoint).await
             }
             _ => self.inner.validate_one(origin, tx.clone()).await,
         }
     }
 }
 
+/// Returns the function selector of the calldata `input`, or zero if it is too short.
+fn function_selector(input: &[u8]) -> [u8; 4] {
+    input
+        .get(..4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_default()
+}
+
 impl<Client, Tx> TransactionValidator for WorldChainTransactionValidator<Client, Tx>
 where
-    Client: ChainSpecProvider<ChainSpec: OpHardforks>
+    Client: ChainSpecProvider<ChainSpec: EthChainSpec + OpHardforks>
         + StateProviderFactory
         + BlockReaderIdExt<Block = Block<OpTransactionSigned>>,
     Tx: WorldChainPoolTransaction<Consensus = OpTransactionSigned>,
@@ -257,38 +652,36 @@ where
         origin: TransactionOrigin,
         transaction: Self::Transaction,
     ) -> TransactionValidationOutcome<Self::Transaction> {
-        if transaction.to().unwrap_or_default() != self.pbh_entrypoint {
+        let now = self.clock.now().timestamp().max(0) as u64;
+        let Some(to) = transaction
+            .to()
+            .filter(|to| self.pbh_entrypoints.contains(*to))
+        else {
             return self.inner.validate_one(origin, transaction.clone()).await;
-        }
+        };
+        let Some(entrypoint) = self.pbh_entrypoints.active(to, now).copied() else {
+            // The builder only spends the nullifier hashes of transactions validated as PBH, so
+            // PBH calls to an inactive entrypoint could reuse them indefinitely
+            if matches!(
+                function_selector(transaction.input()),
+                IPBHEntryPoint::handleAggregatedOpsCall::SELECTOR
+                    | IPBHEntryPoint::pbhMulticallCall::SELECTOR
+            ) {
+                return WorldChainPoolTransactionError::from(
+                    PBHValidationError::InactiveEntrypoint { entrypoint: to },
+                )
+                .to_outcome(transaction);
+            }
+            return self.inner.validate_one(origin, transaction.clone()).await;
+        };
 
-        self.validate_pbh(origin, transaction).await
+        self.validate_pbh(origin, transaction, &entrypoint).await
     }
 
     fn on_new_head_block<B>(&self, new_tip_block: &SealedBlock<B>)
     where
         B: reth_primitives_traits::Block,
     {
-        // Try and fetch the max pbh nonce and gas limit from the state at the latest block
-        if let Ok(state) = self.inner.client().state_by_block_id(BlockId::latest()) {
-            if let Some(max_pbh_nonce) = state
-                .storage(self.pbh_entrypoint, PBH_NONCE_LIMIT_SLOT.into())
-                .ok()
-                .flatten()
-            {
-                let max_pbh_nonce = (max_pbh_nonce >> PBH_NONCE_LIMIT_OFFSET) & MAX_U16;
-                self.max_pbh_nonce
-                    .store(max_pbh_nonce.to(), Ordering::Relaxed);
-            }
-
-            if let Some(max_pbh_gas_limit) = state
-                .storage(self.pbh_entrypoint, PBH_GAS_LIMIT_SLOT.into())
-                .ok()
-                .flatten()
-            {
-                self.max_pbh_gas_limit
-                    .store(max_pbh_gas_limit.to(), Ordering::Relaxed);
-            }
-        }
         self.inner.on_new_head_block(new_tip_block);
         self.root_validator.on_new_block(new_tip_block);
     }
@@ -299,12 +692,18 @@ pub mod tests {
     use alloy_consensus::{Block, Header};
     use alloy_primitives::{address, Address};
     use alloy_sol_types::SolCall;
+    use chrono::TimeZone;
     use reth::transaction_pool::{
         blobstore::InMemoryBlobStore, Pool, TransactionPool, TransactionValidator,
     };
     use reth_optimism_primitives::OpTransactionSigned;
     use reth_primitives::{BlockBody, SealedBlock};
-    use world_chain_pbh::{date_marker::DateMarker, external_nullifier::ExternalNullifier};
+    use std::time::Duration;
+    use world_chain_pbh::{
+        clock::FixedClock,
+        date_marker::DateMarker,
+        external_nullifier::{ExternalNullifier, Period},
+    };
     use world_chain_test::{
         utils::{account, eip1559, eth_tx, pbh_bundle, pbh_multicall, user_op, TREE},
         PBH_DEV_ENTRYPOINT,
@@ -314,7 +713,11 @@ pub mod tests {
     const DEV_WORLD_ID: Address = address!("5FbDB2315678afecb367f032d93F642f64180aa3");
 
     use crate::{
-        ordering::WorldChainOrdering, root::LATEST_ROOT_SLOT, tx::WorldChainPooledTransaction,
+        bindings::IMulticall3::Call3,
+        entrypoint::{PbhEntrypoint, PbhEntrypoints},
+        ordering::{PriorityPolicy, WorldChainOrdering},
+        root::LATEST_ROOT_SLOT,
+        tx::{WorldChainPoolTransaction, WorldChainPooledTransaction},
     };
     use world_chain_test::mock::{ExtendedAccount, MockEthProvider};
 
@@ -325,7 +728,18 @@ pub mod tests {
         address!("Cf7Ed3AccA5a467e9e704C703E8D87F634fB0Fc9");
 
     /// Create a World Chain validator for testing
-    fn world_chain_validator(
+    pub(crate) fn world_chain_validator(
+    ) -> WorldChainTransactionValidator<MockEthProvider, WorldChainPooledTransaction> {
+        world_chain_validator_with_entrypoints(PbhEntrypoints::single(
+            PBH_DEV_ENTRYPOINT,
+            PBH_DEV_SIGNATURE_AGGREGATOR,
+        ))
+    }
+
+    /// Create a World Chain validator for testing which accepts PBH transactions to each of the
+    /// `pbh_entrypoints`
+    fn world_chain_validator_with_entrypoints(
+        pbh_entrypoints: PbhEntrypoints,
     ) -> WorldChainTransactionValidator<MockEthProvider, WorldChainPooledTransaction> {
         use super::{MAX_U16, PBH_GAS_LIMIT_SLOT, PBH_NONCE_LIMIT_SLOT};
         use crate::root::WorldChainRootValidator;
@@ -343,23 +757,20 @@ pub mod tests {
             .build(InMemoryBlobStore::default());
         let validator = OpTransactionValidator::new(validator).require_l1_data_gas_fee(false);
         let root_validator = WorldChainRootValidator::new(client, DEV_WORLD_ID).unwrap();
-        validator.client().add_account(
-            PBH_DEV_ENTRYPOINT,
-            ExtendedAccount::new(0, alloy_primitives::U256::ZERO).extend_storage(vec![
-                (PBH_GAS_LIMIT_SLOT.into(), U256::from(15000000)),
-                (
-                    PBH_NONCE_LIMIT_SLOT.into(),
-                    ((MAX_U16 - U256::from(1)) << U256::from(160)),
-                ),
-            ]),
-        );
-        WorldChainTransactionValidator::new(
-            validator,
-            root_validator,
-            PBH_DEV_ENTRYPOINT,
-            PBH_DEV_SIGNATURE_AGGREGATOR,
-        )
-        .expect("failed to create world chain validator")
+        for entrypoint in pbh_entrypoints.iter() {
+            validator.client().add_account(
+                entrypoint.entrypoint,
+                ExtendedAccount::new(0, alloy_primitives::U256::ZERO).extend_storage(vec![
+                    (PBH_GAS_LIMIT_SLOT.into(), U256::from(15000000)),
+                    (
+                        PBH_NONCE_LIMIT_SLOT.into(),
+                        ((MAX_U16 - U256::from(1)) << U256::from(160)),
+                    ),
+                ]),
+            );
+        }
+        WorldChainTransactionValidator::with_entrypoints(validator, root_validator, pbh_entrypoints)
+            .expect("failed to create world chain validator")
     }
 
     async fn setup() -> Pool<
@@ -367,8 +778,31 @@ pub mod tests {
         WorldChainOrdering<WorldChainPooledTransaction>,
         InMemoryBlobStore,
     > {
-        let validator = world_chain_validator();
+        setup_with_validator(world_chain_validator()).await
+    }
+
+    pub(crate) async fn setup_with_validator(
+        validator: WorldChainTransactionValidator<MockEthProvider, WorldChainPooledTransaction>,
+    ) -> Pool<
+        WorldChainTransactionValidator<MockEthProvider, WorldChainPooledTransaction>,
+        WorldChainOrdering<WorldChainPooledTransaction>,
+        InMemoryBlobStore,
+    > {
+        setup_with_ordering(validator, WorldChainOrdering::default()).await
+    }
 
+    /// Creates a pool ordering transactions by `ordering`
+    pub(crate) async fn setup_with_ordering<P>(
+        validator: WorldChainTransactionValidator<MockEthProvider, WorldChainPooledTransaction>,
+        ordering: WorldChainOrdering<WorldChainPooledTransaction, P>,
+    ) -> Pool<
+        WorldChainTransactionValidator<MockEthProvider, WorldChainPooledTransaction>,
+        WorldChainOrdering<WorldChainPooledTransaction, P>,
+        InMemoryBlobStore,
+    >
+    where
+        P: PriorityPolicy<WorldChainPooledTransaction>,
+    {
         // Fund 10 test accounts
         for acc in 0..10 {
             let account_address = account(acc);
@@ -399,8 +833,6 @@ pub mod tests {
         // Propogate the block to the root validator
         validator.on_new_head_block(&block);
 
-        let ordering = WorldChainOrdering::default();
-
         Pool::new(
             validator,
             ordering,
@@ -470,47 +902,77 @@ pub mod tests {
     }
 
     #[tokio::test]
-    async fn validate_pbh_bundle_duplicate_nullifier_hash() {
+    async fn validate_pbh_bundle_entrypoint_activation() {
         const BUNDLER_ACCOUNT: u32 = 9;
         const USER_ACCOUNT: u32 = 0;
+        const NEXT_ENTRYPOINT: Address = address!("0000000000000000000000000000000000000dd4");
+        const OLD_ENTRYPOINT: Address = address!("0000000000000000000000000000000000000dd5");
 
-        let pool = setup().await;
-
-        let (user_op, proof) = user_op()
-            .acc(USER_ACCOUNT)
-            .external_nullifier(ExternalNullifier::with_date_marker(
-                DateMarker::from(chrono::Utc::now()),
-                0,
-            ))
-            .call();
-
-        // Lets add two of the same userOp in the bundle so the nullifier hash is the same and we should expect an error
-        let bundle = pbh_bundle(
-            vec![user_op.clone(), user_op],
-            vec![proof.clone().into(), proof.into()],
-        );
-        let calldata = bundle.abi_encode();
-
-        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+        let now = chrono::Utc::now();
+        let activation = now.timestamp() as u64 + 3600;
+        let validator = world_chain_validator_with_entrypoints(PbhEntrypoints::new([
+            PbhEntrypoint::new(PBH_DEV_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR),
+            PbhEntrypoint::new(NEXT_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR)
+                .with_activation_timestamp(activation),
+            PbhEntrypoint::new(OLD_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR)
+                .with_deactivation_timestamp(now.timestamp() as u64 - 3600),
+        ]));
+        assert!(validator.pbh_limits(NEXT_ENTRYPOINT).is_some());
+        let pool = setup_with_validator(validator).await;
+
+        let bundle_to = |entrypoint: Address, acc: u32| {
+            let (user_op, proof) = user_op()
+                .acc(acc)
+                .external_nullifier(ExternalNullifier::with_date_marker(
+                    DateMarker::from(now),
+                    0,
+                ))
+                .call();
+            let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+            eip1559().to(entrypoint).input(bundle.abi_encode()).call()
+        };
 
-        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+        // PBH transactions to an entrypoint outside of its active window are rejected, as their
+        // nullifier hashes would never be spent
+        for entrypoint in [NEXT_ENTRYPOINT, OLD_ENTRYPOINT] {
+            let tx = eth_tx(BUNDLER_ACCOUNT, bundle_to(entrypoint, USER_ACCOUNT)).await;
+            let err = pool
+                .add_external_transaction(tx.into())
+                .await
+                .expect_err("Validation should fail because the entrypoint is not active");
+            assert!(err.to_string().contains("is not active"));
+        }
 
-        let res = pool
-            .add_external_transaction(tx.clone().into())
+        // Other calls to an inactive entrypoint are validated as regular transactions
+        let tx = eth_tx(BUNDLER_ACCOUNT, eip1559().to(OLD_ENTRYPOINT).call()).await;
+        pool.add_external_transaction(tx.into())
             .await
-            .expect_err("Failed to add transaction");
+            .expect("Failed to add transaction");
 
-        assert!(res.to_string().contains("Duplicate nullifier hash"),);
+        // The current entrypoint keeps accepting PBH transactions
+        let tx = eth_tx(
+            BUNDLER_ACCOUNT - 1,
+            bundle_to(PBH_DEV_ENTRYPOINT, USER_ACCOUNT + 1),
+        )
+        .await;
+        let hash = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect("Failed to add transaction")
+            .hash;
+        let pooled = pool.get(&hash).unwrap();
+        assert!(pooled.transaction.pbh_payload().is_some());
     }
 
     #[tokio::test]
-    async fn validate_bundle_no_pbh() {
-        const USER_ACCOUNT: u32 = 0;
+    async fn validate_pbh_bundle_nullifier_hash_spent_on_other_entrypoint() {
+        use crate::nullifier::nullifier_hash_slot;
 
-        let pool = setup().await;
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+        const NEXT_ENTRYPOINT: Address = address!("0000000000000000000000000000000000000dd4");
 
-        // NOTE: We're ignoring the proof here
-        let (user_op, _proof) = user_op()
+        let (user_op, proof) = user_op()
             .acc(USER_ACCOUNT)
             .external_nullifier(ExternalNullifier::with_date_marker(
                 DateMarker::from(chrono::Utc::now()),
@@ -518,38 +980,513 @@ pub mod tests {
             ))
             .call();
 
-        let bundle = pbh_bundle(vec![user_op], vec![]);
-
-        let calldata = bundle.abi_encode();
-
-        let tx = eip1559().to(Address::random()).input(calldata).call();
+        // The nullifier hash was spent on the next entrypoint in block 2
+        let validator = world_chain_validator_with_entrypoints(PbhEntrypoints::new([
+            PbhEntrypoint::new(PBH_DEV_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR),
+            PbhEntrypoint::new(NEXT_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR),
+        ]));
+        validator.inner().client().add_account(
+            NEXT_ENTRYPOINT,
+            ExtendedAccount::new(0, alloy_primitives::U256::ZERO).extend_storage(vec![(
+                nullifier_hash_slot(proof.nullifier_hash),
+                alloy_primitives::U256::from(2),
+            )]),
+        );
+        let pool = setup_with_validator(validator).await;
 
-        let tx = eth_tx(USER_ACCOUNT, tx).await;
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
 
-        pool.add_external_transaction(tx.clone().into())
+        let err = pool
+            .add_external_transaction(tx.into())
             .await
-            .expect(
-                "Validation should succeed - PBH data is invalid, but this is not a PBH bundle",
-            );
+            .expect_err("Validation should fail because of a spent nullifier hash");
+        assert!(err.to_string().contains("already spent in block 2"));
     }
 
     #[tokio::test]
-    async fn validate_pbh_bundle_missing_proof_for_user_op() {
+    async fn validate_pbh_bundle_signal_version() {
         const BUNDLER_ACCOUNT: u32 = 9;
         const USER_ACCOUNT: u32 = 0;
+        const NEXT_ENTRYPOINT: Address = address!("0000000000000000000000000000000000000dd4");
 
-        let pool = setup().await;
+        let validator = world_chain_validator_with_entrypoints(PbhEntrypoints::new([
+            PbhEntrypoint::new(PBH_DEV_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR),
+            PbhEntrypoint::new(NEXT_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR)
+                .with_signal_version(2),
+        ]));
+        let pool = setup_with_validator(validator).await;
 
-        // NOTE: We're ignoring the proof here
-        let (user_op, _proof) = user_op()
+        // The proof is generated for the V1 signal of the UserOp
+        let (user_op, proof) = user_op()
             .acc(USER_ACCOUNT)
             .external_nullifier(ExternalNullifier::with_date_marker(
                 DateMarker::from(chrono::Utc::now()),
                 0,
             ))
             .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
 
-        let bundle = pbh_bundle(vec![user_op], vec![]);
+        let tx = eip1559()
+            .to(NEXT_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+        let err = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect_err("Validation should fail because the entrypoint derives V2 signals");
+        assert!(err.to_string().contains("PBH payload 0: Invalid proof"));
+
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+        pool.add_external_transaction(tx.into())
+            .await
+            .expect("Failed to add transaction");
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_failing_simulation() {
+        use crate::simulation::{BundleSimulator, SimulationError};
+        use revm_primitives::U256;
+
+        #[derive(Debug)]
+        struct FailingSimulator;
+
+        impl<Tx> BundleSimulator<Tx> for FailingSimulator {
+            fn simulate(
+                &self,
+                _tx: &Tx,
+                _bundle: &[crate::bindings::IEntryPoint::UserOpsPerAggregator],
+            ) -> Result<(), SimulationError> {
+                Err(SimulationError::FailedOp {
+                    index: U256::ZERO,
+                    reason: "AA23 reverted".to_string(),
+                })
+            }
+        }
+
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let pool =
+            setup_with_validator(world_chain_validator().with_bundle_simulator(FailingSimulator))
+                .await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect_err("Simulation should reject the bundle");
+        assert!(err.to_string().contains("UserOp 0 failed: AA23 reverted"));
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_spent_nullifier_hash() {
+        use super::{MAX_U16, PBH_GAS_LIMIT_SLOT, PBH_NONCE_LIMIT_SLOT};
+        use crate::nullifier::nullifier_hash_slot;
+        use revm_primitives::U256;
+
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+
+        // Mark the nullifier hash as spent in block 1
+        let validator = world_chain_validator();
+        validator.inner().client().add_account(
+            PBH_DEV_ENTRYPOINT,
+            ExtendedAccount::new(0, alloy_primitives::U256::ZERO).extend_storage(vec![
+                (PBH_GAS_LIMIT_SLOT.into(), U256::from(15000000)),
+                (
+                    PBH_NONCE_LIMIT_SLOT.into(),
+                    ((MAX_U16 - U256::from(1)) << U256::from(160)),
+                ),
+                (nullifier_hash_slot(proof.nullifier_hash), U256::from(1)),
+            ]),
+        );
+        let pool = setup_with_validator(validator).await;
+
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.clone().into())
+            .await
+            .expect_err("Failed to add transaction");
+
+        assert!(err.to_string().contains("already spent in block 1"));
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_pooled_nullifier_hash() {
+        use crate::nullifier_index::{DuplicateNullifierPolicy, NullifierIndex};
+        use alloy_primitives::TxHash;
+
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+
+        // Another pooled transaction already carries the nullifier hash
+        let nullifier_index =
+            std::sync::Arc::new(NullifierIndex::new(DuplicateNullifierPolicy::FirstSeen));
+        let other = TxHash::with_last_byte(1);
+        nullifier_index.insert(other, u128::MAX, [&proof.nullifier_hash]);
+        let pool =
+            setup_with_validator(world_chain_validator().with_nullifier_index(nullifier_index))
+                .await;
+
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect_err("Failed to add transaction");
+
+        assert!(err
+            .to_string()
+            .contains(&format!("already used by pooled transaction {other}")));
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_sender_quota() {
+        use crate::quota::{PbhQuotaTracker, PbhQuotas};
+        use alloy_primitives::TxHash;
+
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+
+        // The bundler already has a PBH transaction in the pool
+        let quota_tracker = std::sync::Arc::new(PbhQuotaTracker::new(PbhQuotas {
+            max_txs_per_sender: Some(1),
+            ..Default::default()
+        }));
+        quota_tracker.insert(
+            TxHash::with_last_byte(1),
+            account(BUNDLER_ACCOUNT),
+            0,
+            vec![],
+        );
+        let pool =
+            setup_with_validator(world_chain_validator().with_quota_tracker(quota_tracker)).await;
+
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect_err("Failed to add transaction");
+
+        assert!(err
+            .to_string()
+            .contains("exceeds the quota of 1 pooled PBH transactions"));
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_with_fixed_clock() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let clock = FixedClock::new(
+            chrono::Utc
+                .with_ymd_and_hms(2025, 1, 31, 23, 59, 59)
+                .unwrap(),
+        );
+        let pool = setup_with_validator(world_chain_validator().with_clock(clock)).await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::v1(1, 2025, 0))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        pool.add_external_transaction(tx.clone().into())
+            .await
+            .expect("Failed to add transaction");
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_within_grace_period() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let clock = FixedClock::new(chrono::Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 30).unwrap());
+        let pool = setup_with_validator(
+            world_chain_validator()
+                .with_clock(clock)
+                .with_pbh_grace_period(Duration::from_secs(60)),
+        )
+        .await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::v1(1, 2025, 0))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        pool.add_external_transaction(tx.clone().into())
+            .await
+            .expect("Failed to add transaction");
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_after_grace_period() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let clock = FixedClock::new(chrono::Utc.with_ymd_and_hms(2025, 2, 1, 0, 1, 1).unwrap());
+        let pool = setup_with_validator(
+            world_chain_validator()
+                .with_clock(clock)
+                .with_pbh_grace_period(Duration::from_secs(60)),
+        )
+        .await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::v1(1, 2025, 0))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.clone().into())
+            .await
+            .expect_err("Validation should fail because the grace period has elapsed");
+        assert!(err
+            .to_string()
+            .contains("Invalid external nullifier period"),);
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_external_nullifier_v2() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let now = chrono::Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::v2(Period::Weekly, now.date_naive(), 0))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let tx = eip1559()
+            .to(PBH_DEV_ENTRYPOINT)
+            .input(bundle.abi_encode())
+            .call();
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        // V2 external nullifiers are rejected until enabled
+        let pool =
+            setup_with_validator(world_chain_validator().with_clock(FixedClock::new(now))).await;
+        let err = pool
+            .add_external_transaction(tx.clone().into())
+            .await
+            .expect_err("Validation should fail because V2 external nullifiers are disabled");
+        assert!(err
+            .to_string()
+            .contains("Unsupported external nullifier version"));
+
+        let pool = setup_with_validator(
+            world_chain_validator()
+                .with_clock(FixedClock::new(now))
+                .with_external_nullifier_v2(true),
+        )
+        .await;
+        pool.add_external_transaction(tx.into())
+            .await
+            .expect("Failed to add transaction");
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_caches_verified_proofs() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let clock = FixedClock::new(chrono::Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
+        let validator = world_chain_validator().with_clock(clock);
+        let pool = setup_with_validator(validator.clone()).await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::v1(1, 2025, 0))
+            .call();
+        let bundle = pbh_bundle(vec![user_op], vec![proof.into()]);
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        pool.add_external_transaction(tx.clone().into())
+            .await
+            .expect("Failed to add transaction");
+        assert_eq!(validator.proof_cache.len(), 1);
+
+        // The proof is cached, but the external nullifier period must still be checked
+        let clock = FixedClock::new(chrono::Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap());
+        let pool = setup_with_validator(validator.with_clock(clock)).await;
+
+        let err = pool
+            .add_external_transaction(tx.into())
+            .await
+            .expect_err("Validation should fail because the period has expired");
+        assert!(err
+            .to_string()
+            .contains("Invalid external nullifier period"));
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_duplicate_nullifier_hash() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let pool = setup().await;
+
+        let (user_op, proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+
+        // Lets add two of the same userOp in the bundle so the nullifier hash is the same and we should expect an error
+        let bundle = pbh_bundle(
+            vec![user_op.clone(), user_op],
+            vec![proof.clone().into(), proof.into()],
+        );
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+
+        let tx = eth_tx(BUNDLER_ACCOUNT, tx).await;
+
+        let res = pool
+            .add_external_transaction(tx.clone().into())
+            .await
+            .expect_err("Failed to add transaction");
+
+        assert!(res
+            .to_string()
+            .contains("PBH payload 1: Duplicate nullifier hash"));
+    }
+
+    #[tokio::test]
+    async fn validate_bundle_no_pbh() {
+        const USER_ACCOUNT: u32 = 0;
+
+        let pool = setup().await;
+
+        // NOTE: We're ignoring the proof here
+        let (user_op, _proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+
+        let bundle = pbh_bundle(vec![user_op], vec![]);
+
+        let calldata = bundle.abi_encode();
+
+        let tx = eip1559().to(Address::random()).input(calldata).call();
+
+        let tx = eth_tx(USER_ACCOUNT, tx).await;
+
+        pool.add_external_transaction(tx.clone().into())
+            .await
+            .expect(
+                "Validation should succeed - PBH data is invalid, but this is not a PBH bundle",
+            );
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_bundle_missing_proof_for_user_op() {
+        const BUNDLER_ACCOUNT: u32 = 9;
+        const USER_ACCOUNT: u32 = 0;
+
+        let pool = setup().await;
+
+        // NOTE: We're ignoring the proof here
+        let (user_op, _proof) = user_op()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+
+        let bundle = pbh_bundle(vec![user_op], vec![]);
 
         let calldata = bundle.abi_encode();
 
@@ -587,6 +1524,72 @@ pub mod tests {
         pool.add_external_transaction(tx.clone().into())
             .await
             .expect("Failed to add PBH multicall transaction");
+
+        // The transaction is marked for priority inclusion
+        let pooled = pool.get(tx.hash()).expect("transaction is pooled");
+        assert_eq!(
+            pooled
+                .transaction
+                .pbh_payload()
+                .map(|payloads| payloads.len()),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_multicall_entrypoint_target() {
+        const USER_ACCOUNT: u32 = 1;
+
+        let pool = setup().await;
+
+        let calldata = pbh_multicall()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .calls(vec![Call3 {
+                target: PBH_DEV_ENTRYPOINT,
+                ..Default::default()
+            }])
+            .call();
+        let calldata = calldata.abi_encode();
+
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+        let tx = eth_tx(USER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.clone().into())
+            .await
+            .expect_err("Validation should fail because the multicall targets the entrypoint");
+        assert!(err.to_string().contains("Invalid calldata encoding"));
+    }
+
+    #[tokio::test]
+    async fn validate_pbh_multicall_other_sender() {
+        const USER_ACCOUNT: u32 = 1;
+        const OTHER_ACCOUNT: u32 = 2;
+
+        let pool = setup().await;
+
+        let calldata = pbh_multicall()
+            .acc(USER_ACCOUNT)
+            .external_nullifier(ExternalNullifier::with_date_marker(
+                DateMarker::from(chrono::Utc::now()),
+                0,
+            ))
+            .call();
+        let calldata = calldata.abi_encode();
+
+        // The proof is bound to the sender, so it can not be replayed by another account
+        let tx = eip1559().to(PBH_DEV_ENTRYPOINT).input(calldata).call();
+        let tx = eth_tx(OTHER_ACCOUNT, tx).await;
+
+        let err = pool
+            .add_external_transaction(tx.clone().into())
+            .await
+            .expect_err("Validation should fail because of an invalid proof");
+        assert!(err.to_string().contains("PBH payload 0: Invalid proof"));
     }
 
     #[tokio::test]
@@ -10,3 +10,6 @@ pub use transactions::EthTransactionsExt;
 
 pub mod core;
 pub use core::{EthApiExtServer, WorldChainEthApiExt};
+
+pub mod pbh;
+pub use pbh::{PbhApiServer, WorldChainPbhApi};
@@ -0,0 +1,234 @@
+use alloy_consensus::BlockHeader;
+use alloy_eips::BlockId;
+use alloy_primitives::U256;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::{ErrorCode, ErrorObject},
+};
+use reth_provider::{BlockReaderIdExt, StateProviderBox, StateProviderFactory};
+use world_chain_pool::{
+    entrypoint::PbhEntrypoints, nullifier::unspent_nullifier_hashes, validator::pbh_nonce_limit,
+};
+
+/// The maximum number of nullifier hashes accepted by a single
+/// `pbh_getUnspentNullifierHashes` request.
+pub const MAX_NULLIFIER_HASHES: usize = 1024;
+
+/// WorldChain API for inspecting the PBH nonces consumed by World IDs.
+///
+/// Nullifier hashes can only be derived from the secret of a World ID, so clients compute the
+/// nullifier hashes of every nonce of the current period with
+/// `world_chain_pbh::nonce::nullifier_hashes` and use this API to find the ones which are
+/// still available.
+#[derive(Clone, Debug)]
+pub struct WorldChainPbhApi<Client> {
+    client: Client,
+    pbh_entrypoints: PbhEntrypoints,
+}
+
+impl<Client> WorldChainPbhApi<Client> {
+    pub fn new(client: Client, pbh_entrypoints: PbhEntrypoints) -> Self {
+        Self {
+            client,
+            pbh_entrypoints,
+        }
+    }
+}
+
+#[cfg_attr(not(test), rpc(server, namespace = "pbh"))]
+#[cfg_attr(test, rpc(server, client, namespace = "pbh"))]
+#[async_trait]
+pub trait PbhApi {
+    /// Returns the maximum number of PBH transactions a single World ID can execute in a
+    /// period on the most recently activated PBHEntryPoint, or zero if none is active.
+    #[method(name = "nonceLimit")]
+    async fn nonce_limit(&self) -> RpcResult<u16>;
+
+    /// Returns the nullifier hashes which have not been spent on any of the PBHEntryPoints,
+    /// preserving their order.
+    ///
+    /// Equivalent to `getUnspentNullifierHashes` on every PBHEntryPoint.
+    #[method(name = "getUnspentNullifierHashes")]
+    async fn get_unspent_nullifier_hashes(
+        &self,
+        nullifier_hashes: Vec<U256>,
+    ) -> RpcResult<Vec<U256>>;
+}
+
+#[async_trait]
+impl<Client> PbhApiServer for WorldChainPbhApi<Client>
+where
+    Client: StateProviderFactory + BlockReaderIdExt + 'static,
+{
+    async fn nonce_limit(&self) -> RpcResult<u16> {
+        let timestamp = self
+            .client
+            .latest_header()
+            .map_err(internal_error)?
+            .map_or(0, |header| header.timestamp());
+        let Some(pbh_entrypoint) = self.pbh_entrypoints.latest(timestamp) else {
+            return Ok(0);
+        };
+
+        pbh_nonce_limit(&self.latest_state()?, pbh_entrypoint.entrypoint).map_err(internal_error)
+    }
+
+    async fn get_unspent_nullifier_hashes(
+        &self,
+        nullifier_hashes: Vec<U256>,
+    ) -> RpcResult<Vec<U256>> {
+        if nullifier_hashes.len() > MAX_NULLIFIER_HASHES {
+            return Err(ErrorObject::owned(
+                ErrorCode::InvalidParams.code(),
+                format!("too many nullifier hashes, the maximum is {MAX_NULLIFIER_HASHES}"),
+                None::<()>,
+            ));
+        }
+
+        unspent_nullifier_hashes(
+            &self.latest_state()?,
+            &self.pbh_entrypoints,
+            &nullifier_hashes,
+        )
+        .map_err(internal_error)
+    }
+}
+
+impl<Client> WorldChainPbhApi<Client>
+where
+    Client: StateProviderFactory,
+{
+    fn latest_state(&self) -> RpcResult<StateProviderBox> {
+        self.client
+            .state_by_block_id(BlockId::latest())
+            .map_err(internal_error)
+    }
+}
+
+fn internal_error(err: impl std::fmt::Display) -> ErrorObject<'static> {
+    ErrorObject::owned(ErrorCode::InternalError.code(), err.to_string(), Some(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{Block, Header};
+    use alloy_primitives::{Address, B256};
+    use jsonrpsee::{
+        core::ClientError,
+        http_client::{HttpClient, HttpClientBuilder},
+        server::{Server, ServerHandle},
+    };
+    use world_chain_pool::{
+        entrypoint::PbhEntrypoint,
+        nullifier::nullifier_hash_slot,
+        validator::{PBH_NONCE_LIMIT_OFFSET, PBH_NONCE_LIMIT_SLOT},
+    };
+    use world_chain_test::mock::{ExtendedAccount, MockEthProvider};
+
+    use super::*;
+
+    const ENTRYPOINT: Address = Address::repeat_byte(0x01);
+    const NEXT_ENTRYPOINT: Address = Address::repeat_byte(0x02);
+    const TIMESTAMP: u64 = 1_000;
+
+    /// Serves the `pbh` namespace at block 1, where `ENTRYPOINT` and `NEXT_ENTRYPOINT` have a
+    /// nonce limit of 30 and 60, and nullifier hash 2 and 3 were spent on them respectively.
+    async fn serve(pbh_entrypoints: PbhEntrypoints) -> (HttpClient, ServerHandle) {
+        let client = MockEthProvider::default();
+        client.add_block(
+            B256::with_last_byte(1),
+            Block {
+                header: Header {
+                    number: 1,
+                    timestamp: TIMESTAMP,
+                    ..Default::default()
+                },
+                body: Default::default(),
+            },
+        );
+        client.add_account(
+            ENTRYPOINT,
+            ExtendedAccount::new(0, U256::ZERO).extend_storage([
+                (
+                    PBH_NONCE_LIMIT_SLOT.into(),
+                    U256::from(30) << PBH_NONCE_LIMIT_OFFSET,
+                ),
+                (nullifier_hash_slot(U256::from(2)), U256::from(1)),
+            ]),
+        );
+        client.add_account(
+            NEXT_ENTRYPOINT,
+            ExtendedAccount::new(0, U256::ZERO).extend_storage([
+                (
+                    PBH_NONCE_LIMIT_SLOT.into(),
+                    U256::from(60) << PBH_NONCE_LIMIT_OFFSET,
+                ),
+                (nullifier_hash_slot(U256::from(3)), U256::from(1)),
+            ]),
+        );
+
+        let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let handle = server.start(WorldChainPbhApi::new(client, pbh_entrypoints).into_rpc());
+        (HttpClientBuilder::default().build(url).unwrap(), handle)
+    }
+
+    fn entrypoints(next_activation: u64) -> PbhEntrypoints {
+        PbhEntrypoints::new([
+            PbhEntrypoint::new(ENTRYPOINT, Address::ZERO),
+            PbhEntrypoint::new(NEXT_ENTRYPOINT, Address::ZERO)
+                .with_activation_timestamp(next_activation),
+        ])
+    }
+
+    #[tokio::test]
+    async fn nonce_limit() {
+        // The next entrypoint is not active yet
+        let (client, _server) = serve(entrypoints(TIMESTAMP + 1)).await;
+        assert_eq!(client.nonce_limit().await.unwrap(), 30);
+
+        // Once active, the limit of the next entrypoint applies
+        let (client, _server) = serve(entrypoints(TIMESTAMP)).await;
+        assert_eq!(client.nonce_limit().await.unwrap(), 60);
+
+        // Without an active entrypoint no PBH transactions can be executed
+        let inactive =
+            PbhEntrypoint::new(ENTRYPOINT, Address::ZERO).with_activation_timestamp(TIMESTAMP + 1);
+        let (client, _server) = serve(PbhEntrypoints::new([inactive])).await;
+        assert_eq!(client.nonce_limit().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn get_unspent_nullifier_hashes() {
+        let (client, _server) = serve(entrypoints(TIMESTAMP + 1)).await;
+
+        // Nullifier hashes spent on any entrypoint are filtered out, preserving the order
+        let nullifier_hashes = [4, 2, 1, 3].map(U256::from).to_vec();
+        assert_eq!(
+            client
+                .get_unspent_nullifier_hashes(nullifier_hashes)
+                .await
+                .unwrap(),
+            vec![U256::from(4), U256::from(1)]
+        );
+
+        let nullifier_hashes = vec![U256::from(5); MAX_NULLIFIER_HASHES];
+        assert_eq!(
+            client
+                .get_unspent_nullifier_hashes(nullifier_hashes.clone())
+                .await
+                .unwrap(),
+            nullifier_hashes
+        );
+
+        let err = client
+            .get_unspent_nullifier_hashes(vec![U256::from(5); MAX_NULLIFIER_HASHES + 1])
+            .await
+            .unwrap_err();
+        let ClientError::Call(err) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(err.code(), ErrorCode::InvalidParams.code());
+    }
+}
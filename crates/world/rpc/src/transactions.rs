@@ -14,12 +14,15 @@ use reth::{
         api::eth::{AsEthApiError, FromEthApiError},
         server_types::eth::{utils::recover_raw_transaction, EthApiError},
     },
-    transaction_pool::{PoolTransaction, TransactionOrigin, TransactionPool},
+    transaction_pool::{
+        error::{InvalidPoolTransactionError, PoolError, PoolErrorKind},
+        PoolTransaction, TransactionOrigin, TransactionPool,
+    },
 };
 use reth_optimism_node::txpool::OpPooledTransaction;
 use reth_provider::{BlockReaderIdExt, StateProviderFactory};
 use revm_primitives::{map::FbBuildHasher, Address, Bytes, FixedBytes, B256};
-use world_chain_pool::tx::WorldChainPooledTransaction;
+use world_chain_pool::{error::WorldChainPoolTransactionError, tx::WorldChainPooledTransaction};
 
 use crate::{core::WorldChainEthApiExt, sequencer::SequencerClient};
 
@@ -67,7 +70,7 @@ where
             .pool()
             .add_transaction(TransactionOrigin::Local, pool_transaction)
             .await
-            .map_err(Self::Error::from_eth_err)?;
+            .map_err(pool_error)?;
 
         if let Some(client) = self.raw_tx_forwarder().as_ref() {
             tracing::debug!( target: "rpc::eth",  "forwarding raw conditional transaction to");
@@ -88,7 +91,7 @@ where
             .pool()
             .add_transaction(TransactionOrigin::Local, pool_transaction)
             .await
-            .map_err(Self::Error::from_eth_err)?;
+            .map_err(pool_error)?;
 
         if let Some(client) = self.raw_tx_forwarder().as_ref() {
             tracing::debug!( target: "rpc::eth",  "forwarding raw transaction to sequencer");
@@ -126,6 +129,50 @@ where
     }
 }
 
+/// Converts an error returned by the pool into an RPC error.
+///
+/// Transactions rejected by the World Chain validator are reported with the `-32003`
+/// (transaction rejected) code, and the error data carries the stable code of the
+/// [`WorldChainPoolTransactionError`], the index of the offending UserOp, if any, and the context
+/// of PBH validation errors.
+fn pool_error(err: PoolError) -> EthApiError {
+    if let PoolErrorKind::InvalidTransaction(InvalidPoolTransactionError::Other(other)) = &err.kind
+    {
+        if let Some(error) = other
+            .as_any()
+            .downcast_ref::<WorldChainPoolTransactionError>()
+        {
+            return EthApiError::other(ErrorObject::owned(
+                -32003,
+                error.to_string(),
+                Some(pool_error_data(error)),
+            ));
+        }
+    }
+
+    EthApiError::from_eth_err(err)
+}
+
+/// Returns the JSON-RPC error data of a transaction rejected by the World Chain validator.
+fn pool_error_data(error: &WorldChainPoolTransactionError) -> serde_json::Value {
+    let pbh_error = match error {
+        WorldChainPoolTransactionError::PBH(error) => Some(error),
+        WorldChainPoolTransactionError::PBHPayload(error) => Some(&error.error),
+        _ => None,
+    };
+
+    let mut data = pbh_error
+        .and_then(|error| serde_json::to_value(error).ok())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if let Some(fields) = data.as_object_mut() {
+        fields.insert("code".into(), error.code().into());
+        if let Some(index) = error.index() {
+            fields.insert("index".into(), index.into());
+        }
+    }
+    data
+}
+
 /// Validates the conditional inclusion options provided by the client.
 ///
 /// reference for the implementation <https://notes.ethereum.org/@yoav/SkaX2lS9j#>
@@ -229,3 +276,64 @@ where
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use world_chain_pbh::payload::{PBHBatchValidationError, PBHValidationError};
+    use world_chain_pool::simulation::SimulationError;
+
+    use super::*;
+
+    fn rejected(error: WorldChainPoolTransactionError) -> ErrorObjectOwned {
+        pool_error(PoolError {
+            hash: B256::ZERO,
+            kind: PoolErrorKind::InvalidTransaction(error.into()),
+        })
+        .into()
+    }
+
+    fn data(error: &ErrorObjectOwned) -> serde_json::Value {
+        serde_json::from_str(error.data().expect("missing error data").get()).unwrap()
+    }
+
+    #[test]
+    fn pbh_payload_error() {
+        let error = rejected(
+            PBHBatchValidationError {
+                index: 3,
+                error: PBHValidationError::InvalidExternalNullifierNonce { nonce: 5, limit: 3 },
+            }
+            .into(),
+        );
+
+        assert_eq!(error.code(), -32003);
+        assert_eq!(
+            data(&error),
+            serde_json::json!({
+                "reason": "invalid_external_nullifier_nonce",
+                "nonce": 5,
+                "limit": 3,
+                "code": 1003,
+                "index": 3,
+            })
+        );
+    }
+
+    #[test]
+    fn simulation_error() {
+        let error = rejected(
+            SimulationError::CallGasLimitExceeded {
+                index: 2,
+                gas_limit: 2,
+                limit: 1,
+            }
+            .into(),
+        );
+
+        assert_eq!(error.code(), -32003);
+        assert_eq!(
+            data(&error),
+            serde_json::json!({ "code": 1202, "index": 2 })
+        );
+    }
+}
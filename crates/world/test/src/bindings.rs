@@ -1,5 +1,5 @@
 use alloy_sol_types::sol;
-use serde::{Deserialize, Serialize};
+pub use world_chain_pbh::abi::{IEntryPoint, IMulticall3, IPBHEntryPoint};
 
 sol! {
     struct EncodedSafeOpStruct {
@@ -18,55 +18,4 @@ sol! {
         uint48 validUntil;
         address entryPoint;
     }
-
-    contract IMulticall3 {
-        #[derive(Default)]
-        struct Call3 {
-            address target;
-            bool allowFailure;
-            bytes callData;
-        }
-    }
-
-    contract IEntryPoint {
-        #[derive(Default, Serialize, Deserialize, Debug)]
-        struct PackedUserOperation {
-            address sender;
-            uint256 nonce;
-            bytes initCode;
-            bytes callData;
-            bytes32 accountGasLimits;
-            uint256 preVerificationGas;
-            bytes32 gasFees;
-            bytes paymasterAndData;
-            bytes signature;
-        }
-
-        #[derive(Default)]
-        struct UserOpsPerAggregator {
-            PackedUserOperation[] userOps;
-            address aggregator;
-            bytes signature;
-        }
-    }
-
-    contract IPBHEntryPoint {
-        #[derive(Default)]
-        struct PBHPayload {
-            uint256 root;
-            uint256 pbhExternalNullifier;
-            uint256 nullifierHash;
-            uint256[8] proof;
-        }
-
-        function handleAggregatedOps(
-            IEntryPoint.UserOpsPerAggregator[] calldata,
-            address payable
-        ) external;
-
-        function pbhMulticall(
-            IMulticall3.Call3[] calls,
-            PBHPayload payload,
-        ) external;
-    }
 }
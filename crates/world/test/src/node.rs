@@ -89,6 +89,23 @@ pub fn test_config_with_peers_and_gossip(
         entrypoint: PBH_DEV_ENTRYPOINT,
         signature_aggregator: PBH_DEV_SIGNATURE_AGGREGATOR,
         world_id: DEV_WORLD_ID,
+        grace_period: 0,
+        external_nullifier_v2: false,
+        duplicate_nullifier_policy: Default::default(),
+        root_expiration_window: None,
+        max_txs_per_sender: None,
+        max_nullifiers_per_period: None,
+        max_pool_txs: None,
+        entrypoint_signal_version: 1,
+        additional_entrypoints: vec![],
+        entrypoint_deactivation_timestamp: None,
+        simulate_bundles: false,
+        max_verification_gas: None,
+        max_call_gas: None,
+        ordering_max_gas_per_op: None,
+        ordering_prefer_larger_bundles: false,
+        ordering_age_step: None,
+        ordering_fair_nonces: None,
     };
 
     let flashblocks = FlashblocksArgs {
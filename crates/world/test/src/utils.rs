@@ -18,17 +18,18 @@ use semaphore_rs::{hash_to_field, identity::Identity, poseidon_tree::LazyPoseido
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{str::FromStr, sync::LazyLock};
 
+pub use world_chain_pbh::payload::InclusionProof;
 use world_chain_pbh::{
-    external_nullifier::{EncodedExternalNullifier, ExternalNullifier},
-    payload::{PBHPayload as PbhPayload, Proof, TREE_DEPTH},
+    abi::PBHPayload,
+    external_nullifier::ExternalNullifier,
+    payload::{PBHPayload as PbhPayload, TREE_DEPTH},
 };
 
 use crate::{
     bindings::{
         EncodedSafeOpStruct,
         IEntryPoint::{self, PackedUserOperation, UserOpsPerAggregator},
-        IMulticall3,
-        IPBHEntryPoint::{self, PBHPayload},
+        IMulticall3, IPBHEntryPoint,
     },
     DEVNET_ENTRYPOINT, DEV_CHAIN_ID, MNEMONIC, PBH_DEV_ENTRYPOINT, PBH_DEV_SIGNATURE_AGGREGATOR,
     PBH_NONCE_KEY, TEST_MODULES, TEST_SAFES, WC_SEPOLIA_CHAIN_ID,
@@ -47,12 +48,6 @@ pub static TREE: LazyLock<LazyPoseidonTree> = LazyLock::new(|| {
     tree.derived()
 });
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InclusionProof {
-    pub root: Field,
-    pub proof: semaphore_rs::poseidon_tree::Proof,
-}
-
 pub fn generate_user_op_nonce(sequence: U256, use_pbh_prefix: bool) -> U256 {
     let key = if use_pbh_prefix {
         // Build 192-bit key: top 40 bits = PBH_NONCE_KEY, remaining 152 bits random.
@@ -93,6 +88,13 @@ pub fn tree_inclusion_proof(acc: u32) -> semaphore_rs::poseidon_tree::Proof {
     TREE.proof(acc as usize)
 }
 
+pub fn inclusion_proof(acc: u32) -> InclusionProof {
+    InclusionProof {
+        root: tree_root(),
+        proof: tree_inclusion_proof(acc),
+    }
+}
+
 pub fn nullifier_hash(acc: u32, external_nullifier: Field) -> Field {
     let identity = identity(acc);
 
@@ -200,19 +202,14 @@ pub fn user_op(
         .expect("Failed to sign operation hash");
     let signal = hash_user_op(&user_op);
 
-    let root = TREE.root();
-    let encoded_external_nullifier = EncodedExternalNullifier::from(external_nullifier);
-    let proof = semaphore_proof(acc, encoded_external_nullifier.0, signal);
-    let nullifier_hash = nullifier_hash(acc, encoded_external_nullifier.0);
-
-    let proof = Proof(proof);
-
-    let payload = PbhPayload {
+    let payload = PbhPayload::generate(
+        &identity(acc),
+        &inclusion_proof(acc),
         external_nullifier,
-        nullifier_hash,
-        root,
-        proof,
-    };
+        signal,
+    )
+    .call()
+    .expect("Failed to generate PBH payload");
 
     let mut uo_sig = Vec::new();
 
@@ -303,29 +300,12 @@ pub fn user_op_sepolia(
         .expect("Failed to sign operation hash");
     let signal = hash_user_op(&user_op);
 
-    let encoded_external_nullifier = EncodedExternalNullifier::from(external_nullifier);
-
     let pbh_payload = if let (Some(identity), Some(inclusion_proof)) = (identity, inclusion_proof) {
-        let proof = semaphore_rs::protocol::generate_proof(
-            &identity,
-            &inclusion_proof.proof,
-            encoded_external_nullifier.0,
-            signal,
+        Some(
+            PbhPayload::generate(&identity, &inclusion_proof, external_nullifier, signal)
+                .call()
+                .expect("Failed to generate PBH payload"),
         )
-        .expect("Failed to generate semaphore proof");
-        let nullifier_hash = semaphore_rs::protocol::generate_nullifier_hash(
-            &identity,
-            encoded_external_nullifier.0,
-        );
-
-        let proof = Proof(proof);
-
-        Some(PbhPayload {
-            external_nullifier,
-            nullifier_hash,
-            root: inclusion_proof.root,
-            proof,
-        })
     } else {
         None
     };
@@ -376,37 +356,22 @@ pub fn pbh_bundle(
 pub fn pbh_multicall(
     acc: u32,
     #[builder(default = ExternalNullifier::v1(12, 2024, 0))] external_nullifier: ExternalNullifier,
+    #[builder(default = vec![IMulticall3::Call3::default()])] calls: Vec<IMulticall3::Call3>,
 ) -> IPBHEntryPoint::pbhMulticallCall {
     let sender = account(acc);
 
-    let call = IMulticall3::Call3::default();
-    let calls = vec![call];
-
     let signal_hash: alloy_primitives::Uint<256, 4> =
-        hash_to_field(&SolValue::abi_encode_packed(&(sender, calls.clone())));
-
-    let root = TREE.root();
-    let encoded_external_nullifier = EncodedExternalNullifier::from(external_nullifier);
-    let proof = semaphore_proof(acc, encoded_external_nullifier.0, signal_hash);
-    let nullifier_hash = nullifier_hash(acc, encoded_external_nullifier.0);
-
-    let proof = [
-        proof.0 .0,
-        proof.0 .1,
-        proof.1 .0[0],
-        proof.1 .0[1],
-        proof.1 .1[0],
-        proof.1 .1[1],
-        proof.2 .0,
-        proof.2 .1,
-    ];
-
-    let payload = IPBHEntryPoint::PBHPayload {
-        root,
-        pbhExternalNullifier: EncodedExternalNullifier::from(external_nullifier).0,
-        nullifierHash: nullifier_hash,
-        proof,
-    };
+        hash_to_field(&SolValue::abi_encode_params(&(sender, calls.clone())));
+
+    let payload = PbhPayload::generate(
+        &identity(acc),
+        &inclusion_proof(acc),
+        external_nullifier,
+        signal_hash,
+    )
+    .call()
+    .expect("Failed to generate PBH payload")
+    .into();
 
     IPBHEntryPoint::pbhMulticallCall { calls, payload }
 }
@@ -466,26 +431,6 @@ impl From<PackedUserOperation> for EncodedSafeOpStruct {
     }
 }
 
-impl From<PbhPayload> for PBHPayload {
-    fn from(val: PbhPayload) -> Self {
-        let p0 = val.proof.0 .0 .0;
-        let p1 = val.proof.0 .0 .1;
-        let p2 = val.proof.0 .1 .0[0];
-        let p3 = val.proof.0 .1 .0[1];
-        let p4 = val.proof.0 .1 .1[0];
-        let p5 = val.proof.0 .1 .1[1];
-        let p6 = val.proof.0 .2 .0;
-        let p7 = val.proof.0 .2 .1;
-
-        Self {
-            root: val.root,
-            pbhExternalNullifier: EncodedExternalNullifier::from(val.external_nullifier).0,
-            nullifierHash: val.nullifier_hash,
-            proof: [p0, p1, p2, p3, p4, p5, p6, p7],
-        }
-    }
-}
-
 #[allow(clippy::from_over_into)]
 impl Into<RpcUserOperationV0_7> for (PackedUserOperation, Option<Address>) {
     fn into(self) -> RpcUserOperationV0_7 {